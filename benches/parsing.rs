@@ -0,0 +1,120 @@
+// Parsing time of a year of menus in every text format, and of a bulk upload
+// parsed file by file or all at once, like `PendingFile::start_all` does:
+//
+//     cargo bench --bench parsing
+//
+// Menus aren't part of the sources, set `OVR_BENCH_PDF` to one to measure
+// the PDF parser and bulk uploads of PDFs too.
+
+use std::{env, fs, hint::black_box, thread};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ovr::{parse_csv, parse_ics, parse_json, parse_pdf, ParserConfig};
+use time::{macros::date, Date, Duration, Weekday};
+
+const DISHES: [&str; 5] = [
+    "Carottes râpées",
+    "Poulet rôti",
+    "Pommes de terre sautées",
+    "Yaourt nature",
+    "Fruit de saison",
+];
+const UPLOADED_FILES: usize = 10;
+
+// Weekdays from the first Monday of 2023 on.
+fn weekdays(weeks: i64) -> impl Iterator<Item = Date> {
+    (0..weeks * 7)
+        .map(|offset| date!(2023 - 01 - 02) + Duration::days(offset))
+        .filter(|date| !matches!(date.weekday(), Weekday::Saturday | Weekday::Sunday))
+}
+
+fn csv(weeks: i64) -> Vec<u8> {
+    weekdays(weeks)
+        .map(|date| format!("{date},{}\n", DISHES.join(",")))
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn json(weeks: i64) -> Vec<u8> {
+    let rows = weekdays(weeks)
+        .map(|date| {
+            [date.to_string()]
+                .into_iter()
+                .chain(DISHES.map(ToOwned::to_owned))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_vec(&rows).expect("rows serialization failed")
+}
+
+fn ics(weeks: i64) -> Vec<u8> {
+    let events = weekdays(weeks)
+        .map(|date| {
+            format!(
+                "BEGIN:VEVENT\r\nDTSTART:{}T100000Z\r\nDESCRIPTION:{}\r\nEND:VEVENT\r\n",
+                date.to_string().replace('-', ""),
+                DISHES.join("\\n")
+            )
+        })
+        .collect::<String>();
+    format!("BEGIN:VCALENDAR\r\n{events}END:VCALENDAR\r\n").into_bytes()
+}
+
+fn formats(c: &mut Criterion) {
+    let config = ParserConfig::default();
+    let mut group = c.benchmark_group("year");
+    let (csv, json, ics) = (csv(52), json(52), ics(52));
+    group.throughput(Throughput::Elements(weekdays(52).count() as u64));
+    group.bench_function("csv", |b| b.iter(|| parse_csv(black_box(&csv), &config)));
+    group.bench_function("json", |b| b.iter(|| parse_json(black_box(&json), &config)));
+    group.bench_function("ics", |b| b.iter(|| parse_ics(black_box(&ics), &config)));
+    group.finish();
+
+    if let Some(pdf) = pdf() {
+        c.bench_function("pdf", |b| b.iter(|| parse_pdf(black_box(&pdf), &config)));
+    }
+}
+
+fn upload(c: &mut Criterion) {
+    let config = &ParserConfig::default();
+    let (files, parse): (Vec<_>, fn(&[u8], &ParserConfig) -> Option<usize>) = match pdf() {
+        Some(pdf) => (vec![pdf; UPLOADED_FILES], |data, config| {
+            parse_pdf(data, config).ok().map(|days| days.len())
+        }),
+        None => (vec![csv(1); UPLOADED_FILES], |data, config| {
+            parse_csv(data, config).ok().map(|days| days.len())
+        }),
+    };
+    let mut group = c.benchmark_group("upload");
+    group.throughput(Throughput::Elements(UPLOADED_FILES as u64));
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            files
+                .iter()
+                .map(|file| parse(black_box(file), config))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            thread::scope(|scope| {
+                files
+                    .iter()
+                    .map(|file| scope.spawn(move || parse(black_box(file), config)))
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .map(|parsing| parsing.join().expect("parsing panicked"))
+                    .collect::<Vec<_>>()
+            })
+        })
+    });
+    group.finish();
+}
+
+fn pdf() -> Option<Vec<u8>> {
+    let path = env::var_os("OVR_BENCH_PDF")?;
+    Some(fs::read(&path).expect("failed to read OVR_BENCH_PDF"))
+}
+
+criterion_group!(benches, formats, upload);
+criterion_main!(benches);
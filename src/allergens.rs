@@ -0,0 +1,225 @@
+use std::{collections::HashMap, str::FromStr, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+static KEYWORDS: OnceLock<Vec<(Allergen, Vec<String>)>> = OnceLock::new();
+
+// The fourteen allergens that must be labelled in the EU.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Allergen {
+    Gluten,
+    Crustaceans,
+    Eggs,
+    Fish,
+    Peanuts,
+    Soy,
+    Lactose,
+    Nuts,
+    Celery,
+    Mustard,
+    Sesame,
+    Sulphites,
+    Lupin,
+    Molluscs,
+}
+
+impl Allergen {
+    const ALL: [Allergen; 14] = [
+        Allergen::Gluten,
+        Allergen::Crustaceans,
+        Allergen::Eggs,
+        Allergen::Fish,
+        Allergen::Peanuts,
+        Allergen::Soy,
+        Allergen::Lactose,
+        Allergen::Nuts,
+        Allergen::Celery,
+        Allergen::Mustard,
+        Allergen::Sesame,
+        Allergen::Sulphites,
+        Allergen::Lupin,
+        Allergen::Molluscs,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Allergen::Gluten => "gluten",
+            Allergen::Crustaceans => "crustaceans",
+            Allergen::Eggs => "eggs",
+            Allergen::Fish => "fish",
+            Allergen::Peanuts => "peanuts",
+            Allergen::Soy => "soy",
+            Allergen::Lactose => "lactose",
+            Allergen::Nuts => "nuts",
+            Allergen::Celery => "celery",
+            Allergen::Mustard => "mustard",
+            Allergen::Sesame => "sesame",
+            Allergen::Sulphites => "sulphites",
+            Allergen::Lupin => "lupin",
+            Allergen::Molluscs => "molluscs",
+        }
+    }
+
+    pub fn as_fr_str(&self) -> &'static str {
+        match self {
+            Allergen::Gluten => "Gluten",
+            Allergen::Crustaceans => "Crustacés",
+            Allergen::Eggs => "Œufs",
+            Allergen::Fish => "Poisson",
+            Allergen::Peanuts => "Arachides",
+            Allergen::Soy => "Soja",
+            Allergen::Lactose => "Lactose",
+            Allergen::Nuts => "Fruits à coque",
+            Allergen::Celery => "Céleri",
+            Allergen::Mustard => "Moutarde",
+            Allergen::Sesame => "Sésame",
+            Allergen::Sulphites => "Sulfites",
+            Allergen::Lupin => "Lupin",
+            Allergen::Molluscs => "Mollusques",
+        }
+    }
+
+    fn builtin_keywords(&self) -> &'static [&'static str] {
+        match self {
+            Allergen::Gluten => &[
+                "pâte",
+                "pain",
+                "blé",
+                "farine",
+                "semoule",
+                "couscous",
+                "pizza",
+                "quiche",
+                "tarte",
+                "lasagne",
+                "gâteau",
+                "crêpe",
+                "pané",
+                "biscuit",
+                "brioche",
+                "croque",
+                "spaghetti",
+                "tagliatelle",
+                "penne",
+                "macaroni",
+                "raviole",
+                "cake",
+                "gaufre",
+                "beignet",
+            ],
+            Allergen::Crustaceans => &["crevette", "crabe", "homard", "langoustine", "écrevisse"],
+            Allergen::Eggs => &["œuf", "oeuf", "omelette", "mayonnaise", "quiche", "flan"],
+            Allergen::Fish => &[
+                "poisson",
+                "saumon",
+                "thon",
+                "cabillaud",
+                "colin",
+                "merlu",
+                "sardine",
+                "maquereau",
+                "truite",
+                "hoki",
+                "limande",
+                "lieu",
+                "surimi",
+                "brandade",
+            ],
+            Allergen::Peanuts => &["arachide", "cacahuète"],
+            Allergen::Soy => &["soja", "tofu"],
+            Allergen::Lactose => &[
+                "fromage",
+                "lait",
+                "crème",
+                "beurre",
+                "yaourt",
+                "emmental",
+                "gruyère",
+                "camembert",
+                "brie",
+                "mozzarella",
+                "parmesan",
+                "béchamel",
+                "gratin",
+                "comté",
+                "chèvre",
+                "raclette",
+                "tartiflette",
+                "flan",
+            ],
+            Allergen::Nuts => &["noix", "noisette", "amande", "pistache", "cajou"],
+            Allergen::Celery => &["céleri"],
+            Allergen::Mustard => &["moutarde", "dijonnaise"],
+            Allergen::Sesame => &["sésame"],
+            Allergen::Sulphites => &["vin"],
+            Allergen::Lupin => &["lupin"],
+            Allergen::Molluscs => &["moule", "calamar", "encornet", "seiche", "poulpe", "huître"],
+        }
+    }
+}
+
+impl FromStr for Allergen {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Allergen::ALL
+            .into_iter()
+            .find(|allergen| allergen.as_str() == s.trim())
+            .ok_or(Error::InvalidAllergen)
+    }
+}
+
+// Must be called before the first detection, later calls fail. `extra`
+// keywords are added to the builtin ones.
+pub fn configure(extra: &HashMap<Allergen, Vec<String>>) -> Result<(), String> {
+    KEYWORDS
+        .set(keywords(extra))
+        .map_err(|_| "allergens already configured".to_owned())
+}
+
+fn keywords(extra: &HashMap<Allergen, Vec<String>>) -> Vec<(Allergen, Vec<String>)> {
+    Allergen::ALL
+        .into_iter()
+        .map(|allergen| {
+            let keywords = allergen
+                .builtin_keywords()
+                .iter()
+                .map(|keyword| (*keyword).to_owned())
+                .chain(
+                    extra
+                        .get(&allergen)
+                        .into_iter()
+                        .flatten()
+                        .map(|keyword| keyword.to_lowercase()),
+                )
+                .collect();
+            (allergen, keywords)
+        })
+        .collect()
+}
+
+// Keywords match whole words, plurals included, so "lait" doesn't match
+// "laitue".
+pub fn detect(dish: &str) -> Vec<Allergen> {
+    let dish = dish.to_lowercase();
+    let words = dish
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>();
+    KEYWORDS
+        .get_or_init(|| keywords(&HashMap::new()))
+        .iter()
+        .filter(|(_, keywords)| {
+            keywords.iter().any(|keyword| {
+                words.iter().any(|word| {
+                    word.strip_prefix(keyword.as_str())
+                        .is_some_and(|rest| matches!(rest, "" | "s" | "x" | "e" | "es"))
+                })
+            })
+        })
+        .map(|(allergen, _)| *allergen)
+        .collect()
+}
@@ -0,0 +1,47 @@
+(async () => {
+    if (!('serviceWorker' in navigator)) {
+        return;
+    }
+    const registration = await navigator.serviceWorker.register('/sw.js');
+    if (!('PushManager' in window)) {
+        return;
+    }
+    const response = await fetch('/push/key', { headers: { Accept: 'application/json' } });
+    if (!response.ok) {
+        return;
+    }
+    const { public_key } = await response.json();
+    const site = location.pathname.match(/^\/sites\/([^/]+)/)?.[1];
+
+    const button = document.createElement('button');
+    button.className = 'push';
+    const refresh = async () => {
+        button.textContent = await registration.pushManager.getSubscription()
+            ? 'Désactiver les notifications'
+            : 'Recevoir le menu chaque matin';
+    };
+    button.addEventListener('click', async () => {
+        const existing = await registration.pushManager.getSubscription();
+        if (existing) {
+            await fetch('/push/subscriptions', {
+                method: 'DELETE',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({ endpoint: existing.endpoint }),
+            });
+            await existing.unsubscribe();
+        } else {
+            const subscription = await registration.pushManager.subscribe({
+                userVisibleOnly: true,
+                applicationServerKey: public_key,
+            });
+            await fetch('/push/subscriptions', {
+                method: 'POST',
+                headers: { 'Content-Type': 'application/json' },
+                body: JSON.stringify({ ...subscription.toJSON(), site }),
+            });
+        }
+        await refresh();
+    });
+    await refresh();
+    document.body.appendChild(button);
+})();
@@ -0,0 +1,211 @@
+use std::{
+    convert::Infallible,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::request::Parts,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+
+use crate::{
+    auth::Caller,
+    catalogue::CatalogueUpdate,
+    config::Config,
+    error::Error,
+    proxy::client_ip,
+    response::TextRepresentable,
+    utils::{format_date, now_local},
+};
+
+#[derive(Debug)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+    file: Option<File>,
+}
+
+impl AuditLog {
+    // Previous entries are read back so the log survives restarts.
+    pub fn open(path: Option<&Path>) -> Result<Self, String> {
+        let Some(path) = path else {
+            return Ok(Self {
+                entries: Vec::new(),
+                file: None,
+            });
+        };
+        let entries = match fs::read_to_string(path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| format!("invalid audit log: {err}"))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.to_string()),
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            entries,
+            file: Some(file),
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        source: &AuditSource,
+        action: AuditAction,
+        site: Option<&str>,
+        file: Option<&str>,
+        result: Result<&CatalogueUpdate, &Error>,
+    ) {
+        let entry = AuditEntry {
+            timestamp: now_local()
+                .format(&Rfc3339)
+                .expect("audit date formatting failed"),
+            source: source.0.clone(),
+            action,
+            site: site.map(ToOwned::to_owned),
+            file: file.map(ToOwned::to_owned),
+            dates: result
+                .ok()
+                .map(|update| update.dates().into_iter().map(format_date).collect())
+                .unwrap_or_default(),
+            error: result.err().map(ToString::to_string),
+        };
+        if let Some(file) = &mut self.file {
+            let line = serde_json::to_string(&entry).expect("audit entry serialization failed");
+            if let Err(err) = writeln!(file, "{line}") {
+                eprintln!("failed to write audit log: {err}");
+            }
+        }
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> AuditEntries {
+        AuditEntries {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditEntry {
+    timestamp: String,
+    source: String,
+    action: AuditAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    site: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(default)]
+    dates: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Upload,
+    Import,
+    Undo,
+    Edit,
+    Replicate,
+    Reparse,
+    Fetch,
+    Delete,
+}
+
+impl AuditAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Upload => "upload",
+            AuditAction::Import => "import",
+            AuditAction::Undo => "undo",
+            AuditAction::Edit => "edit",
+            AuditAction::Replicate => "replicate",
+            AuditAction::Reparse => "reparse",
+            AuditAction::Fetch => "fetch",
+            AuditAction::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct AuditEntries {
+    entries: Vec<AuditEntry>,
+}
+
+impl TextRepresentable for AuditEntries {
+    fn as_plain_text(&self, _human: bool) -> String {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let mut line = format!(
+                    "{} {} {}",
+                    entry.timestamp,
+                    entry.source,
+                    entry.action.as_str()
+                );
+                if let Some(site) = &entry.site {
+                    line += &format!(" [{site}]");
+                }
+                if let Some(file) = &entry.file {
+                    line += &format!(" {file}");
+                }
+                if let Some(error) = &entry.error {
+                    line += &format!(": {error}");
+                } else if !entry.dates.is_empty() {
+                    line += &format!(": {}", entry.dates.iter().join(", "));
+                }
+                line
+            })
+            .join("\n")
+    }
+}
+
+// Who made a change, as recorded in the audit log.
+#[derive(Clone, Debug)]
+pub struct AuditSource(String);
+
+impl AuditSource {
+    // For changes not made by a request.
+    pub fn new(source: String) -> Self {
+        Self(source)
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuditSource
+where
+    S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+        let address = client_ip(&parts.headers, &parts.extensions, &config)
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| "unknown".to_owned());
+        let key = parts
+            .extensions
+            .get::<Caller>()
+            .and_then(|caller| caller.key.as_deref());
+        Ok(Self(match key {
+            Some(key) => format!("{key}@{address}"),
+            None => address,
+        }))
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, Method, Request},
+    middleware::Next,
+    response::Response,
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::{
+    config::Config, error::Error, proxy::client_ip, response::ErrorFormat, utils::now_local,
+};
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+const SESSION_COOKIE: &str = "ovr_session";
+// A working day, admins logging in again the next morning.
+const SESSION_LIFETIME: Duration = Duration::from_secs(12 * 60 * 60);
+
+#[derive(Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Read,
+    Upload,
+    // Implies the other scopes.
+    Admin,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ApiKeyConfig {
+    // Recorded in the audit log instead of the key itself.
+    pub name: String,
+    pub key: String,
+    pub scopes: Vec<Scope>,
+    // Requests per minute, the global `rate_limit` by default.
+    pub rate_limit: Option<u32>,
+}
+
+impl ApiKeyConfig {
+    fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&Scope::Admin) || self.scopes.contains(&scope)
+    }
+}
+
+// Sent as a bearer token or in `X-Api-Key`. Unknown bearer tokens, like
+// profile tokens, are not keys.
+fn api_key<'a>(headers: &HeaderMap, config: &'a Config) -> Option<&'a ApiKeyConfig> {
+    let provided = headers
+        .get("x-api-key")
+        .and_then(|h| h.to_str().ok())
+        .or_else(|| bearer_token(headers))?;
+    config
+        .api_keys
+        .iter()
+        .find(|key| constant_time_eq(&key.key, provided))
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+fn reject(headers: &HeaderMap, err: Error) -> Response {
+    ErrorFormat::loose(headers).respond(err)
+}
+
+// The API key used by a request, added to its extensions by `guard`.
+#[derive(Clone, Debug)]
+pub struct Caller {
+    pub key: Option<String>,
+}
+
+// Counts requests per key, or per client address without one, in windows of
+// a minute.
+#[derive(Default, Debug)]
+pub struct RateLimiter {
+    windows: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    // The time left before the next request is allowed, if `client` went over
+    // `limit`.
+    fn hit(&self, client: String, limit: u32) -> Option<Duration> {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().expect("rate limiter poisoned");
+        windows.retain(|_, (start, _)| now.duration_since(*start) < RATE_LIMIT_WINDOW);
+        let (start, count) = windows.entry(client).or_insert((now, 0));
+        *count += 1;
+        (*count > limit).then(|| RATE_LIMIT_WINDOW - now.duration_since(*start))
+    }
+}
+
+// Applied to every request: rate limits, and the `read` scope when
+// `read_requires_key` is set.
+pub async fn guard<B>(
+    State(config): State<Arc<Config>>,
+    State(limiter): State<Arc<RateLimiter>>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let key = api_key(request.headers(), &config);
+    if config.read_requires_key
+        && matches!(*request.method(), Method::GET | Method::HEAD)
+        && !key.is_some_and(|key| key.allows(Scope::Read))
+        && !is_admin(request.headers(), &config)
+    {
+        return reject(request.headers(), Error::Unauthorized);
+    }
+
+    let limit = key.and_then(|key| key.rate_limit).or(config.rate_limit);
+    if let Some(limit) = limit {
+        let client = match key {
+            Some(key) => format!("key {}", key.name),
+            None => client_ip(request.headers(), request.extensions(), &config)
+                .map(|ip| ip.to_string())
+                .unwrap_or_default(),
+        };
+        if let Some(retry_after) = limiter.hit(client, limit) {
+            let mut response = reject(request.headers(), Error::TooManyRequests);
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(retry_after.as_secs().max(1)),
+            );
+            return response;
+        }
+    }
+
+    request.extensions_mut().insert(Caller {
+        key: key.map(|key| key.name.clone()),
+    });
+    next.run(request).await
+}
+
+fn is_admin_token(headers: &HeaderMap, config: &Config) -> bool {
+    config
+        .admin_token
+        .as_deref()
+        .is_some_and(|token| {
+            bearer_token(headers).is_some_and(|provided| constant_time_eq(token, provided))
+        })
+}
+
+// Whether the request would pass `Admin`, to show admins their controls.
+pub fn is_admin(headers: &HeaderMap, config: &Config) -> bool {
+    is_admin_token(headers, config)
+        || api_key(headers, config).is_some_and(|key| key.allows(Scope::Admin))
+        || has_session(headers, config)
+}
+
+// The `admin_token` and the keys with the `admin` scope, which can open a
+// session.
+fn admin_credentials(config: &Config) -> impl Iterator<Item = &str> {
+    config.admin_token.as_deref().into_iter().chain(
+        config
+            .api_keys
+            .iter()
+            .filter(|key| key.allows(Scope::Admin))
+            .map(|key| key.key.as_str()),
+    )
+}
+
+// The session cookie of `/login`, for the `admin_token` or a key with the
+// `admin` scope. Only sent over HTTPS when `secure`.
+pub fn login(token: &str, config: &Config, secure: bool) -> Result<HeaderValue, Error> {
+    let secret = config
+        .session_secret
+        .as_deref()
+        .ok_or(Error::SessionsDisabled)?;
+    let credential = admin_credentials(config)
+        .find(|credential| constant_time_eq(credential, token))
+        .ok_or(Error::Unauthorized)?;
+    let expiry = now_local().unix_timestamp() + SESSION_LIFETIME.as_secs() as i64;
+    session_cookie(
+        &format!("{expiry}.{}", session_signature(secret, credential, expiry)),
+        SESSION_LIFETIME.as_secs(),
+        secure,
+    )
+}
+
+// Expires the session cookie of `/login`.
+pub fn logout(secure: bool) -> Result<HeaderValue, Error> {
+    session_cookie("", 0, secure)
+}
+
+fn session_cookie(value: &str, max_age: u64, secure: bool) -> Result<HeaderValue, Error> {
+    let cookie = format!(
+        "{SESSION_COOKIE}={value}; Max-Age={max_age}; Path=/; HttpOnly; SameSite=Strict{}",
+        if secure { "; Secure" } else { "" },
+    );
+    HeaderValue::from_str(&cookie).map_err(|_| Error::Internal)
+}
+
+// Like "1686571200.3f1c…", the expiry and its hex HMAC-SHA256, also covering
+// the credential used to log in so that sessions end with it.
+fn session_signature(secret: &str, credential: &str, expiry: i64) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("invalid hmac key length");
+    mac.update(credential.as_bytes());
+    mac.update(b".");
+    mac.update(expiry.to_string().as_bytes());
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn has_session(headers: &HeaderMap, config: &Config) -> bool {
+    let Some(secret) = config.session_secret.as_deref() else {
+        return false;
+    };
+    headers
+        .get_all(header::COOKIE)
+        .into_iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='))
+        .any(|session| {
+            let Some((expiry, signature)) = session.split_once('.') else {
+                return false;
+            };
+            let Ok(expiry) = expiry.parse::<i64>() else {
+                return false;
+            };
+            expiry > now_local().unix_timestamp()
+                && admin_credentials(config).any(|credential| {
+                    constant_time_eq(&session_signature(secret, credential, expiry), signature)
+                })
+        })
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+// Guards endpoints that can destroy data, using the `admin_token` from the
+// configuration as a bearer token, a key with the `admin` scope, or the
+// session cookie of `/login`.
+pub struct Admin;
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Admin
+where
+    S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+        if config.admin_token.is_none()
+            && !config.api_keys.iter().any(|key| key.allows(Scope::Admin))
+        {
+            return Err(reject(&parts.headers, Error::AdminDisabled));
+        }
+        if !is_admin(&parts.headers, &config) {
+            return Err(reject(&parts.headers, Error::Unauthorized));
+        }
+        Ok(Admin)
+    }
+}
+
+// Guards uploads once API keys are configured, which then need the `upload`
+// scope or the admin token. They stay open otherwise.
+pub struct Uploader;
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Uploader
+where
+    S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+        if config.api_keys.is_empty()
+            || is_admin(&parts.headers, &config)
+            || api_key(&parts.headers, &config).is_some_and(|key| key.allows(Scope::Upload))
+        {
+            Ok(Uploader)
+        } else {
+            Err(reject(&parts.headers, Error::Unauthorized))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(admin_token: &str) -> Config {
+        Config {
+            admin_token: Some(admin_token.to_owned()),
+            session_secret: Some("secret".to_owned()),
+            ..Config::default()
+        }
+    }
+
+    // The `Cookie` header sent back by a browser after `login`.
+    fn session(config: &Config, token: &str) -> HeaderMap {
+        let cookie = login(token, config, false).expect("login failed");
+        let session = cookie.to_str().unwrap().split(';').next().unwrap().to_owned();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::COOKIE, HeaderValue::from_str(&session).unwrap());
+        headers
+    }
+
+    #[test]
+    fn login_checks_the_token() {
+        assert!(matches!(
+            login("wrong", &config("token"), false),
+            Err(Error::Unauthorized)
+        ));
+    }
+
+    #[test]
+    fn sessions_end_with_their_credential() {
+        let headers = session(&config("token"), "token");
+        assert!(has_session(&headers, &config("token")));
+        assert!(!has_session(&headers, &config("rotated")));
+    }
+
+    #[test]
+    fn logout_expires_the_cookie() {
+        let cookie = logout(false).unwrap();
+        assert!(cookie.to_str().unwrap().starts_with("ovr_session=; Max-Age=0;"));
+    }
+}
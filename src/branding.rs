@@ -0,0 +1,62 @@
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::utils::escape;
+
+static BRANDING: OnceLock<BrandingConfig> = OnceLock::new();
+
+// Tells the HTML pages of instances apart, nothing shown by default.
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct BrandingConfig {
+    // The page title and header, "Menu" otherwise.
+    pub site_name: Option<String>,
+    // Shown above the menus, next to the name.
+    pub logo_url: Option<String>,
+    pub footer: Option<String>,
+}
+
+// Must be called before the first page is rendered, later calls fail.
+pub fn configure(config: BrandingConfig) -> Result<(), String> {
+    BRANDING
+        .set(config)
+        .map_err(|_| "branding already configured".to_owned())
+}
+
+fn branding() -> &'static BrandingConfig {
+    BRANDING.get_or_init(BrandingConfig::default)
+}
+
+pub fn title() -> String {
+    escape(branding().site_name.as_deref().unwrap_or("Menu"))
+}
+
+// The logo and name, empty without them.
+pub fn header() -> String {
+    let branding = branding();
+    if branding.site_name.is_none() && branding.logo_url.is_none() {
+        return String::new();
+    }
+    format!(
+        r#"<header class="branding">{}{}</header>"#,
+        branding
+            .logo_url
+            .as_deref()
+            .map(|url| format!(r#"<img src="{}" alt="">"#, escape(url)))
+            .unwrap_or_default(),
+        branding
+            .site_name
+            .as_deref()
+            .map(|name| format!("<span>{}</span>", escape(name)))
+            .unwrap_or_default(),
+    )
+}
+
+pub fn footer() -> String {
+    branding()
+        .footer
+        .as_deref()
+        .map(|footer| format!(r#"<footer class="branding">{}</footer>"#, escape(footer)))
+        .unwrap_or_default()
+}
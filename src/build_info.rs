@@ -0,0 +1,60 @@
+use serde::Serialize;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::response::TextRepresentable;
+
+// Cargo features the binary was built with.
+const FEATURES: &[(&str, bool)] = &[
+    ("ocr", cfg!(feature = "ocr")),
+    ("otlp", cfg!(feature = "otlp")),
+    ("server", cfg!(feature = "server")),
+    ("translation-api", cfg!(feature = "translation-api")),
+];
+
+// What is running, to tell instances apart.
+#[derive(Serialize, Debug)]
+pub struct BuildInfo {
+    version: &'static str,
+    commit: Option<&'static str>,
+    build_date: Option<String>,
+    features: Vec<&'static str>,
+    sites: usize,
+}
+
+impl BuildInfo {
+    pub fn new(sites: usize) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: option_env!("OVR_GIT_COMMIT"),
+            build_date: env!("OVR_BUILD_TIMESTAMP")
+                .parse()
+                .ok()
+                .and_then(|timestamp| OffsetDateTime::from_unix_timestamp(timestamp).ok())
+                .and_then(|date| date.format(&Rfc3339).ok()),
+            features: FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| *name)
+                .collect(),
+            sites,
+        }
+    }
+}
+
+impl TextRepresentable for BuildInfo {
+    // Like "OVR v0.1.21 (3f2c1ab, built 2023-06-12T08:30:00Z), features: ocr,
+    // server, 3 sites".
+    fn as_plain_text(&self, _human: bool) -> String {
+        let mut built = vec![self.commit.unwrap_or("unknown commit").to_owned()];
+        if let Some(date) = &self.build_date {
+            built.push(format!("built {date}"));
+        }
+        format!(
+            "OVR v{} ({}), features: {}, {} sites",
+            self.version,
+            built.join(", "),
+            self.features.join(", "),
+            self.sites
+        )
+    }
+}
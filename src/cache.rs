@@ -0,0 +1,240 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use axum::{
+    body::Bytes,
+    http::{header, HeaderValue, StatusCode},
+};
+use either::Either;
+use time::Date;
+use tokio::time::interval;
+
+use crate::{
+    catalogue::{CalendarFilter, Catalogue},
+    config::Config,
+    day::{lists_categories, UpcomingDay},
+    error::Error,
+    proxy::Origin,
+    response::{ApiResponse, RenderedResponse, ResponseType},
+    site::{Site, Sites},
+    utils::now_local,
+};
+
+// How often the catalogues are checked for changes, to render the routes
+// again before they are requested.
+const WARM_INTERVAL: Duration = Duration::from_secs(5);
+// Variants are chosen by clients, through the query or the host.
+const MAX_ENTRIES: usize = 256;
+
+// The most requested routes, untranslated and with the configured cutoff.
+// The index is only from the next meal on with `?upcoming=true`.
+#[derive(Clone, Debug)]
+pub enum CachedRoute {
+    Index(ResponseType, bool),
+    Today(ResponseType),
+    Next(ResponseType),
+    Calendar(Origin, CalendarFilter),
+}
+
+impl CachedRoute {
+    fn render(&self, site: &Site, catalogue: &Catalogue, config: &Config) -> RenderedResponse {
+        let root = site.path_prefix();
+        match self {
+            CachedRoute::Index(response_type, upcoming) => {
+                let catalogue = if *upcoming {
+                    catalogue.upcoming(config.cutoff)
+                } else {
+                    catalogue.clone()
+                };
+                ApiResponse {
+                    response_type: *response_type,
+                    data: Ok(if matches!(response_type, ResponseType::Html(_, _)) {
+                        Either::Left(catalogue.weeks().in_site(&root))
+                    } else {
+                        Either::Right(catalogue)
+                    }),
+                }
+                .render()
+            }
+            CachedRoute::Today(response_type) => ApiResponse {
+                response_type: *response_type,
+                data: catalogue
+                    .today()
+                    .map(|day| day.in_site(&root))
+                    .ok_or(Error::NoMealToday),
+            }
+            .render(),
+            CachedRoute::Next(response_type) => ApiResponse {
+                response_type: *response_type,
+                data: catalogue
+                    .next(config.cutoff)
+                    .map(|day| UpcomingDay(day.in_site(&root)))
+                    .ok_or(Error::NoNextMeal),
+            }
+            .render(),
+            CachedRoute::Calendar(origin, filter) => calendar(
+                site,
+                &filter.apply(catalogue, config.cutoff),
+                config,
+                origin,
+            ),
+        }
+    }
+}
+
+// The events of `catalogue`, for subscriptions or `Accept: text/calendar`.
+pub fn calendar(
+    site: &Site,
+    catalogue: &Catalogue,
+    config: &Config,
+    origin: &Origin,
+) -> RenderedResponse {
+    let (calendar_name, filename) = match &site.name {
+        Some(name) => (format!("Menus {name}"), format!("ovr-{name}.ics")),
+        None => ("Menus".to_owned(), "ovr.ics".to_owned()),
+    };
+    let root = origin.site_url(site.name.as_deref());
+    let calendar = match catalogue.ics(
+        &calendar_name,
+        &config.calendar_namespace,
+        config.calendar_main_dish_summary,
+        Some(&root),
+    ) {
+        Ok(calendar) => calendar,
+        Err(err) => {
+            return ApiResponse::<()> {
+                response_type: ResponseType::Json(false),
+                data: Err(err),
+            }
+            .render()
+        }
+    };
+    let disposition = HeaderValue::from_str(&format!(r#"inline; filename="{filename}""#))
+        .unwrap_or_else(|_| HeaderValue::from_static(r#"inline; filename="ovr.ics""#));
+    RenderedResponse {
+        status: StatusCode::OK,
+        headers: vec![
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/calendar; charset=utf-8"),
+            ),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body: Bytes::from(calendar),
+    }
+}
+
+// Today's and the next meal also change with the time.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct Epoch {
+    date: Date,
+    after_cutoff: bool,
+}
+
+impl Epoch {
+    fn now(config: &Config) -> Self {
+        let now = now_local();
+        Self {
+            date: now.date(),
+            after_cutoff: now.time() >= config.cutoff,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    version: String,
+    epoch: Epoch,
+    response: RenderedResponse,
+}
+
+// Rendered responses of the `CachedRoute`s, valid as long as the catalogue
+// version and the epoch are the same.
+#[derive(Default, Debug)]
+pub struct ResponseCache {
+    // Keyed by site, route and whether days list their categories.
+    entries: RwLock<HashMap<(Option<String>, String, bool), CacheEntry>>,
+}
+
+impl ResponseCache {
+    pub async fn respond(
+        &self,
+        site: &Site,
+        config: &Config,
+        route: CachedRoute,
+    ) -> RenderedResponse {
+        let catalogue = site.catalogue.read().await;
+        let key = (site.name.clone(), format!("{route:?}"), lists_categories());
+        let version = catalogue.version();
+        let epoch = Epoch::now(config);
+        if let Some(entry) = self
+            .entries
+            .read()
+            .expect("response cache poisoned")
+            .get(&key)
+            .filter(|entry| entry.version == version && entry.epoch == epoch)
+        {
+            return entry.response.clone();
+        }
+
+        let response = route.render(site, &catalogue, config);
+        let mut entries = self.entries.write().expect("response cache poisoned");
+        if entries.len() < MAX_ENTRIES || entries.contains_key(&key) {
+            entries.insert(
+                key,
+                CacheEntry {
+                    version,
+                    epoch,
+                    response: response.clone(),
+                },
+            );
+        }
+        response
+    }
+}
+
+// Renders the default variants of the cached routes as soon as a catalogue
+// changes, so the first request after an upload doesn't pay for it.
+pub fn spawn(sites: Arc<Sites>, config: Arc<Config>, cache: Arc<ResponseCache>) {
+    tokio::spawn(async move {
+        let names = [None]
+            .into_iter()
+            .chain(config.sites.iter().map(|name| Some(name.as_str())))
+            .collect::<Vec<_>>();
+        let mut routes = [
+            ResponseType::Json(false),
+            ResponseType::Text(false, false),
+            ResponseType::Html(1.0, false),
+        ]
+        .into_iter()
+        .flat_map(|response_type| {
+            [
+                CachedRoute::Index(response_type, false),
+                CachedRoute::Today(response_type),
+                CachedRoute::Next(response_type),
+            ]
+        })
+        .collect::<Vec<_>>();
+        // Links of calendars depend on the host used otherwise.
+        routes.extend(
+            Origin::public(&config)
+                .map(|origin| CachedRoute::Calendar(origin, CalendarFilter::default())),
+        );
+
+        let mut interval = interval(WARM_INTERVAL);
+        loop {
+            interval.tick().await;
+            for &name in &names {
+                let Some(site) = sites.get(name) else {
+                    continue;
+                };
+                for route in &routes {
+                    cache.respond(&site, &config, route.clone()).await;
+                }
+            }
+        }
+    });
+}
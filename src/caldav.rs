@@ -0,0 +1,121 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use reqwest::{header, Client, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use tokio::time::interval;
+
+use crate::{config::Config, proxy::Origin, site::Sites};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Debug)]
+pub struct CalDavConfig {
+    // Collection URL, like
+    // "https://cloud.example.com/remote.php/dav/calendars/USER/cantine/".
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub site: Option<String>,
+}
+
+// Every event is uploaded at startup, then only the ones that changed. Events
+// of removed days are deleted. Failed requests are tried again at the next
+// poll.
+pub fn spawn(sites: Arc<Sites>, config: Arc<Config>) {
+    if config.caldav.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        let caldav = config.caldav.as_ref().expect("caldav configuration missing");
+        let Some(site) = sites.get(caldav.site.as_deref()) else {
+            eprintln!("caldav: unknown site {:?}", caldav.site);
+            return;
+        };
+        let publisher = Publisher {
+            client: Client::new(),
+            config: caldav,
+        };
+        let root = Origin::public(&config).map(|origin| origin.site_url(site.name.as_deref()));
+        let mut published = HashMap::new();
+        let mut interval = interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let events = site
+                .catalogue
+                .read()
+                .await
+                .ics_events(
+                    &config.calendar_namespace,
+                    config.calendar_main_dish_summary,
+                    root.as_deref(),
+                );
+            let events = match events {
+                Ok(events) => events.into_iter().collect::<HashMap<_, _>>(),
+                Err(err) => {
+                    eprintln!("caldav: {err}");
+                    continue;
+                }
+            };
+            publisher.sync(&mut published, events).await;
+        }
+    });
+}
+
+struct Publisher<'a> {
+    client: Client,
+    config: &'a CalDavConfig,
+}
+
+impl Publisher<'_> {
+    async fn sync(
+        &self,
+        published: &mut HashMap<String, Vec<u8>>,
+        events: HashMap<String, Vec<u8>>,
+    ) {
+        let removed = published
+            .keys()
+            .filter(|uid| !events.contains_key(*uid))
+            .cloned()
+            .collect::<Vec<_>>();
+        for uid in removed {
+            match self.send(self.client.delete(self.event_url(&uid))).await {
+                Ok(()) => {
+                    published.remove(&uid);
+                }
+                Err(err) if err.status() == Some(StatusCode::NOT_FOUND) => {
+                    published.remove(&uid);
+                }
+                Err(err) => eprintln!("caldav: failed to delete {uid}: {err}"),
+            }
+        }
+
+        for (uid, event) in events {
+            if published.get(&uid) == Some(&event) {
+                continue;
+            }
+            let request = self
+                .client
+                .put(self.event_url(&uid))
+                .header(header::CONTENT_TYPE, "text/calendar; charset=utf-8")
+                .body(event.clone());
+            match self.send(request).await {
+                Ok(()) => {
+                    published.insert(uid, event);
+                }
+                Err(err) => eprintln!("caldav: failed to upload {uid}: {err}"),
+            }
+        }
+    }
+
+    fn event_url(&self, uid: &str) -> String {
+        format!("{}/{uid}.ics", self.config.url.trim_end_matches('/'))
+    }
+
+    async fn send(&self, mut request: RequestBuilder) -> Result<(), reqwest::Error> {
+        if let Some(username) = &self.config.username {
+            request = request.basic_auth(username, self.config.password.as_ref());
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
@@ -1,169 +1,1081 @@
-use std::ops::AddAssign;
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+    iter, mem,
+    ops::AddAssign,
+    sync::OnceLock,
+};
 
 use ics::{
-    properties::{Description, DtEnd, DtStart, Status, Summary},
+    components::{Parameter, Property},
+    properties::{Description, DtEnd, DtStart, LastModified, Sequence, Status, Summary},
     Event, ICalendar,
 };
 use itertools::Itertools;
-use serde::{ser::SerializeStruct, Serialize, Serializer};
-use time::{Date, Duration, Weekday};
+use serde::{
+    ser::{self, SerializeStruct},
+    Deserialize, Serialize, Serializer,
+};
+use sha2::{Digest, Sha256};
+use time::{
+    format_description::well_known::Rfc3339, macros::time, util, Date, Duration, Month,
+    OffsetDateTime, Time, Weekday,
+};
 use uuid::Uuid;
 
 use crate::{
-    day::Day,
+    allergens::Allergen,
+    day::{
+        self, format_human_week, format_human_weekday, format_long_date, format_month,
+        format_weekday, join_human, Day, Dish, DishCategory, StoredDay,
+    },
     error::Error,
-    response::TextRepresentable,
-    utils::{format_date, format_icalendar_date, now_local},
+    holidays,
+    response::{TextRepresentable, TextStyle},
+    search::{DishIndex, DishList},
+    text::Language,
+    utils::{
+        escape, format_date, format_icalendar_date, format_icalendar_utc, local_datetime,
+        now_local, timezone_name,
+    },
 };
 
+const MAX_REVISIONS_PER_DAY: usize = 10;
+// Uploads `/undo` can revert one after the other, the latest first. Older
+// ones may have lost the revisions of their days anyway.
+const MAX_UNDOABLE_UPLOADS: usize = 10;
+const MAX_REMEMBERED_DOCUMENTS: usize = 50;
+// Menus are uploaded once a week at most, but corrections should show up
+// the same day.
+const ICS_REFRESH_INTERVAL: &str = "PT12H";
+const ICS_PRODUCT_ID: &str = "-//xyz Corp//NONSGML PDA Calendar Version 1.0//EN";
+
+// What to do when an inserted day already exists in the catalogue.
+#[derive(Deserialize, Copy, Clone, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicy {
+    #[default]
+    Replace,
+    SkipExisting,
+    MergeDishes,
+}
+
 #[derive(Serialize, Clone, Debug)]
 pub struct Catalogue {
     days: Vec<Day>,
+    #[serde(skip)]
+    revisions: HashMap<Date, Vec<Revision>>,
+    // What each upload changed, undone as a whole, the latest last.
+    #[serde(skip)]
+    uploads: Vec<(UploadId, CatalogueUpdate)>,
+    #[serde(skip)]
+    last_upload: u64,
+    #[serde(skip)]
+    modifications: HashMap<Date, Modification>,
+    // SHA-256 of the latest uploaded documents, with the days parsed from
+    // them.
+    #[serde(skip)]
+    documents: Vec<(String, Vec<Day>)>,
+    // Hash of the days and their dishes by name, computed when first needed
+    // and reset on every change, so subsets only pay for them when used.
+    #[serde(skip)]
+    version: OnceLock<String>,
+    #[serde(skip)]
+    index: OnceLock<DishIndex>,
+    // Dates of the days by year and ISO week.
+    #[serde(skip)]
+    weeks: HashMap<(i32, u8), BTreeSet<Date>>,
 }
 
 impl Catalogue {
     pub fn new() -> Self {
-        Self { days: Vec::new() }
+        Self {
+            days: Vec::new(),
+            revisions: HashMap::new(),
+            uploads: Vec::new(),
+            last_upload: 0,
+            modifications: HashMap::new(),
+            documents: Vec::new(),
+            version: OnceLock::new(),
+            index: OnceLock::new(),
+            weeks: HashMap::new(),
+        }
+    }
+
+    // Whether the document with `hash` was uploaded before and its days are
+    // all still there as they were, uploading it again would change nothing.
+    pub fn is_unchanged(&self, hash: &str) -> bool {
+        self.documents
+            .iter()
+            .find(|(document, _)| document == hash)
+            .is_some_and(|(_, days)| days.iter().all(|day| self.contains(day)))
+    }
+
+    // Whether `day` is there as is.
+    pub fn contains(&self, day: &Day) -> bool {
+        self.position(day.date())
+            .is_ok_and(|index| self.days[index] == *day)
+    }
+
+    pub fn remember_document(&mut self, hash: String, days: Vec<Day>) {
+        self.documents.retain(|(document, _)| *document != hash);
+        self.documents.push((hash, days));
+        if self.documents.len() > MAX_REMEMBERED_DOCUMENTS {
+            self.documents.remove(0);
+        }
     }
 
-    pub fn insert(&mut self, days: Vec<Day>) -> CatalogueUpdate {
+    // Starts an upload of several files, see `insert_as`.
+    pub fn start_upload(&mut self) -> UploadId {
+        self.last_upload += 1;
+        UploadId(self.last_upload)
+    }
+
+    // With `protect`, days whose replacement looks like a different menu
+    // altogether are left untouched and only reported as conflicts.
+    pub fn insert(
+        &mut self,
+        days: Vec<Day>,
+        policy: MergePolicy,
+        protect: bool,
+    ) -> CatalogueUpdate {
+        let upload = self.start_upload();
+        self.insert_as(upload, days, policy, protect)
+    }
+
+    // Like `insert`, undone along with the other inserts of `upload`.
+    pub fn insert_as(
+        &mut self,
+        upload: UploadId,
+        days: Vec<Day>,
+        policy: MergePolicy,
+        protect: bool,
+    ) -> CatalogueUpdate {
         let mut updates = CatalogueUpdate::default();
         for day in days {
-            match self.days.binary_search_by_key(&day.date(), |d| d.date()) {
+            match self.position(day.date()) {
                 Ok(to_replace) => {
+                    let day = match policy {
+                        MergePolicy::Replace => {
+                            if is_conflicting(&self.days[to_replace], &day) {
+                                updates.conflicts.push(day.date());
+                                if protect {
+                                    continue;
+                                }
+                            }
+                            day
+                        }
+                        MergePolicy::SkipExisting => {
+                            updates.skipped.push(day.date());
+                            continue;
+                        }
+                        MergePolicy::MergeDishes => {
+                            let mut merged = self.days[to_replace].clone();
+                            merged.merge(day);
+                            merged
+                        }
+                    };
                     updates.replaced.push(day.date());
-                    self.days[to_replace].replace_dishes(day.dishes())
+                    updates.changes.push(DayDiff::between(&self.days[to_replace], &day));
+                    if self.days[to_replace] != day {
+                        self.touch(day.date());
+                    }
+                    if let Some(index) = self.index.get_mut() {
+                        index.remove(&self.days[to_replace]);
+                        index.add(&day);
+                    }
+                    let previous = mem::replace(&mut self.days[to_replace], day);
+                    self.push_revision(previous, Some(upload));
                 }
                 Err(insert_position) => {
                     updates.inserted.push(day.date());
+                    self.touch(day.date());
+                    if let Some(index) = self.index.get_mut() {
+                        index.add(&day);
+                    }
+                    self.weeks
+                        .entry(week_of(day.date()))
+                        .or_default()
+                        .insert(day.date());
                     self.days.insert(insert_position, day);
                 }
             }
         }
         updates.sort();
+        if !updates.is_empty() {
+            let mut recorded = match self.uploads.iter().position(|(id, _)| *id == upload) {
+                Some(position) => self.uploads.remove(position).1,
+                None => CatalogueUpdate::default(),
+            };
+            recorded += updates.clone();
+            self.uploads.push((upload, recorded));
+            if self.uploads.len() > MAX_UNDOABLE_UPLOADS {
+                self.uploads.remove(0);
+            }
+        }
+        self.version = OnceLock::new();
+        day::prune_names();
         updates
     }
 
-    pub fn today(&self) -> Option<Day> {
-        let today = now_local().date();
-        self.days.iter().find(|day| day.date() == today).cloned()
+    // Reverts the most recent upload, with all its files: replaced days get
+    // their revision from before it back and inserted days are removed. Days
+    // changed since are left as they are.
+    pub fn undo(&mut self) -> Result<CatalogueUpdate, Error> {
+        let (id, upload) = self.uploads.pop().ok_or(Error::NothingToUndo)?;
+        let mut updates = CatalogueUpdate::default();
+        for &date in upload.replaced.iter().unique() {
+            let (Ok(index), Some(revisions)) =
+                (self.position(date), self.revisions.get_mut(&date))
+            else {
+                continue;
+            };
+            // The earliest revision of the upload, if none was pushed since.
+            let mut previous = None;
+            while revisions
+                .last()
+                .is_some_and(|revision| revision.upload == Some(id))
+            {
+                previous = revisions.pop();
+            }
+            let Some(revision) = previous else {
+                continue;
+            };
+            updates
+                .changes
+                .push(DayDiff::between(&self.days[index], &revision.day));
+            if self.days[index] != revision.day {
+                self.touch(date);
+            }
+            if let Some(dish_index) = self.index.get_mut() {
+                dish_index.remove(&self.days[index]);
+                dish_index.add(&revision.day);
+            }
+            self.days[index] = revision.day;
+            if !upload.inserted.contains(&date) {
+                updates.replaced.push(date);
+            }
+        }
+        for &date in &upload.inserted {
+            if let Ok(index) = self.position(date) {
+                let day = self.days.remove(index);
+                if let Some(index) = self.index.get_mut() {
+                    index.remove(&day);
+                }
+                if let Some(dates) = self.weeks.get_mut(&week_of(date)) {
+                    dates.remove(&date);
+                    if dates.is_empty() {
+                        self.weeks.remove(&week_of(date));
+                    }
+                }
+                updates.removed.push(date);
+            }
+        }
+        updates.changes.retain(|diff| !upload.inserted.contains(&diff.date));
+        updates.sort();
+        self.version = OnceLock::new();
+        day::prune_names();
+        Ok(updates)
     }
 
-    pub fn next(&self) -> Option<Day> {
-        let mut now = now_local();
-        if now.time().hour() >= 14 {
-            now += Duration::days(1);
-        }
-        self.days
+    // Deletes a day, kept in its history. Not undoable, `undo` reverting
+    // uploads only.
+    pub fn remove_day(&mut self, date: Date) -> Result<CatalogueUpdate, Error> {
+        self.position(date).map_err(|_| Error::DayNotFound)?;
+        Ok(self.remove(&[date]))
+    }
+
+    pub fn remove_week(&mut self, year: i32, week: u8) -> Result<CatalogueUpdate, Error> {
+        let dates = self
+            .weeks
+            .get(&(year, week))
+            .ok_or(Error::WeekNotFound)?
             .iter()
-            .find(|day| day.date() >= now.date())
-            .cloned()
+            .copied()
+            .collect_vec();
+        Ok(self.remove(&dates))
     }
 
-    pub fn find_dish_next<'a>(&self, mut search: Vec<String>) -> Option<Day> {
-        search.iter_mut().for_each(|d| *d = d.to_lowercase());
-        let mut now = now_local();
-        if now.time().hour() >= 14 {
-            now += Duration::days(1);
+    fn remove(&mut self, dates: &[Date]) -> CatalogueUpdate {
+        let mut updates = CatalogueUpdate::default();
+        for &date in dates {
+            let Ok(index) = self.position(date) else {
+                continue;
+            };
+            let day = self.days.remove(index);
+            if let Some(index) = self.index.get_mut() {
+                index.remove(&day);
+            }
+            if let Some(dates) = self.weeks.get_mut(&week_of(date)) {
+                dates.remove(&date);
+                if dates.is_empty() {
+                    self.weeks.remove(&week_of(date));
+                }
+            }
+            self.touch(date);
+            self.push_revision(day, None);
+            updates.removed.push(date);
         }
-        self.days
+        updates.sort();
+        self.version = OnceLock::new();
+        day::prune_names();
+        updates
+    }
+
+    // Replaces every day at once, reporting what changed compared to the
+    // current days.
+    pub fn restore(&mut self, mut days: Vec<Day>) -> CatalogueUpdate {
+        days.sort_by_key(|d| d.date());
+        days.dedup_by_key(|d| d.date());
+
+        let mut updates = CatalogueUpdate::default();
+        for day in &days {
+            if let Ok(index) = self.position(day.date()) {
+                updates.replaced.push(day.date());
+                updates.changes.push(DayDiff::between(&self.days[index], day));
+                if &self.days[index] != day {
+                    self.touch(day.date());
+                }
+            } else {
+                updates.inserted.push(day.date());
+                self.touch(day.date());
+            }
+        }
+        updates.removed = self
+            .days
             .iter()
-            .find(|day| {
-                day.date() >= now.date()
-                    && search.iter().all(|search_dish| {
-                        day.dishes_ref()
-                            .into_iter()
-                            .any(|day_dish| day_dish.to_lowercase().contains(search_dish))
-                    })
+            .map(|d| d.date())
+            .filter(|date| days.binary_search_by_key(date, |d| d.date()).is_err())
+            .collect();
+        self.index = OnceLock::new();
+        self.weeks = weeks(&days);
+        self.days = days;
+        self.uploads.clear();
+        self.version = OnceLock::new();
+        day::prune_names();
+        updates
+    }
+
+    // Calendar clients only update an event when its sequence grows, so it is
+    // kept even when the day is removed, in case it comes back.
+    fn touch(&mut self, date: Date) {
+        let now = now_local();
+        self.modifications
+            .entry(date)
+            .and_modify(|modification| {
+                modification.sequence += 1;
+                modification.modified_at = now;
             })
+            .or_insert(Modification {
+                sequence: 0,
+                modified_at: now,
+            });
+    }
+
+    fn push_revision(&mut self, day: Day, upload: Option<UploadId>) {
+        let revisions = self.revisions.entry(day.date()).or_default();
+        revisions.push(Revision {
+            replaced_at: now_local(),
+            day,
+            upload,
+        });
+        if revisions.len() > MAX_REVISIONS_PER_DAY {
+            revisions.remove(0);
+        }
+    }
+
+    pub fn history(&self, date: Date) -> Result<DayHistory, Error> {
+        let current = self.day(date).ok();
+        let revisions = self
+            .revisions
+            .get(&date)
+            .map(|revisions| revisions.iter().rev().cloned().collect_vec())
+            .unwrap_or_default();
+        if current.is_none() && revisions.is_empty() {
+            return Err(Error::DayNotFound);
+        }
+        Ok(DayHistory {
+            date,
+            current,
+            revisions,
+        })
+    }
+
+    pub fn export(&self) -> CatalogueExport {
+        let now = now_local();
+        CatalogueExport {
+            version: CatalogueExport::VERSION,
+            generator: concat!("OVR v", env!("CARGO_PKG_VERSION")).to_owned(),
+            exported_at: now.format(&Rfc3339).unwrap_or_else(|_| now.to_string()),
+            days: self.days.clone(),
+        }
+    }
+
+    // Hex SHA-256 of the days, which changes with any of them.
+    pub fn version(&self) -> String {
+        self.version
+            .get_or_init(|| days_version(&self.days))
+            .clone()
+    }
+
+    fn index(&self) -> &DishIndex {
+        self.index.get_or_init(|| DishIndex::new(&self.days))
+    }
+
+    pub fn snapshot(&self) -> CatalogueSnapshot {
+        CatalogueSnapshot {
+            version: self.version(),
+            days: self.days.clone(),
+        }
+    }
+
+    pub fn today(&self) -> Option<Day> {
+        self.day(now_local().date()).ok()
+    }
+
+    pub fn next(&self, cutoff: Time) -> Option<Day> {
+        self.days_from(next_meal_date(cutoff))
+            .find(|day| holidays::is_open(day.date()))
             .cloned()
     }
 
+    // Dishes with one of the `without` allergens don't count as served. Each
+    // search matches the dishes with its words at the start of words of
+    // their names, see `DishIndex::matching`.
+    pub fn find_dish_next(
+        &self,
+        search: Vec<String>,
+        without: &[Allergen],
+        cutoff: Time,
+    ) -> Option<Day> {
+        let from = next_meal_date(cutoff);
+        let mut dates: Option<BTreeSet<Date>> = None;
+        for search_dish in &search {
+            let served = self
+                .index()
+                .matching(search_dish)
+                .into_iter()
+                .filter(|&(date, _)| date >= from)
+                .filter(|&(date, position)| {
+                    self.dish(date, position)
+                        .is_some_and(|dish| !dish.contains_any(without))
+                })
+                .map(|(date, _)| date)
+                .collect::<BTreeSet<_>>();
+            dates = Some(match dates {
+                Some(dates) => dates.intersection(&served).copied().collect(),
+                None => served,
+            });
+        }
+        dates?
+            .into_iter()
+            .find(|&date| holidays::is_open(date))
+            .and_then(|date| self.day(date).ok())
+    }
+
+    // Known dishes, the most served first, only those matching `query` when
+    // there is one.
+    pub fn dishes(&self, query: Option<&str>, limit: usize) -> DishList {
+        match query.filter(|query| !query.trim().is_empty()) {
+            Some(query) => DishList::new(
+                self.index()
+                    .matching(query)
+                    .into_iter()
+                    .filter_map(|(date, position)| {
+                        Some((date, self.dish(date, position)?.name()))
+                    }),
+                limit,
+            ),
+            None => DishList::new(
+                self.days.iter().flat_map(|day| {
+                    day.dishes_ref()
+                        .iter()
+                        .map(move |dish| (day.date(), dish.name()))
+                }),
+                limit,
+            ),
+        }
+    }
+
+    fn dish(&self, date: Date, position: usize) -> Option<&Dish> {
+        let index = self.position(date).ok()?;
+        self.days[index].dishes_ref().get(position)
+    }
+
+    // Dishes that absorbed near-duplicates while parsing, for review.
+    pub fn merged_dishes(&self) -> MergedDishes {
+        MergedDishes {
+            dishes: self
+                .days
+                .iter()
+                .flat_map(|day| {
+                    day.dishes_ref()
+                        .iter()
+                        .filter(|dish| !dish.merged().is_empty())
+                        .map(|dish| MergedDish {
+                            date: format_date(day.date()),
+                            dish: dish.name().to_owned(),
+                            merged: dish.merged().to_vec(),
+                        })
+                })
+                .collect(),
+        }
+    }
+
+    pub fn texts(&self) -> Vec<String> {
+        self.days.iter().flat_map(Day::texts).unique().collect()
+    }
+
+    pub fn translated(
+        mut self,
+        language: Language,
+        translations: &HashMap<String, String>,
+    ) -> Self {
+        self.days = self
+            .days
+            .into_iter()
+            .map(|day| day.translated(language, translations))
+            .collect();
+        self.version = OnceLock::new();
+        self.index = OnceLock::new();
+        self
+    }
+
+    // Links the days to their pages on the site under `root`.
+    pub fn in_site(mut self, root: &str) -> Self {
+        self.days = self.days.into_iter().map(|day| day.in_site(root)).collect();
+        self
+    }
+
     pub fn weeks(&self) -> WeeksList {
         WeeksList::from(self.days.as_slice())
     }
 
     pub fn week(&self, year: i32, week: u8) -> Result<Self, Error> {
         let days = self
-            .days
-            .iter()
-            .filter(|d| d.date().year() == year && d.date().iso_week() == week)
-            .cloned()
+            .weeks
+            .get(&(year, week))
+            .into_iter()
+            .flatten()
+            .filter_map(|&date| self.day(date).ok())
             .collect_vec();
         if days.is_empty() {
             Err(Error::WeekNotFound)
         } else {
-            Ok(Self { days })
+            Ok(self.subset(days))
+        }
+    }
+
+    pub fn month(&self, year: i32, month: Month) -> CatalogueMonth {
+        CatalogueMonth {
+            year,
+            month,
+            root: String::new(),
+            days: self.subset(
+                self.days
+                    .iter()
+                    .filter(|day| (day.date().year(), day.date().month()) == (year, month))
+                    .cloned()
+                    .collect(),
+            ),
         }
     }
 
+    // Days from the next meal on, the ones screens and calendars show.
+    pub fn upcoming(&self, cutoff: Time) -> Self {
+        let from = next_meal_date(cutoff);
+        self.subset(
+            self.days
+                .iter()
+                .filter(|day| day.date() >= from)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    // The dishes of `category` alone, without the days having none.
+    pub fn with_category(&self, category: DishCategory) -> Self {
+        self.subset(
+            self.days
+                .iter()
+                .filter_map(|day| {
+                    let mut day = day.clone();
+                    day.retain_dishes(|dish| dish.category() == Some(category));
+                    (!day.dishes_ref().is_empty()).then_some(day)
+                })
+                .collect(),
+        )
+    }
+
+    // Some of the days, keeping their modifications for calendar sequences.
+    fn subset(&self, days: Vec<Day>) -> Self {
+        Self {
+            version: OnceLock::new(),
+            index: OnceLock::new(),
+            weeks: weeks(&days),
+            days,
+            revisions: HashMap::new(),
+            uploads: Vec::new(),
+            last_upload: 0,
+            modifications: self.modifications.clone(),
+            documents: Vec::new(),
+        }
+    }
+
+    // The closest weeks with days before and after the given one.
+    pub fn adjacent_weeks(&self, year: i32, week: u8) -> (Option<(i32, u8)>, Option<(i32, u8)>) {
+        let keys = self.weeks.keys().copied();
+        (
+            keys.clone().filter(|&key| key < (year, week)).max(),
+            keys.filter(|&key| key > (year, week)).min(),
+        )
+    }
+
+    pub fn set_allergens(
+        &mut self,
+        date: Date,
+        index: usize,
+        allergens: Option<Vec<Allergen>>,
+    ) -> Result<Day, Error> {
+        let position = self.position(date).map_err(|_| Error::DayNotFound)?;
+        let mut day = self.days[position].clone();
+        day.set_allergens(index, allergens)?;
+        if self.days[position] != day {
+            self.touch(date);
+            let previous = mem::replace(&mut self.days[position], day.clone());
+            self.push_revision(previous, None);
+            self.version = OnceLock::new();
+        }
+        Ok(day)
+    }
+
+    pub fn dates(&self) -> impl Iterator<Item = Date> + '_ {
+        self.days.iter().map(Day::date)
+    }
+
+    pub fn days_from(&self, from: Date) -> impl Iterator<Item = &Day> {
+        let start = self.position(from).unwrap_or_else(|index| index);
+        self.days[start..].iter()
+    }
+
     pub fn day(&self, date: Date) -> Result<Day, Error> {
-        self.days
-            .iter()
-            .find(|d| d.date() == date)
-            .cloned()
-            .ok_or(Error::DayNotFound)
+        self.position(date)
+            .map(|index| self.days[index].clone())
+            .map_err(|_| Error::DayNotFound)
     }
 
-    pub fn ics(&self) -> Vec<u8> {
-        let mut calendar =
-            ICalendar::new("2.0", "-//xyz Corp//NONSGML PDA Calendar Version 1.0//EN");
-        for day in &self.days {
-            let start = day.date().with_hms(12, 00, 00).unwrap();
-            let start_str = format_icalendar_date(start);
-            let mut event = Event::new(
-                Uuid::new_v5(&Uuid::nil(), start_str.as_bytes()).to_string(),
-                start_str.clone(),
-            );
-            event.push(DtStart::new(start_str));
-            event.push(DtEnd::new(format_icalendar_date(
-                start + Duration::hours(1),
-            )));
-            event.push(Status::confirmed());
-            event.push(Summary::new("Pause déjeuner"));
-            event.push(Description::new(ics::escape_text(day.as_plain_text(false))));
+    // Where the day of `date` is in the sorted days, or would be inserted.
+    fn position(&self, date: Date) -> Result<usize, usize> {
+        self.days.binary_search_by_key(&date, |d| d.date())
+    }
+
+    // `name` is the one calendar apps show for the subscription. Event UIDs
+    // only depend on `namespace` and the date. With `main_dish_summary`,
+    // event titles show the main course when the menu has one. Events link
+    // to their day under `root`, an absolute URL, when there is one.
+    pub fn ics(
+        &self,
+        name: &str,
+        namespace: &Uuid,
+        main_dish_summary: bool,
+        root: Option<&str>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut calendar = ICalendar::new("2.0", ICS_PRODUCT_ID);
+        calendar.push(Property::new("X-WR-CALNAME", ics::escape_text(name)));
+        calendar.push(Property::new("X-WR-TIMEZONE", timezone_name()));
+        let mut refresh = Property::new("REFRESH-INTERVAL", ICS_REFRESH_INTERVAL);
+        refresh.add(Parameter::new("VALUE", "DURATION"));
+        calendar.push(refresh);
+        calendar.push(Property::new("X-PUBLISHED-TTL", ICS_REFRESH_INTERVAL));
+        for (_, event) in self.events(namespace, main_dish_summary, root) {
             calendar.add_event(event);
         }
 
         let mut data = Vec::new();
-        calendar.write(&mut data).expect("ics file creation failed");
-        data
+        calendar
+            .write(&mut data)
+            .map_err(|err| Error::Rendering(format!("calendar: {err}")))?;
+        Ok(data)
+    }
+
+    // One calendar per event, by UID, as CalDAV servers store them.
+    pub fn ics_events(
+        &self,
+        namespace: &Uuid,
+        main_dish_summary: bool,
+        root: Option<&str>,
+    ) -> Result<Vec<(String, Vec<u8>)>, Error> {
+        self.events(namespace, main_dish_summary, root)
+            .map(|(uid, event)| {
+                let mut calendar = ICalendar::new("2.0", ICS_PRODUCT_ID);
+                calendar.add_event(event);
+                let mut data = Vec::new();
+                calendar
+                    .write(&mut data)
+                    .map_err(|err| Error::Rendering(format!("event {uid}: {err}")))?;
+                Ok((uid, data))
+            })
+            .collect()
+    }
+
+    // The event of a single day, like in the calendar.
+    pub fn day_ics(
+        &self,
+        date: Date,
+        namespace: &Uuid,
+        main_dish_summary: bool,
+        root: Option<&str>,
+    ) -> Result<Vec<u8>, Error> {
+        self.subset(vec![self.day(date)?])
+            .ics_events(namespace, main_dish_summary, root)?
+            .into_iter()
+            .next()
+            .map(|(_, data)| data)
+            .ok_or(Error::DayNotFound)
+    }
+
+    fn events<'a>(
+        &'a self,
+        namespace: &'a Uuid,
+        main_dish_summary: bool,
+        root: Option<&'a str>,
+    ) -> impl Iterator<Item = (String, Event<'static>)> + 'a {
+        self.days
+            .iter()
+            .filter(|day| holidays::closure(day.date()).is_none())
+            .map(move |day| {
+                let uid_seed = format_icalendar_date(day.date().with_time(time!(12:00)));
+                let uid = Uuid::new_v5(namespace, uid_seed.as_bytes()).to_string();
+                let start = local_datetime(day.date(), time!(12:00));
+                let start_str = format_icalendar_utc(start);
+                let (sequence, modified_at) = match self.modifications.get(&day.date()) {
+                    Some(modification) => (
+                        modification.sequence,
+                        format_icalendar_utc(modification.modified_at),
+                    ),
+                    None => (0, start_str.clone()),
+                };
+                let mut event = Event::new(uid.clone(), modified_at.clone());
+                event.push(Sequence::new(sequence.to_string()));
+                event.push(LastModified::new(modified_at));
+                event.push(DtStart::new(start_str));
+                event.push(DtEnd::new(format_icalendar_utc(start + Duration::hours(1))));
+                event.push(Status::confirmed());
+                let summary = match day.main_dish().filter(|_| main_dish_summary) {
+                    Some(dish) => format!("Déjeuner — {}", dish.name()),
+                    None => "Pause déjeuner".to_owned(),
+                };
+                event.push(Summary::new(ics::escape_text(summary)));
+                event.push(Description::new(ics::escape_text(day.as_sectioned_text())));
+                if let Some(root) = root {
+                    event.push(Property::new(
+                        "URL",
+                        format!("{root}/days/{}", format_date(day.date())),
+                    ));
+                }
+                (uid, event)
+            })
     }
 }
 
 impl TextRepresentable for Catalogue {
+    // Read aloud in human mode, like "Cette semaine : lundi, carottes râpées
+    // et poulet ; mardi, …", a sentence per week.
     fn as_plain_text(&self, human: bool) -> String {
+        self.as_styled_text(human, TextStyle::Plain)
+    }
+
+    // Today's date emphasized.
+    fn as_styled_text(&self, human: bool, style: TextStyle) -> String {
+        if human {
+            return self
+                .days
+                .iter()
+                .group_by(|day| monday(day.date()))
+                .into_iter()
+                .map(|(monday, days)| {
+                    format!(
+                        "{} : {}.",
+                        monday
+                            .map(|monday| format_human_week(monday, true))
+                            .unwrap_or_default(),
+                        days.map(format_human_weekday).join(" ; ")
+                    )
+                })
+                .join("\n");
+        }
+        let today = now_local().date();
         self.days
             .iter()
             .map(|day| {
+                let date = format_date(day.date());
                 format!(
                     "{} :\n{}",
-                    format_date(day.date()),
-                    day.as_plain_text(human)
+                    if day.date() == today {
+                        style.today(&date)
+                    } else {
+                        date
+                    },
+                    day.as_styled_text(human, style)
                 )
             })
             .join("\n\n")
     }
 
+    // A table per week, see `week_table`.
+    fn as_table(&self) -> String {
+        self.days
+            .iter()
+            .group_by(|day| monday(day.date()))
+            .into_iter()
+            .map(|(_, days)| week_table(&days.collect_vec()))
+            .join("\n\n")
+    }
+
     fn as_html(&self) -> String {
         self.days.iter().map(Day::as_html).collect()
     }
 }
 
-#[derive(Default, Debug)]
+// Days and dishes of a calendar, every one of them by default.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct CalendarFilter {
+    pub upcoming: bool,
+    pub category: Option<DishCategory>,
+}
+
+impl CalendarFilter {
+    pub fn apply<'a>(&self, catalogue: &'a Catalogue, cutoff: Time) -> Cow<'a, Catalogue> {
+        let mut catalogue = Cow::Borrowed(catalogue);
+        if self.upcoming {
+            catalogue = Cow::Owned(catalogue.upcoming(cutoff));
+        }
+        if let Some(category) = self.category {
+            catalogue = Cow::Owned(catalogue.with_category(category));
+        }
+        catalogue
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CatalogueExport {
+    pub version: u32,
+    pub generator: String,
+    pub exported_at: String,
+    #[serde(serialize_with = "day::serialize_with_categories")]
+    pub days: Vec<Day>,
+}
+
+impl CatalogueExport {
+    pub const VERSION: u32 = 1;
+}
+
+#[derive(Serialize, Debug)]
+pub struct CatalogueSnapshot {
+    pub version: String,
+    pub days: Vec<Day>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Modification {
+    sequence: u32,
+    modified_at: OffsetDateTime,
+}
+
+#[derive(Clone, Debug)]
+struct Revision {
+    replaced_at: OffsetDateTime,
+    day: Day,
+    // The upload that replaced the day, see `Catalogue::undo`.
+    upload: Option<UploadId>,
+}
+
+// Inserts undone together, one per upload request.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct UploadId(u64);
+
+impl Serialize for Revision {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            replaced_at: String,
+            #[serde(flatten)]
+            day: &'a Day,
+        }
+
+        Entry {
+            replaced_at: self
+                .replaced_at
+                .format(&Rfc3339)
+                .map_err(ser::Error::custom)?,
+            day: &self.day,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Debug)]
+pub struct DayHistory {
+    date: Date,
+    current: Option<Day>,
+    revisions: Vec<Revision>,
+}
+
+impl DayHistory {
+    // Links the revisions to the day's page on the site under `root`.
+    pub fn in_site(mut self, root: &str) -> Self {
+        self.current = self.current.map(|day| day.in_site(root));
+        self.revisions = self
+            .revisions
+            .into_iter()
+            .map(|revision| Revision {
+                day: revision.day.in_site(root),
+                ..revision
+            })
+            .collect();
+        self
+    }
+}
+
+impl Serialize for DayHistory {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("DayHistory", 3)?;
+        state.serialize_field("date", &format_date(self.date))?;
+        state.serialize_field("current", &self.current)?;
+        state.serialize_field("revisions", &self.revisions)?;
+        state.end()
+    }
+}
+
+impl TextRepresentable for DayHistory {
+    fn as_plain_text(&self, human: bool) -> String {
+        let current = self
+            .current
+            .iter()
+            .map(|day| format!("Current:\n{}", day.as_plain_text(human)));
+        let revisions = self.revisions.iter().map(|revision| {
+            format!(
+                "Replaced on {}:\n{}",
+                revision
+                    .replaced_at
+                    .format(&Rfc3339)
+                    .unwrap_or_else(|_| revision.replaced_at.to_string()),
+                revision.day.as_plain_text(human)
+            )
+        });
+        current.chain(revisions).join("\n\n")
+    }
+
+    fn as_html(&self) -> String {
+        let current = self.current.iter().map(Day::as_html);
+        let revisions = self.revisions.iter().map(|revision| {
+            format!(
+                r#"<div class="revision">Remplacé le {} à {:02}h{:02}</div>{}"#,
+                format_date(revision.replaced_at.date()),
+                revision.replaced_at.hour(),
+                revision.replaced_at.minute(),
+                revision.day.as_html()
+            )
+        });
+        current.chain(revisions).collect()
+    }
+}
+
+// Dishes that appeared or disappeared when a day was replaced.
+#[derive(Clone, Debug)]
+pub struct DayDiff {
+    date: Date,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+impl DayDiff {
+    fn between(previous: &Day, current: &Day) -> Self {
+        Self {
+            date: current.date(),
+            added: current
+                .dishes_ref()
+                .iter()
+                .filter(|dish| !previous.has_dish(dish.name()))
+                .map(|dish| dish.name().to_owned())
+                .collect(),
+            removed: previous
+                .dishes_ref()
+                .iter()
+                .filter(|dish| !current.has_dish(dish.name()))
+                .map(|dish| dish.name().to_owned())
+                .collect(),
+        }
+    }
+}
+
+impl Serialize for DayDiff {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("DayDiff", 3)?;
+        state.serialize_field("date", &format_date(self.date))?;
+        state.serialize_field("added", &self.added)?;
+        state.serialize_field("removed", &self.removed)?;
+        state.end()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
 pub struct CatalogueUpdate {
     pub inserted: Vec<Date>,
     pub replaced: Vec<Date>,
+    pub removed: Vec<Date>,
+    pub skipped: Vec<Date>,
+    pub conflicts: Vec<Date>,
+    pub changes: Vec<DayDiff>,
+    // The document was already uploaded and wasn't parsed again.
+    pub unchanged: bool,
 }
 
 impl CatalogueUpdate {
+    pub fn unchanged() -> Self {
+        Self {
+            unchanged: true,
+            ..Self::default()
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.inserted.is_empty() && self.replaced.is_empty()
+        self.inserted.is_empty() && self.replaced.is_empty() && self.removed.is_empty()
+    }
+
+    // Conflicting days that were kept because the upload wasn't forced.
+    pub fn has_blocked_conflicts(&self) -> bool {
+        self.conflicts
+            .iter()
+            .any(|date| !self.replaced.contains(date))
+    }
+
+    pub fn dates(&self) -> Vec<Date> {
+        self.inserted
+            .iter()
+            .chain(&self.replaced)
+            .chain(&self.removed)
+            .copied()
+            .sorted()
+            .collect()
     }
 
     fn sort(&mut self) {
         self.inserted.sort();
         self.replaced.sort();
+        self.removed.sort();
+        self.skipped.sort();
+        self.conflicts.sort();
+        self.changes.sort_by_key(|diff| diff.date);
     }
 }
 
@@ -175,6 +1087,14 @@ impl AddAssign<Self> for CatalogueUpdate {
                 self.replaced.push(replaced);
             }
         }
+        for diff in rhs.changes {
+            if !self.inserted.contains(&diff.date) {
+                self.changes.push(diff);
+            }
+        }
+        self.removed.extend(rhs.removed);
+        self.skipped.extend(rhs.skipped);
+        self.conflicts.extend(rhs.conflicts);
     }
 }
 
@@ -183,7 +1103,7 @@ impl Serialize for CatalogueUpdate {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("CatalogueUpdate", 2)?;
+        let mut state = serializer.serialize_struct("CatalogueUpdate", 7)?;
         state.serialize_field(
             "inserted",
             &self
@@ -200,12 +1120,41 @@ impl Serialize for CatalogueUpdate {
                 .map(|&date| format_date(date))
                 .collect_vec(),
         )?;
+        state.serialize_field(
+            "removed",
+            &self
+                .removed
+                .iter()
+                .map(|&date| format_date(date))
+                .collect_vec(),
+        )?;
+        state.serialize_field(
+            "skipped",
+            &self
+                .skipped
+                .iter()
+                .map(|&date| format_date(date))
+                .collect_vec(),
+        )?;
+        state.serialize_field(
+            "conflicts",
+            &self
+                .conflicts
+                .iter()
+                .map(|&date| format_date(date))
+                .collect_vec(),
+        )?;
+        state.serialize_field("changes", &self.changes)?;
+        state.serialize_field("unchanged", &self.unchanged)?;
         state.end()
     }
 }
 
 impl TextRepresentable for CatalogueUpdate {
     fn as_plain_text(&self, _human: bool) -> String {
+        if self.unchanged {
+            return "Already uploaded, nothing changed.".to_owned();
+        }
         let mut text = String::new();
         if !self.inserted.is_empty() {
             text += "Inserted:\n";
@@ -223,32 +1172,540 @@ impl TextRepresentable for CatalogueUpdate {
             text += &self
                 .replaced
                 .iter()
+                .map(|&date| {
+                    let changes = self
+                        .changes
+                        .iter()
+                        .filter(|diff| diff.date == date)
+                        .flat_map(|diff| {
+                            diff.added
+                                .iter()
+                                .map(|dish| format!("\n  + {dish}"))
+                                .chain(diff.removed.iter().map(|dish| format!("\n  - {dish}")))
+                        })
+                        .collect::<String>();
+                    format!("{}{changes}", format_date(date))
+                })
+                .join("\n");
+        }
+        if !self.removed.is_empty() {
+            if !text.is_empty() {
+                text += "\n\n";
+            }
+            text += "Removed:\n";
+            text += &self
+                .removed
+                .iter()
+                .map(|&date| format_date(date))
+                .join("\n");
+        }
+        if !self.skipped.is_empty() {
+            if !text.is_empty() {
+                text += "\n\n";
+            }
+            text += "Skipped:\n";
+            text += &self
+                .skipped
+                .iter()
+                .map(|&date| format_date(date))
+                .join("\n");
+        }
+        if !self.conflicts.is_empty() {
+            if !text.is_empty() {
+                text += "\n\n";
+            }
+            text += "Conflicts:\n";
+            text += &self
+                .conflicts
+                .iter()
                 .map(|&date| format_date(date))
                 .join("\n");
         }
         text
     }
+
+    fn as_html(&self) -> String {
+        self.as_html_in("")
+    }
+}
+
+impl CatalogueUpdate {
+    // A section per kind of change, linking to the days of the site served
+    // under `root`, with the dishes added and removed from the replaced ones.
+    pub fn as_html_in(&self, root: &str) -> String {
+        if self.unchanged {
+            return r#"<div class="update-title">Déjà envoyé, rien n'a changé.</div>"#
+                .to_owned();
+        }
+        let section = |title: &str, dates: &[Date]| {
+            if dates.is_empty() {
+                return String::new();
+            }
+            let days = dates
+                .iter()
+                .map(|&date| {
+                    let changes = self
+                        .changes
+                        .iter()
+                        .filter(|diff| diff.date == date)
+                        .flat_map(|diff| {
+                            diff.added
+                                .iter()
+                                .map(|dish| {
+                                    format!(
+                                        r#"<div class="change added">+ {}</div>"#,
+                                        escape(dish)
+                                    )
+                                })
+                                .chain(diff.removed.iter().map(|dish| {
+                                    format!(
+                                        r#"<div class="change removed">- {}</div>"#,
+                                        escape(dish)
+                                    )
+                                }))
+                        })
+                        .collect::<String>();
+                    format!(
+                        r#"<a href="{}/days/{}">{}</a>{changes}"#,
+                        escape(root),
+                        format_date(date),
+                        format_long_date(date)
+                    )
+                })
+                .collect::<String>();
+            format!(r#"<div class="update"><div class="update-title">{title}</div>{days}</div>"#)
+        };
+        [
+            section("Ajoutés", &self.inserted),
+            section("Remplacés", &self.replaced),
+            section("Supprimés", &self.removed),
+            section("Ignorés", &self.skipped),
+            section("En conflit", &self.conflicts),
+        ]
+        .concat()
+    }
+}
+
+fn days_version(days: &[Day]) -> String {
+    let days = days.iter().map(StoredDay).collect_vec();
+    sha256_hex(&serde_json::to_vec(&days).expect("catalogue serialization failed"))
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// ISO year and week, as `Catalogue::week` takes them. The year is the one of
+// the Thursday, 2024-12-30 being in 2025-01.
+pub fn week_of(date: Date) -> (i32, u8) {
+    let (year, week, _) = date.to_iso_week_date();
+    (year, week)
+}
+
+fn weeks(days: &[Day]) -> HashMap<(i32, u8), BTreeSet<Date>> {
+    let mut weeks = HashMap::<_, BTreeSet<_>>::new();
+    for day in days {
+        weeks
+            .entry(week_of(day.date()))
+            .or_default()
+            .insert(day.date());
+    }
+    weeks
+}
+
+// Today's meal is over once `cutoff` has passed, and nothing is served on
+// weekends or closed days.
+fn next_meal_date(cutoff: Time) -> Date {
+    let now = now_local();
+    let mut date = if now.time() >= cutoff {
+        now.date() + Duration::days(1)
+    } else {
+        now.date()
+    };
+    for _ in 0..366 {
+        if holidays::is_open(date) {
+            break;
+        }
+        date += Duration::days(1);
+    }
+    date
+}
+
+// Less than half of the dishes in common usually means the wrong menu was
+// uploaded rather than a corrected one.
+fn is_conflicting(previous: &Day, current: &Day) -> bool {
+    let common = current
+        .dishes_ref()
+        .iter()
+        .filter(|dish| previous.has_dish(dish.name()))
+        .count();
+    let total = previous.dishes_ref().len().max(current.dishes_ref().len());
+    !previous.dishes_ref().is_empty() && common * 2 < total
+}
+
+#[derive(Serialize, Debug)]
+pub struct MergedDishes {
+    dishes: Vec<MergedDish>,
+}
+
+#[derive(Serialize, Debug)]
+struct MergedDish {
+    date: String,
+    dish: String,
+    merged: Vec<String>,
+}
+
+impl TextRepresentable for MergedDishes {
+    fn as_plain_text(&self, _human: bool) -> String {
+        self.dishes
+            .iter()
+            .map(|dish| format!("{} : {} ← {}", dish.date, dish.dish, dish.merged.join(", ")))
+            .join("\n")
+    }
+}
+
+// The days side by side, their dishes and notes under their weekday, like:
+//
+// Lundi 12        | Mardi 13
+// ----------------+---------------
+// Carottes râpées | Salade verte
+// Poulet rôti     | Lasagnes
+fn week_table(days: &[&Day]) -> String {
+    let columns = days
+        .iter()
+        .map(|day| {
+            let date = day.date();
+            iter::once(format!("{} {}", format_weekday(date.weekday()), date.day()))
+                .chain(day.as_plain_text(false).lines().map(str::to_owned))
+                .collect_vec()
+        })
+        .collect_vec();
+    let widths = columns
+        .iter()
+        .map(|column| column.iter().map(|line| line.chars().count()).max().unwrap_or(0))
+        .collect_vec();
+    let height = columns.iter().map(Vec::len).max().unwrap_or(0);
+    let row = |line: usize| {
+        columns
+            .iter()
+            .zip(&widths)
+            .map(|(column, &width)| {
+                format!("{:<width$}", column.get(line).map_or("", String::as_str))
+            })
+            .join(" | ")
+            .trim_end()
+            .to_owned()
+    };
+    let separator = widths.iter().map(|&width| "-".repeat(width)).join("-+-");
+    iter::once(row(0))
+        .chain(iter::once(separator))
+        .chain((1..height).map(row))
+        .join("\n")
+}
+
+// Start of the ISO week of `date`, `None` before the first representable
+// date.
+fn monday(date: Date) -> Option<Date> {
+    let (year, week, _) = date.to_iso_week_date();
+    Date::from_iso_week_date(year, week, Weekday::Monday).ok()
+}
+
+// The days of a week, with its neighbours in the catalogue to page through
+// them.
+pub struct CatalogueWeek {
+    year: i32,
+    week: u8,
+    // Monday of the week.
+    from: Option<Date>,
+    days: Catalogue,
+    previous: Option<(i32, u8)>,
+    next: Option<(i32, u8)>,
+    // Path prefix of the site, see `Site::path_prefix`.
+    root: String,
+}
+
+impl CatalogueWeek {
+    pub fn new(
+        (year, week): (i32, u8),
+        days: Catalogue,
+        (previous, next): (Option<(i32, u8)>, Option<(i32, u8)>),
+    ) -> Self {
+        Self {
+            year,
+            week,
+            from: days.days.first().and_then(|day| monday(day.date())),
+            days,
+            previous,
+            next,
+            root: String::new(),
+        }
+    }
+
+    // Links the days and the adjacent weeks to their pages on the site under
+    // `root`.
+    pub fn in_site(self, root: &str) -> Self {
+        Self {
+            days: self.days.in_site(root),
+            root: root.to_owned(),
+            ..self
+        }
+    }
+
+    pub fn days(&self) -> &Catalogue {
+        &self.days
+    }
+
+    // Only the days from the next meal on, the week keeping its dates.
+    pub fn upcoming(self, cutoff: Time) -> Self {
+        Self {
+            days: self.days.upcoming(cutoff),
+            ..self
+        }
+    }
+
+    // Monday to Friday, as in the weeks list.
+    fn bounds(&self) -> Option<(Date, Date)> {
+        let from = self.from?;
+        Some((from, from.checked_add(Duration::days(4))?))
+    }
+}
+
+fn format_week((year, week): (i32, u8)) -> String {
+    format!("{year}-{week:02}")
+}
+
+impl Serialize for CatalogueWeek {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Week<'a> {
+            year: i32,
+            week: u8,
+            from: Option<String>,
+            to: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            previous: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            next: Option<String>,
+            #[serde(flatten)]
+            days: &'a Catalogue,
+        }
+
+        let bounds = self.bounds();
+        Week {
+            year: self.year,
+            week: self.week,
+            from: bounds.map(|(from, _)| format_date(from)),
+            to: bounds.map(|(_, to)| format_date(to)),
+            previous: self.previous.map(format_week),
+            next: self.next.map(format_week),
+            days: &self.days,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl TextRepresentable for CatalogueWeek {
+    fn as_plain_text(&self, human: bool) -> String {
+        self.days.as_plain_text(human)
+    }
+
+    fn as_styled_text(&self, human: bool, style: TextStyle) -> String {
+        self.days.as_styled_text(human, style)
+    }
+
+    fn as_table(&self) -> String {
+        self.days.as_table()
+    }
+
+    fn as_html(&self) -> String {
+        let link = |week: Option<(i32, u8)>, title: &str| {
+            week.map(|week| {
+                format!(
+                    r#"<a href="{}/weeks/{}" class="week">{title}</a>"#,
+                    escape(&self.root),
+                    format_week(week)
+                )
+            })
+            .unwrap_or_default()
+        };
+        format!(
+            "{}{}{}",
+            self.days.as_html(),
+            link(self.previous, "Semaine précédente"),
+            link(self.next, "Semaine suivante")
+        )
+    }
+}
+
+// The days of a month, as a calendar in HTML.
+pub struct CatalogueMonth {
+    year: i32,
+    month: Month,
+    // Path prefix of the site, see `Site::path_prefix`.
+    root: String,
+    days: Catalogue,
+}
+
+impl CatalogueMonth {
+    // Links the cells to the days' pages on the site under `root`.
+    pub fn in_site(mut self, root: &str) -> Self {
+        self.root = root.to_owned();
+        self
+    }
+
+    // The day of the month, with its main dish or what replaces it.
+    fn cell(&self, date: Date, today: Date) -> String {
+        if date.month() != self.month {
+            return r#"<div class="cell outside"></div>"#.to_owned();
+        }
+        let class_str = if date == today { "current" } else { "" };
+        let Some(day) = self.days.days.iter().find(|day| day.date() == date) else {
+            return format!(
+                r#"
+                <div class="cell {class_str}">
+                    <span class="cell-date">{}</span>
+                    <span class="cell-dish">{}</span>
+                </div>"#,
+                date.day(),
+                holidays::closure(date).unwrap_or_default()
+            );
+        };
+        let dish = day
+            .main_dish()
+            .or_else(|| day.dishes_ref().first())
+            .map(Dish::name)
+            .or_else(|| day.notes().first().map(String::as_str))
+            .map(escape)
+            .unwrap_or_default();
+        format!(
+            r#"
+            <a href="{}/days/{}" class="cell {class_str}">
+                <span class="cell-date">{}</span>
+                <span class="cell-dish">{dish}</span>
+            </a>"#,
+            escape(&self.root),
+            format_date(date),
+            date.day()
+        )
+    }
+}
+
+impl Serialize for CatalogueMonth {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            year: i32,
+            month: u8,
+            #[serde(flatten)]
+            days: &'a Catalogue,
+        }
+
+        Entry {
+            year: self.year,
+            month: self.month.into(),
+            days: &self.days,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl TextRepresentable for CatalogueMonth {
+    fn as_plain_text(&self, human: bool) -> String {
+        self.days.as_plain_text(human)
+    }
+
+    fn as_styled_text(&self, human: bool, style: TextStyle) -> String {
+        self.days.as_styled_text(human, style)
+    }
+
+    fn as_table(&self) -> String {
+        self.days.as_table()
+    }
+
+    // Weeks from Monday to Friday, or Sunday when the month has weekend
+    // menus.
+    fn as_html(&self) -> String {
+        let days_in_month = util::days_in_year_month(self.year, self.month);
+        let (Ok(first), Ok(last)) = (
+            Date::from_calendar_date(self.year, self.month, 1),
+            Date::from_calendar_date(self.year, self.month, days_in_month),
+        ) else {
+            return String::new();
+        };
+        let weekends = self
+            .days
+            .days
+            .iter()
+            .any(|day| matches!(day.date().weekday(), Weekday::Saturday | Weekday::Sunday));
+        let columns = if weekends { 7 } else { 5 };
+        let today = now_local().date();
+        let mut cells = iter::successors(Some(Weekday::Monday), |weekday| Some(weekday.next()))
+            .take(columns)
+            .map(|weekday| {
+                // Weekday names are ASCII.
+                format!(
+                    r#"<div class="cell-header">{}</div>"#,
+                    &format_weekday(weekday)[..3]
+                )
+            })
+            .collect::<String>();
+        let mut week = monday(first);
+        while let Some(start) = week.filter(|&start| start <= last) {
+            cells += &(0..columns as i64)
+                .filter_map(|offset| start.checked_add(Duration::days(offset)))
+                .map(|date| self.cell(date, today))
+                .collect::<String>();
+            week = start.checked_add(Duration::weeks(1));
+        }
+        format!(
+            r#"
+            <div class="update-title">{}</div>
+            <div class="month" style="--columns: {columns}">{cells}</div>"#,
+            format_month(self.year, self.month)
+        )
+    }
 }
 
 pub struct WeeksList {
     weeks: Vec<Date>,
+    // Path prefix of the site, see `Site::path_prefix`.
+    root: String,
 }
 
 impl From<&[Day]> for WeeksList {
     fn from(days: &[Day]) -> Self {
         Self {
             weeks: days
-                .into_iter()
-                .map(|d| {
-                    Date::from_iso_week_date(d.date().year(), d.date().iso_week(), Weekday::Monday)
-                        .expect("week list creation failed")
-                })
+                .iter()
+                .filter_map(|d| monday(d.date()))
                 .unique()
                 .collect(),
+            root: String::new(),
         }
     }
 }
 
+impl WeeksList {
+    pub fn mondays(&self) -> &[Date] {
+        &self.weeks
+    }
+
+    // Links the weeks to their pages on the site under `root`.
+    pub fn in_site(mut self, root: &str) -> Self {
+        self.root = root.to_owned();
+        self
+    }
+}
+
 impl Serialize for WeeksList {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -277,32 +1734,310 @@ impl Serialize for WeeksList {
 }
 
 impl TextRepresentable for WeeksList {
-    fn as_plain_text(&self, _human: bool) -> String {
+    fn as_plain_text(&self, human: bool) -> String {
+        if human {
+            if self.weeks.is_empty() {
+                return "Aucun menu disponible.".to_owned();
+            }
+            let weeks = self
+                .weeks
+                .iter()
+                .map(|&monday| format_human_week(monday, false))
+                .collect_vec();
+            return format!(
+                "Menus disponibles pour {}.",
+                join_human(&weeks.iter().map(String::as_str).collect_vec())
+            );
+        }
         self.weeks
             .iter()
-            .map(|week| format!("{}-{}", week.year(), week.iso_week()))
+            .map(|&monday| {
+                let (year, week) = week_of(monday);
+                format!("{year}-{week}")
+            })
             .join("\n")
     }
 
     fn as_html(&self) -> String {
         let today = now_local().date();
-        let current = (today.year(), today.iso_week());
+        let current = week_of(today);
+        let root = escape(&self.root);
         self.weeks
             .iter()
-            .map(|week| {
-                let class_str = if (week.year(), week.iso_week()) == current {
-                    "current"
-                } else {
-                    ""
-                };
+            .map(|&monday| {
+                let (year, week) = week_of(monday);
+                let class_str = if (year, week) == current { "current" } else { "" };
                 format!(
-                    r#"<a href="/weeks/{}-{}" class="week {class_str}">Semaine {} - {}</a>"#,
-                    week.year(),
-                    week.iso_week(),
-                    week.iso_week(),
-                    week.year()
+                    r#"<a href="{root}/weeks/{}-{}" class="week {class_str}">Semaine {} - {}</a>"#,
+                    year,
+                    week,
+                    week,
+                    year
                 )
             })
             .collect()
     }
 }
+
+// The weeks list of admins, with a form to upload menus, buttons to delete
+// weeks and days, a link to the audit log and a logout button.
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct AdminIndex {
+    weeks: WeeksList,
+    #[serde(skip)]
+    dates: Vec<Date>,
+    // Path prefix of the site, see `Site::path_prefix`.
+    #[serde(skip)]
+    root: String,
+}
+
+impl AdminIndex {
+    pub fn new(catalogue: &Catalogue, root: String) -> Self {
+        Self {
+            weeks: catalogue.weeks(),
+            dates: catalogue.dates().collect(),
+            root,
+        }
+    }
+}
+
+impl TextRepresentable for AdminIndex {
+    fn as_plain_text(&self, human: bool) -> String {
+        self.weeks.as_plain_text(human)
+    }
+
+    fn as_html(&self) -> String {
+        let today = now_local().date();
+        let current = week_of(today);
+        let root = escape(&self.root);
+        let delete = |path: String, confirm: String, title: &str| {
+            format!(
+                r#"<button class="delete" data-delete="{path}"
+                    data-confirm="{confirm}">{title}</button>"#
+            )
+        };
+        let weeks = self
+            .dates
+            .iter()
+            .group_by(|&&date| monday(date))
+            .into_iter()
+            .filter_map(|(monday, dates)| Some((monday?, dates)))
+            .map(|(monday, dates)| {
+                let week = week_of(monday);
+                let class_str = if week == current { "current" } else { "" };
+                let days = dates
+                    .map(|&date| {
+                        format!(
+                            r#"<div class="admin-day">{}{}</div>"#,
+                            format_long_date(date),
+                            delete(
+                                format!("{root}/days/{}", format_date(date)),
+                                format!("Supprimer le {} ?", format_long_date(date)),
+                                "Supprimer"
+                            )
+                        )
+                    })
+                    .collect::<String>();
+                format!(
+                    r#"
+                    <div class="admin-week">
+                        <a href="{root}/weeks/{}" class="week {class_str}">Semaine {} - {}</a>
+                        {}{days}
+                    </div>
+                "#,
+                    format_week(week),
+                    week.1,
+                    week.0,
+                    delete(
+                        format!("{root}/weeks/{}", format_week(week)),
+                        format!("Supprimer la semaine {} ?", week.1),
+                        "Supprimer la semaine"
+                    )
+                )
+            })
+            .collect::<String>();
+        format!(
+            r#"
+            <div class="admin">
+                <form method="post" action="{root}/upload" enctype="multipart/form-data">
+                    <input type="file" name="file" accept=".pdf,.json,.csv,.xlsx,.ics"
+                        multiple required>
+                    <button type="submit">Publier</button>
+                </form>
+                <a href="/audit" class="admin-link">Journal des modifications</a>
+                <form method="post" action="/logout">
+                    <button type="submit">Se déconnecter</button>
+                </form>
+            </div>
+            {weeks}
+            <script>
+                document.querySelectorAll('[data-delete]').forEach(button => {{
+                    button.addEventListener('click', async () => {{
+                        if (!confirm(button.dataset.confirm)) {{
+                            return;
+                        }}
+                        const resp = await fetch(button.dataset.delete, {{method: 'DELETE'}});
+                        if (resp.ok) {{
+                            location.reload();
+                        }} else {{
+                            alert((await resp.json()).error);
+                        }}
+                    }});
+                }});
+            </script>
+        "#
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+    use crate::week::{parse_ics, ParserConfig};
+
+    fn day(date: &str, dishes: &[&str]) -> Day {
+        let fields = iter::once(date)
+            .chain(dishes.iter().copied())
+            .map(ToOwned::to_owned)
+            .collect();
+        Day::new(fields)
+            .expect("invalid day")
+            .expect("day without dishes")
+    }
+
+    #[test]
+    fn week_of_dates() {
+        assert_eq!(week_of(date!(2023 - 06 - 12)), (2023, 24));
+        assert_eq!(week_of(date!(2023 - 06 - 18)), (2023, 24));
+        assert_eq!(week_of(date!(2023 - 06 - 19)), (2023, 25));
+        assert_eq!(week_of(date!(2024 - 12 - 30)), (2025, 1));
+        assert_eq!(week_of(date!(2025 - 01 - 01)), (2025, 1));
+    }
+
+    #[test]
+    fn monday_of_week() {
+        assert_eq!(monday(date!(2023 - 06 - 15)), Some(date!(2023 - 06 - 12)));
+        assert_eq!(monday(date!(2023 - 06 - 18)), Some(date!(2023 - 06 - 12)));
+        assert_eq!(monday(date!(2024 - 01 - 03)), Some(date!(2024 - 01 - 01)));
+        assert_eq!(monday(date!(2025 - 01 - 01)), Some(date!(2024 - 12 - 30)));
+    }
+
+    #[test]
+    fn week_lookup_after_insert() {
+        let mut catalogue = Catalogue::new();
+        catalogue.insert(
+            vec![
+                day("2023-06-12", &["Poulet rôti"]),
+                day("2023-06-19", &["Lasagnes"]),
+            ],
+            MergePolicy::Replace,
+            false,
+        );
+        assert_eq!(
+            catalogue.adjacent_weeks(2023, 24),
+            (None, Some((2023, 25)))
+        );
+    }
+
+    #[test]
+    fn week_lookup_across_years() {
+        let mut catalogue = Catalogue::new();
+        catalogue.restore(vec![
+            day("2024-12-23", &["Dinde"]),
+            day("2024-12-30", &["Poulet rôti"]),
+            day("2025-01-02", &["Galette des rois"]),
+        ]);
+        let week = catalogue.week(2025, 1).expect("week not found");
+        assert_eq!(
+            week.days.iter().map(Day::date).collect_vec(),
+            [date!(2024 - 12 - 30), date!(2025 - 01 - 02)]
+        );
+        assert!(matches!(catalogue.week(2024, 1), Err(Error::WeekNotFound)));
+        assert_eq!(catalogue.adjacent_weeks(2025, 1), (Some((2024, 52)), None));
+    }
+
+    #[test]
+    fn remove_week_across_years() {
+        let mut catalogue = Catalogue::new();
+        catalogue.restore(vec![
+            day("2024-01-02", &["Lasagnes"]),
+            day("2024-12-30", &["Poulet rôti"]),
+            day("2025-01-02", &["Galette des rois"]),
+        ]);
+        catalogue.remove_week(2024, 1).expect("week not found");
+        assert_eq!(
+            catalogue.dates().collect_vec(),
+            [date!(2024 - 12 - 30), date!(2025 - 01 - 02)]
+        );
+        catalogue.remove_week(2025, 1).expect("week not found");
+        assert_eq!(catalogue.dates().next(), None);
+    }
+
+    #[test]
+    fn site_pages_link_to_the_site() {
+        let mut catalogue = Catalogue::new();
+        catalogue.restore(vec![
+            day("2023-06-12", &["Poulet rôti"]),
+            day("2023-06-19", &["Lasagnes"]),
+        ]);
+        let weeks = catalogue.weeks().in_site("/sites/nord").as_html();
+        assert!(weeks.contains(r#"href="/sites/nord/weeks/2023-24""#));
+        let week = CatalogueWeek::new(
+            (2023, 24),
+            catalogue.week(2023, 24).expect("week not found"),
+            catalogue.adjacent_weeks(2023, 24),
+        )
+        .in_site("/sites/nord")
+        .as_html();
+        assert!(week.contains(r#"href="/sites/nord/days/2023-06-12""#));
+        assert!(week.contains(r#"href="/sites/nord/weeks/2023-25""#));
+        let month = catalogue.month(2023, Month::June).in_site("/sites/nord").as_html();
+        assert!(month.contains(r#"href="/sites/nord/days/2023-06-19""#));
+    }
+
+    #[test]
+    fn next_meal_date_skips_closed_days() {
+        // Today's meal is always over with a midnight cutoff.
+        let date = next_meal_date(time!(00:00));
+        let today = now_local().date();
+        assert!(date > today);
+        assert!(date - today <= Duration::weeks(1));
+        assert!(holidays::is_open(date));
+    }
+
+    #[test]
+    fn legacy_export_keeps_categories() {
+        let categorized = serde_json::from_value::<Day>(serde_json::json!({
+            "date": "2023-06-12",
+            "dishes": ["Carottes râpées", "Poulet rôti"],
+            "starters": ["Carottes râpées"],
+            "mains": ["Poulet rôti"],
+        }))
+        .expect("invalid day");
+        let mut catalogue = Catalogue::new();
+        catalogue.restore(vec![categorized]);
+        let export = day::LEGACY_SHAPE
+            .sync_scope(true, || serde_json::to_vec(&catalogue.export()))
+            .expect("export failed");
+        let export = serde_json::from_slice::<CatalogueExport>(&export).expect("import failed");
+        assert_eq!(export.days, catalogue.export().days);
+    }
+
+    #[test]
+    fn ics_round_trip() {
+        let days = vec![
+            day("2023-06-12", &["Carottes râpées", "Poulet rôti"]),
+            day("2023-06-13", &["Salade verte", "Lasagnes", "Yaourt"]),
+        ];
+        let mut catalogue = Catalogue::new();
+        catalogue.restore(days.clone());
+        let ics = catalogue
+            .ics("Menu", &Uuid::nil(), false, None)
+            .expect("calendar generation failed");
+        let parsed = parse_ics(&ics, &ParserConfig::default()).expect("ics parsing failed");
+        assert_eq!(parsed, days);
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::HashMap,
+    fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use serde::{de, Deserialize, Deserializer};
+use time::{macros::time, Date, Time};
+use uuid::Uuid;
+
+#[cfg(feature = "otlp")]
+use crate::telemetry::TelemetryConfig;
+#[cfg(feature = "translation-api")]
+use crate::translation::TranslationApiConfig;
+use crate::{
+    allergens::Allergen,
+    auth::ApiKeyConfig,
+    branding::BrandingConfig,
+    caldav::CalDavConfig,
+    connections::HttpConfig,
+    crawlers::CrawlersConfig,
+    inbox::ImapConfig,
+    catalogue::MergePolicy,
+    fallback::FallbackConfig,
+    icons::IconsConfig,
+    mailer::EmailConfig,
+    matrix::MatrixConfig,
+    mqtt::MqttConfig,
+    notifier::WebhookConfig,
+    push::WebPushConfig,
+    replication::ReplicationConfig,
+    storage::S3Config,
+    utils::{parse_date, parse_time},
+    vendor::VendorApiConfig,
+    webpage::ScraperConfig,
+    week::ParserConfig,
+};
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct Config {
+    #[serde(flatten)]
+    pub parser: ParserConfig,
+    // Added to the builtin allergen keywords.
+    pub allergen_keywords: HashMap<Allergen, Vec<String>>,
+    pub public_holidays: bool,
+    pub open_on_weekends: bool,
+    #[serde(deserialize_with = "deserialize_dates")]
+    pub closures: Vec<Date>,
+    pub timezone: Option<String>,
+    #[serde(deserialize_with = "deserialize_time")]
+    pub cutoff: Time,
+    pub default_site: String,
+    pub sites: Vec<String>,
+    pub merge_policy: MergePolicy,
+    pub require_force_on_conflict: bool,
+    // Uploads parsed at once, unlimited by default.
+    pub max_concurrent_uploads: Option<usize>,
+    // Seconds an upload waits for its turn before being rejected.
+    pub upload_queue_timeout: u64,
+    pub audit_log: Option<PathBuf>,
+    // Uploaded PDFs are kept there when set.
+    pub sources_dir: Option<PathBuf>,
+    // Menus loaded at startup, like the directories given on the command
+    // line.
+    pub import_dir: Option<PathBuf>,
+    // Moves them to `done` in the directory once loaded.
+    pub move_imported: bool,
+    // Keeps the uploaded PDFs and snapshots of the catalogues in a bucket.
+    pub s3: Option<S3Config>,
+    pub admin_token: Option<String>,
+    // Signs the admin session cookies of `/login`, which is disabled
+    // without it.
+    pub session_secret: Option<String>,
+    pub api_keys: Vec<ApiKeyConfig>,
+    // Requests per minute and client, unlimited by default.
+    pub rate_limit: Option<u32>,
+    pub read_requires_key: bool,
+    // Reverse proxies allowed to set the client address, scheme and host
+    // with `X-Forwarded-*` headers.
+    pub trusted_proxies: Vec<IpAddr>,
+    // Root URL of the instance in generated links, taken from the requests
+    // otherwise.
+    pub public_url: Option<String>,
+    pub http: HttpConfig,
+    pub crawlers: CrawlersConfig,
+    pub icons: IconsConfig,
+    pub branding: BrandingConfig,
+    // UUID namespace of the calendar event UIDs, should be unique per
+    // deployment sharing a calendar app.
+    pub calendar_namespace: Uuid,
+    pub calendar_main_dish_summary: bool,
+    pub caldav: Option<CalDavConfig>,
+    pub replication: Option<ReplicationConfig>,
+    pub imap: Option<ImapConfig>,
+    pub scrapers: Vec<ScraperConfig>,
+    pub vendor_api: Option<VendorApiConfig>,
+    pub fallback: Option<FallbackConfig>,
+    pub webhooks: Vec<WebhookConfig>,
+    // Signs outgoing webhook requests.
+    pub webhook_secret: Option<String>,
+    pub dead_letter_log: Option<PathBuf>,
+    #[serde(deserialize_with = "deserialize_time")]
+    pub announce_at: Time,
+    pub email: Option<EmailConfig>,
+    pub watches_file: Option<PathBuf>,
+    pub profiles_file: Option<PathBuf>,
+    pub ratings_log: Option<PathBuf>,
+    pub rsvp_file: Option<PathBuf>,
+    pub translation_glossary: Option<PathBuf>,
+    // Off without a table.
+    pub nutrition_table: Option<PathBuf>,
+    #[cfg(feature = "translation-api")]
+    pub translation_api: Option<TranslationApiConfig>,
+    pub web_push: Option<WebPushConfig>,
+    pub push_subscriptions_file: Option<PathBuf>,
+    pub mqtt: Option<MqttConfig>,
+    pub matrix: Option<MatrixConfig>,
+    // Requests from other skills are rejected when set.
+    pub alexa_skill_id: Option<String>,
+    #[cfg(feature = "otlp")]
+    pub telemetry: Option<TelemetryConfig>,
+}
+
+impl Config {
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        match path {
+            Some(path) => toml::from_str(&fs::read_to_string(path).map_err(|err| err.to_string())?)
+                .map_err(|err| err.to_string()),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            parser: ParserConfig::default(),
+            allergen_keywords: HashMap::new(),
+            public_holidays: true,
+            open_on_weekends: false,
+            closures: Vec::new(),
+            timezone: None,
+            cutoff: time!(14:00),
+            default_site: "default".to_owned(),
+            sites: Vec::new(),
+            merge_policy: MergePolicy::default(),
+            require_force_on_conflict: false,
+            max_concurrent_uploads: None,
+            upload_queue_timeout: 30,
+            audit_log: None,
+            sources_dir: None,
+            import_dir: None,
+            move_imported: false,
+            s3: None,
+            admin_token: None,
+            session_secret: None,
+            api_keys: Vec::new(),
+            rate_limit: None,
+            read_requires_key: false,
+            trusted_proxies: Vec::new(),
+            public_url: None,
+            http: HttpConfig::default(),
+            crawlers: CrawlersConfig::default(),
+            icons: IconsConfig::default(),
+            branding: BrandingConfig::default(),
+            calendar_namespace: Uuid::nil(),
+            calendar_main_dish_summary: false,
+            caldav: None,
+            replication: None,
+            imap: None,
+            scrapers: Vec::new(),
+            vendor_api: None,
+            fallback: None,
+            webhooks: Vec::new(),
+            webhook_secret: None,
+            dead_letter_log: None,
+            announce_at: time!(10:00),
+            email: None,
+            watches_file: None,
+            profiles_file: None,
+            ratings_log: None,
+            rsvp_file: None,
+            translation_glossary: None,
+            nutrition_table: None,
+            #[cfg(feature = "translation-api")]
+            translation_api: None,
+            web_push: None,
+            push_subscriptions_file: None,
+            mqtt: None,
+            alexa_skill_id: None,
+            matrix: None,
+            #[cfg(feature = "otlp")]
+            telemetry: None,
+        }
+    }
+}
+
+fn deserialize_time<'de, D>(deserializer: D) -> Result<Time, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let input = String::deserialize(deserializer)?;
+    parse_time(&input).ok_or_else(|| de::Error::custom("invalid time"))
+}
+
+fn deserialize_dates<'de, D>(deserializer: D) -> Result<Vec<Date>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|input| parse_date(input).ok_or_else(|| de::Error::custom("invalid date")))
+        .collect()
+}
@@ -0,0 +1,128 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use tokio::sync::{AcquireError, OwnedSemaphorePermit, Semaphore};
+use tower::Service;
+
+// Options of the HTTP server, the defaults being hyper's.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct HttpConfig {
+    // Also accepts HTTP/2 without TLS, for reverse proxies and clients
+    // supporting it.
+    pub http2: bool,
+    // Keeps HTTP/1 connections open between requests.
+    pub keep_alive: bool,
+    // TCP keep-alive probes interval in seconds, to drop dead clients.
+    pub tcp_keepalive: Option<u64>,
+    // Seconds allowed to send the request headers.
+    pub header_read_timeout: Option<u64>,
+    // Connections served at once, the next ones wait to be accepted.
+    pub max_connections: Option<usize>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            http2: false,
+            keep_alive: true,
+            tcp_keepalive: None,
+            header_read_timeout: None,
+            max_connections: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn tcp_keepalive(&self) -> Option<Duration> {
+        self.tcp_keepalive.map(Duration::from_secs)
+    }
+
+    pub fn header_read_timeout(&self) -> Option<Duration> {
+        self.header_read_timeout.map(Duration::from_secs)
+    }
+}
+
+type Acquiring = Pin<Box<dyn Future<Output = Result<OwnedSemaphorePermit, AcquireError>> + Send>>;
+
+// Makes the service of a connection only once fewer than the maximum are
+// open, which stops the server from accepting new ones meanwhile.
+pub struct ConnectionLimit<M> {
+    inner: M,
+    semaphore: Arc<Semaphore>,
+    acquiring: Option<Acquiring>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl<M> ConnectionLimit<M> {
+    pub fn new(inner: M, max: Option<usize>) -> Self {
+        Self {
+            inner,
+            semaphore: Arc::new(Semaphore::new(max.unwrap_or(Semaphore::MAX_PERMITS))),
+            acquiring: None,
+            permit: None,
+        }
+    }
+}
+
+impl<M, T> Service<T> for ConnectionLimit<M>
+where
+    M: Service<T>,
+    M::Future: Send + 'static,
+{
+    type Response = Limited<M::Response>;
+    type Error = M::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.permit.is_none() {
+            let acquiring = self
+                .acquiring
+                .get_or_insert_with(|| Box::pin(Arc::clone(&self.semaphore).acquire_owned()));
+            let permit = ready!(acquiring.as_mut().poll(cx)).expect("connection limit closed");
+            self.acquiring = None;
+            self.permit = Some(permit);
+        }
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, target: T) -> Self::Future {
+        let permit = self.permit.take().expect("connection limit not ready");
+        let making = self.inner.call(target);
+        Box::pin(async move {
+            Ok(Limited {
+                inner: making.await?,
+                _permit: permit,
+            })
+        })
+    }
+}
+
+// The service of a connection, releasing its place once closed.
+pub struct Limited<S> {
+    inner: S,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<S, R> Service<R> for Limited<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.inner.call(request)
+    }
+}
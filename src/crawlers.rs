@@ -0,0 +1,66 @@
+use itertools::Itertools;
+use serde::Deserialize;
+
+use crate::{
+    catalogue::week_of,
+    proxy::Origin,
+    site::Sites,
+    utils::{escape, format_date},
+};
+
+// Answers of `/robots.txt` and `/sitemap.xml`. Crawlers are kept away unless
+// the instance is meant to be public.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct CrawlersConfig {
+    // Lets crawlers index every page.
+    pub allow: bool,
+    // Served as is instead of the generated `robots.txt`.
+    pub robots_txt: Option<String>,
+    // Lists the week and day pages of every site at `/sitemap.xml`.
+    pub sitemap: bool,
+}
+
+impl CrawlersConfig {
+    pub fn robots_txt(&self, origin: &Origin) -> String {
+        if let Some(robots_txt) = &self.robots_txt {
+            return robots_txt.clone();
+        }
+        let mut robots_txt = format!(
+            "User-agent: *\nDisallow: {}\n",
+            if self.allow { "" } else { "/" }
+        );
+        if self.sitemap {
+            robots_txt += &format!("Sitemap: {}\n", origin.url("/sitemap.xml"));
+        }
+        robots_txt
+    }
+}
+
+pub async fn sitemap(sites: &Sites, origin: &Origin) -> String {
+    let mut urls = Vec::new();
+    for site in sites.all() {
+        let root = origin.site_url(site.name.as_deref());
+        let catalogue = site.catalogue.read().await;
+        let dates = catalogue.dates().collect_vec();
+        // Weeks are numbered like the `/weeks` routes parse them.
+        for (year, week) in dates.iter().map(|&date| week_of(date)).dedup() {
+            urls.push(format!("{root}/weeks/{year}-{week:02}"));
+        }
+        for &date in &dates {
+            urls.push(format!("{root}/days/{}", format_date(date)));
+        }
+    }
+
+    let mut sitemap = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+        "\n",
+        r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#,
+        "\n",
+    ));
+    for url in urls {
+        sitemap += &format!("<url><loc>{}</loc></url>\n", escape(&url));
+    }
+    sitemap += "</urlset>\n";
+    sitemap
+}
@@ -1,17 +1,203 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem,
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+};
+
 use itertools::Itertools;
-use serde::{ser::SerializeStruct, Serialize, Serializer};
-use time::{Date, Duration, Month, OffsetDateTime, Weekday};
+use serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::json;
+use time::{macros::time, Date, Duration, Month, OffsetDateTime, Time, Weekday};
 
 use crate::{
+    allergens::{self, Allergen},
     error::Error,
-    response::TextRepresentable,
-    utils::{format_date, now_local},
+    holidays,
+    normalize::NormalizationConfig,
+    nutrition::{self, Estimate},
+    text::{Language, TextRepresentable, TextStyle},
+    utils::{escape, format_date, now_local, parse_date},
 };
 
-#[derive(Clone, Debug)]
+static CUTOFF: OnceLock<Time> = OnceLock::new();
+
+// The end of the meal, past which today isn't flagged `is_today` anymore.
+// Must be called before the first day is serialized, later calls fail.
+pub fn configure(cutoff: Time) -> Result<(), String> {
+    CUTOFF
+        .set(cutoff)
+        .map_err(|_| "cutoff already configured".to_owned())
+}
+
+fn cutoff() -> Time {
+    *CUTOFF.get_or_init(|| time!(14:00))
+}
+
+#[derive(Clone, PartialEq, Debug)]
 pub struct Day {
     date: Date,
-    dishes: Vec<String>,
+    dishes: Vec<Dish>,
+    notes: Vec<String>,
+    // Of the weekday, set when translated.
+    language: Language,
+    // Path prefix of the site linked to, see `Site::path_prefix`.
+    root: String,
+}
+
+static NAMES: OnceLock<Mutex<HashSet<Arc<str>>>> = OnceLock::new();
+
+fn names() -> MutexGuard<'static, HashSet<Arc<str>>> {
+    NAMES
+        .get_or_init(Default::default)
+        .lock()
+        .expect("dish names poisoned")
+}
+
+// The same dishes come back every few weeks and in every site, so their names
+// are shared rather than stored once per day.
+fn intern(name: &str) -> Arc<str> {
+    let mut names = names();
+    match names.get(name) {
+        Some(interned) => Arc::clone(interned),
+        None => {
+            let interned = Arc::<str>::from(name);
+            names.insert(Arc::clone(&interned));
+            interned
+        }
+    }
+}
+
+// Forgets the names no dish uses anymore, like those of replaced days or
+// rejected uploads. Called by catalogues when they drop days.
+pub fn prune_names() {
+    names().retain(|name| Arc::strong_count(name) > 1);
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Dish {
+    name: Arc<str>,
+    category: Option<DishCategory>,
+    // Set by hand, replaces the detected allergens.
+    allergens: Option<Vec<Allergen>>,
+    // The name as read from the upload, when normalization changed it.
+    raw: Option<String>,
+    // Near-duplicates dropped in favor of this dish, see `Day::normalize`.
+    merged: Vec<String>,
+    // The French name of a translated dish.
+    original: Option<String>,
+}
+
+impl Dish {
+    fn new(name: String) -> Self {
+        Self {
+            name: intern(&name),
+            category: None,
+            allergens: None,
+            raw: None,
+            merged: Vec::new(),
+            original: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn category(&self) -> Option<DishCategory> {
+        self.category
+    }
+
+    pub fn merged(&self) -> &[String] {
+        &self.merged
+    }
+
+    pub fn allergens(&self) -> Vec<Allergen> {
+        match &self.allergens {
+            Some(allergens) => allergens.clone(),
+            None => allergens::detect(&self.name),
+        }
+    }
+
+    pub fn nutrition(&self) -> Option<Estimate> {
+        nutrition::estimate(self.original.as_deref().unwrap_or(&self.name))
+    }
+
+    fn as_html(&self) -> String {
+        let title = self
+            .nutrition()
+            .map(|estimate| format!(r#" title="{}""#, estimate.as_fr_str()))
+            .unwrap_or_default();
+        format!(
+            r#"<div class="dish"{title}>{}{}</div>"#,
+            escape(&self.name),
+            self.allergens()
+                .iter()
+                .map(|allergen| {
+                    format!(r#"<span class="allergen">{}</span>"#, allergen.as_fr_str())
+                })
+                .collect::<String>()
+        )
+    }
+
+    pub fn contains_any(&self, allergens: &[Allergen]) -> bool {
+        !allergens.is_empty()
+            && self
+                .allergens()
+                .iter()
+                .any(|allergen| allergens.contains(allergen))
+    }
+}
+
+// Sections of the menu, in the order they are printed.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum DishCategory {
+    Starter,
+    Main,
+    Side,
+    Cheese,
+    Dessert,
+}
+
+impl DishCategory {
+    pub const ALL: [DishCategory; 5] = [
+        DishCategory::Starter,
+        DishCategory::Main,
+        DishCategory::Side,
+        DishCategory::Cheese,
+        DishCategory::Dessert,
+    ];
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    pub fn from_field_str(field: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|category| category.as_field_str() == field)
+    }
+
+    // Field of the day listing the dishes of the category in JSON, as the
+    // catering API names them.
+    pub fn as_field_str(&self) -> &'static str {
+        match self {
+            DishCategory::Starter => "starters",
+            DishCategory::Main => "mains",
+            DishCategory::Side => "sides",
+            DishCategory::Cheese => "cheeses",
+            DishCategory::Dessert => "desserts",
+        }
+    }
+
+    pub fn as_fr_str(&self) -> &'static str {
+        match self {
+            DishCategory::Starter => "Entrées",
+            DishCategory::Main => "Plats",
+            DishCategory::Side => "Accompagnements",
+            DishCategory::Cheese => "Fromages",
+            DishCategory::Dessert => "Desserts",
+        }
+    }
 }
 
 impl Day {
@@ -51,54 +237,471 @@ impl Day {
 
         Ok(Some(Self {
             date,
-            dishes: fields[1..].to_vec(),
+            dishes: fields[1..].iter().cloned().map(Dish::new).collect(),
+            notes: Vec::new(),
+            language: Language::French,
+            root: String::new(),
         }))
     }
 
+    // Categories of the dishes, in the same order.
+    pub fn with_categories(mut self, categories: Vec<Option<DishCategory>>) -> Self {
+        for (dish, category) in self.dishes.iter_mut().zip(categories) {
+            dish.category = category;
+        }
+        self
+    }
+
     pub fn date(&self) -> Date {
         self.date
     }
 
-    pub fn replace_dishes(&mut self, dishes: Vec<String>) {
-        self.dishes = dishes;
+    pub fn dishes_ref(&self) -> &[Dish] {
+        &self.dishes
+    }
+
+    pub fn has_dish(&self, name: &str) -> bool {
+        self.dishes.iter().any(|dish| &*dish.name == name)
     }
 
-    pub fn dishes(self) -> Vec<String> {
+    // Dishes grouped by category, uncategorized ones first.
+    pub fn dishes_by_category(&self) -> Vec<(Option<DishCategory>, Vec<&str>)> {
         self.dishes
+            .iter()
+            .map(|dish| (dish.category, dish.name()))
+            .into_group_map()
+            .into_iter()
+            .sorted_by_key(|(category, _)| *category)
+            .collect()
     }
 
-    pub fn dishes_ref(&self) -> &[String] {
-        &self.dishes
+    pub fn retain_dishes(&mut self, keep: impl FnMut(&Dish) -> bool) {
+        self.dishes.retain(keep);
+    }
+
+    // `None` goes back to the detected allergens.
+    pub fn set_allergens(
+        &mut self,
+        index: usize,
+        allergens: Option<Vec<Allergen>>,
+    ) -> Result<(), Error> {
+        let dish = self.dishes.get_mut(index).ok_or(Error::DishNotFound)?;
+        dish.allergens =
+            allergens.map(|allergens| allergens.into_iter().sorted().dedup().collect());
+        Ok(())
+    }
+
+    pub fn main_dish(&self) -> Option<&Dish> {
+        self.dishes
+            .iter()
+            .find(|dish| dish.category == Some(DishCategory::Main))
+    }
+
+    // schema.org description of the menu, for search appliances and link
+    // previews, with a section per category.
+    fn json_ld(&self) -> String {
+        let items = |dishes: Vec<&str>| {
+            dishes
+                .into_iter()
+                .map(|name| json!({"@type": "MenuItem", "name": name}))
+                .collect_vec()
+        };
+        let mut menu = json!({
+            "@context": "https://schema.org",
+            "@type": "Menu",
+            "name": format_long_date(self.date),
+            "inLanguage": match self.language {
+                Language::French => "fr",
+                Language::English => "en",
+            },
+        });
+        let (sections, uncategorized): (Vec<_>, Vec<_>) = self
+            .dishes_by_category()
+            .into_iter()
+            .partition(|(category, _)| category.is_some());
+        if !sections.is_empty() {
+            menu["hasMenuSection"] = sections
+                .into_iter()
+                .filter_map(|(category, dishes)| {
+                    Some(json!({
+                        "@type": "MenuSection",
+                        "name": category?.as_fr_str(),
+                        "hasMenuItem": items(dishes),
+                    }))
+                })
+                .collect();
+        }
+        if let Some((_, dishes)) = uncategorized.into_iter().next() {
+            menu["hasMenuItem"] = items(dishes).into();
+        }
+        // Dish names can't close the script.
+        format!(
+            r#"<script type="application/ld+json">{}</script>"#,
+            menu.to_string().replace("</", r"<\/")
+        )
+    }
+
+    // One paragraph per category, with its title, then the notes. Days
+    // without categories are listed like the plain text.
+    pub fn as_sectioned_text(&self) -> String {
+        if self.dishes.iter().all(|dish| dish.category.is_none()) {
+            return self.as_plain_text(false);
+        }
+        self.dishes_by_category()
+            .into_iter()
+            .map(|(category, dishes)| match category {
+                Some(category) => format!("{} :\n{}", category.as_fr_str(), dishes.join("\n")),
+                None => dishes.join("\n"),
+            })
+            .chain((!self.notes.is_empty()).then(|| self.notes.join("\n")))
+            .join("\n\n")
+    }
+
+    // Like "Lundi 12 juin".
+    pub fn long_date(&self) -> String {
+        format!(
+            "{} {} {}",
+            weekday_as_fr_str(self.date.weekday(), true),
+            self.date.day(),
+            month_as_fr_str(self.date.month())
+        )
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    // Adds the dishes and notes of `other` that aren't already listed.
+    pub fn merge(&mut self, other: Day) {
+        for dish in other.dishes {
+            if !self.has_dish(&dish.name) {
+                self.dishes.push(dish);
+            }
+        }
+        for note in other.notes {
+            if !self.notes.contains(&note) {
+                self.notes.push(note);
+            }
+        }
+    }
+
+    // Cleans up dish names, dropping the empty ones. Near-duplicates, in any
+    // category, are merged into the first one and kept in its `merged` list.
+    pub fn normalize(&mut self, config: &NormalizationConfig) {
+        let dishes = mem::take(&mut self.dishes);
+        for mut dish in dishes {
+            let name = config.normalize(&dish.name);
+            if name.is_empty() {
+                continue;
+            }
+            if let Some(kept) = self
+                .dishes
+                .iter_mut()
+                .find(|kept| config.is_duplicate(&kept.name, &name))
+            {
+                if *kept.name != name && !kept.merged.contains(&name) {
+                    kept.merged.push(name);
+                }
+                continue;
+            }
+            if name != *dish.name {
+                let raw = mem::replace(&mut dish.name, intern(&name)).to_string();
+                if config.keep_raw {
+                    dish.raw = dish.raw.or(Some(raw));
+                }
+            }
+            self.dishes.push(dish);
+        }
+    }
+
+    pub fn texts(&self) -> Vec<String> {
+        self.dishes
+            .iter()
+            .map(|dish| dish.name.to_string())
+            .chain(self.notes.iter().cloned())
+            .collect()
+    }
+
+    // Links the day to its page on the site under `root`.
+    pub fn in_site(mut self, root: &str) -> Self {
+        self.root = root.to_owned();
+        self
+    }
+
+    // Renames dishes and notes found in `translations`. Allergens are
+    // detected from French names, so they are kept from before.
+    pub fn translated(
+        mut self,
+        language: Language,
+        translations: &HashMap<String, String>,
+    ) -> Self {
+        self.language = language;
+        for dish in &mut self.dishes {
+            if let Some(translation) = translations.get(&*dish.name) {
+                dish.allergens = Some(dish.allergens());
+                let name = mem::replace(&mut dish.name, intern(translation));
+                dish.original = dish.original.take().or_else(|| Some(name.to_string()));
+            }
+        }
+        for note in &mut self.notes {
+            if let Some(translation) = translations.get(note) {
+                *note = translation.clone();
+            }
+        }
+        self
+    }
+
+    // Moves lines like "Restaurant fermé" out of the dishes.
+    pub fn extract_notes(&mut self, keywords: &[String]) {
+        let keywords = keywords.iter().map(|k| k.to_lowercase()).collect_vec();
+        let (notes, dishes): (Vec<_>, Vec<_>) =
+            mem::take(&mut self.dishes).into_iter().partition(|dish| {
+                let dish = dish.name.to_lowercase();
+                keywords.iter().any(|k| dish.contains(k))
+            });
+        self.dishes = dishes;
+        self.notes.extend(notes.into_iter().map(|dish| dish.name.to_string()));
     }
 }
 
-impl Serialize for Day {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+#[cfg(feature = "server")]
+tokio::task_local! {
+    // True while answering the routes served at the root, whose days keep the
+    // shape they had before `/api/v1`.
+    pub static LEGACY_SHAPE: bool;
+}
+
+// Whether days list their dishes by category, everywhere but in the answers
+// of the legacy routes.
+pub fn lists_categories() -> bool {
+    #[cfg(feature = "server")]
+    let legacy = LEGACY_SHAPE.try_with(|&legacy| legacy).unwrap_or(false);
+    #[cfg(not(feature = "server"))]
+    let legacy = false;
+    !legacy
+}
+
+// Days with their categories on every route, for backups imported back.
+pub fn serialize_with_categories<S>(days: &[Day], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    #[cfg(feature = "server")]
+    {
+        LEGACY_SHAPE.sync_scope(false, || days.serialize(serializer))
+    }
+    #[cfg(not(feature = "server"))]
+    {
+        days.serialize(serializer)
+    }
+}
+
+impl Day {
+    fn serialize_fields<S>(&self, serializer: S, relative: bool) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("Day", 3)?;
+        let mut state = serializer.serialize_struct("Day", 15)?;
         state.serialize_field("date", &format_date(self.date))?;
-        state.serialize_field("dishes", &self.dishes)?;
+        if relative {
+            let now = now_local();
+            state.serialize_field(
+                "weekday",
+                &match self.language {
+                    Language::French => weekday_as_fr_str(self.date.weekday(), false).to_owned(),
+                    Language::English => self.date.weekday().to_string(),
+                },
+            )?;
+            state.serialize_field(
+                "is_today",
+                &(self.date == now.date() && now.time() < cutoff()),
+            )?;
+            state.serialize_field("days_from_now", &(self.date - now.date()).whole_days())?;
+        } else {
+            state.skip_field("weekday")?;
+            state.skip_field("is_today")?;
+            state.skip_field("days_from_now")?;
+        }
+        state.serialize_field(
+            "dishes",
+            &self.dishes.iter().map(Dish::name).collect_vec(),
+        )?;
+        let categories = !relative || lists_categories();
+        for category in DishCategory::ALL {
+            let dishes = self
+                .dishes
+                .iter()
+                .filter(|dish| dish.category == Some(category))
+                .map(Dish::name)
+                .collect_vec();
+            if !categories || dishes.is_empty() {
+                state.skip_field(category.as_field_str())?;
+            } else {
+                state.serialize_field(category.as_field_str(), &dishes)?;
+            }
+        }
+        if self.notes.is_empty() {
+            state.skip_field("notes")?;
+        } else {
+            state.serialize_field("notes", &self.notes)?;
+        }
+        let allergens = self
+            .dishes
+            .iter()
+            .map(|dish| (dish.name(), dish.allergens()))
+            .filter(|(_, allergens)| !allergens.is_empty())
+            .collect::<BTreeMap<_, _>>();
+        if allergens.is_empty() {
+            state.skip_field("allergens")?;
+        } else {
+            state.serialize_field("allergens", &allergens)?;
+        }
+        let nutrition = self
+            .dishes
+            .iter()
+            .filter_map(|dish| Some((dish.name(), dish.nutrition()?)))
+            .collect::<BTreeMap<_, _>>();
+        if nutrition.is_empty() {
+            state.skip_field("nutrition")?;
+        } else {
+            state.serialize_field("nutrition", &nutrition)?;
+        }
+        let raw = self
+            .dishes
+            .iter()
+            .filter_map(|dish| Some((dish.name(), dish.raw.as_deref()?)))
+            .collect::<BTreeMap<_, _>>();
+        if raw.is_empty() {
+            state.skip_field("raw")?;
+        } else {
+            state.serialize_field("raw", &raw)?;
+        }
+        let merged = self
+            .dishes
+            .iter()
+            .filter(|dish| !dish.merged.is_empty())
+            .map(|dish| (dish.name(), &dish.merged))
+            .collect::<BTreeMap<_, _>>();
+        if merged.is_empty() {
+            state.skip_field("merged")?;
+        } else {
+            state.serialize_field("merged", &merged)?;
+        }
         state.end()
     }
 }
 
+impl Serialize for Day {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.serialize_fields(serializer, true)
+    }
+}
+
+// A day without the fields depending on when it is answered, so the version
+// of a catalogue only changes with its days.
+pub struct StoredDay<'a>(pub &'a Day);
+
+impl Serialize for StoredDay<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize_fields(serializer, false)
+    }
+}
+
+impl<'de> Deserialize<'de> for Day {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct DayRecord {
+            date: String,
+            dishes: Vec<String>,
+            #[serde(default)]
+            notes: Vec<String>,
+            // Allergens listed for a dish replace the detected ones.
+            #[serde(default)]
+            allergens: HashMap<String, Vec<Allergen>>,
+            #[serde(default)]
+            raw: HashMap<String, String>,
+            #[serde(default)]
+            merged: HashMap<String, Vec<String>>,
+            // Categories of the dishes, listed in `dishes` too.
+            #[serde(default)]
+            starters: Vec<String>,
+            #[serde(default)]
+            mains: Vec<String>,
+            #[serde(default)]
+            sides: Vec<String>,
+            #[serde(default)]
+            cheeses: Vec<String>,
+            #[serde(default)]
+            desserts: Vec<String>,
+        }
+
+        let mut record = DayRecord::deserialize(deserializer)?;
+        let mut categories = HashMap::new();
+        let listed = [
+            record.starters,
+            record.mains,
+            record.sides,
+            record.cheeses,
+            record.desserts,
+        ];
+        for (category, dishes) in DishCategory::ALL.into_iter().zip(listed) {
+            for dish in dishes {
+                categories.entry(dish).or_insert(category);
+            }
+        }
+        Ok(Self {
+            date: parse_date(&record.date).ok_or_else(|| de::Error::custom("invalid date"))?,
+            dishes: record
+                .dishes
+                .into_iter()
+                .map(|name| Dish {
+                    category: categories.get(&name).copied(),
+                    allergens: record.allergens.remove(&name),
+                    raw: record.raw.remove(&name),
+                    merged: record.merged.remove(&name).unwrap_or_default(),
+                    ..Dish::new(name)
+                })
+                .collect(),
+            notes: record.notes,
+            language: Language::French,
+            root: String::new(),
+        })
+    }
+}
+
 impl TextRepresentable for Day {
     fn as_plain_text(&self, human: bool) -> String {
+        self.as_styled_text(human, TextStyle::Plain)
+    }
+
+    // The main courses emphasized, when the upload tells them apart.
+    fn as_styled_text(&self, human: bool, style: TextStyle) -> String {
         if human {
-            let dishes_str = if self.dishes.len() >= 2 {
-                format!(
-                    "{} et {}",
-                    self.dishes.iter().dropping_back(1).join(", "),
-                    self.dishes.last().unwrap(),
-                )
-            } else {
-                self.dishes.iter().join(", ")
-            };
-            format!("Au menu {} : {}.", format_human_date(self.date), dishes_str)
+            let dishes_str = join_human(&self.dishes.iter().map(Dish::name).collect_vec());
+            let menu_str = (!self.dishes.is_empty())
+                .then(|| format!("Au menu {} : {}.", format_human_date(self.date), dishes_str));
+            menu_str
+                .into_iter()
+                .chain(self.notes.iter().map(|note| format!("{note}.")))
+                .join(" ")
         } else {
-            self.dishes.iter().join("\n")
+            self.dishes
+                .iter()
+                .map(|dish| match dish.category {
+                    Some(DishCategory::Main) => style.main_course(dish.name()),
+                    _ => dish.name().to_owned(),
+                })
+                .chain(self.notes.iter().cloned())
+                .join("\n")
         }
     }
 
@@ -112,23 +715,64 @@ impl TextRepresentable for Day {
         format!(
             r#"
             <div class="day {class_str}">
-                <a href="/days/{}">{} {} {} {}</a>
-                {}
+                <a href="{}/days/{}">{}</a>
+                {}{}{}{}
             </div>
         "#,
+            escape(&self.root),
             format_date(self.date),
-            weekday_as_fr_str(self.date.weekday(), true),
-            self.date.day(),
-            month_as_fr_str(self.date.month()),
-            self.date.year(),
-            self.dishes
+            format_long_date(self.date),
+            holidays::closure(self.date)
+                .map(|reason| format!(r#"<div class="holiday">{reason}</div>"#))
+                .unwrap_or_default(),
+            self.dishes.iter().map(Dish::as_html).collect::<String>(),
+            self.notes
                 .iter()
-                .map(|dish| format!(r#"<div class="dish">{dish}</div>"#))
-                .collect::<String>()
+                .map(|note| format!(r#"<div class="note">{}</div>"#, escape(note)))
+                .collect::<String>(),
+            self.json_ld()
         )
     }
 }
 
+// A day answered by `/next` or `/find`, with how far away it is so clients
+// can phrase it themselves.
+pub struct UpcomingDay(pub Day);
+
+impl Serialize for UpcomingDay {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Upcoming<'a> {
+            #[serde(flatten)]
+            day: &'a Day,
+            days_until: i64,
+        }
+
+        Upcoming {
+            day: &self.0,
+            days_until: (self.0.date - now_local().date()).whole_days(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl TextRepresentable for UpcomingDay {
+    fn as_plain_text(&self, human: bool) -> String {
+        self.0.as_plain_text(human)
+    }
+
+    fn as_styled_text(&self, human: bool, style: TextStyle) -> String {
+        self.0.as_styled_text(human, style)
+    }
+
+    fn as_html(&self) -> String {
+        self.0.as_html()
+    }
+}
+
 fn parse_fr_weekday_str(weekday: &str) -> Option<Weekday> {
     match weekday.to_lowercase().as_str() {
         "lundi" => Some(Weekday::Monday),
@@ -189,12 +833,85 @@ fn month_as_fr_str(month: Month) -> &'static str {
     }
 }
 
+// Like "Juin 2023".
+pub fn format_month(year: i32, month: Month) -> String {
+    let mut chars = month_as_fr_str(month).chars();
+    let month = chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+        .unwrap_or_default();
+    format!("{month} {year}")
+}
+
+// Like "Lundi".
+pub fn format_weekday(weekday: Weekday) -> &'static str {
+    weekday_as_fr_str(weekday, true)
+}
+
+// Like "Lundi 12 juin 2023".
+pub fn format_long_date(date: Date) -> String {
+    format!(
+        "{} {} {} {}",
+        weekday_as_fr_str(date.weekday(), true),
+        date.day(),
+        month_as_fr_str(date.month()),
+        date.year()
+    )
+}
+
+// Like "lundi, carottes râpées et poulet", for sentences about a whole week.
+pub fn format_human_weekday(day: &Day) -> String {
+    let weekday = weekday_as_fr_str(day.date.weekday(), false);
+    if day.dishes.is_empty() {
+        return format!("{weekday}, {}", day.notes.join(", "));
+    }
+    format!(
+        "{weekday}, {}",
+        join_human(&day.dishes.iter().map(Dish::name).collect_vec())
+    )
+}
+
+// "cette semaine", "la semaine prochaine" or "la semaine du 12 juin", from
+// its Monday.
+pub fn format_human_week(monday: Date, titlecase: bool) -> String {
+    let today = now_local().date();
+    let current = today - Duration::days(today.weekday().number_days_from_monday().into());
+    let week = match (monday - current).whole_weeks() {
+        0 => "cette semaine".to_owned(),
+        1 => "la semaine prochaine".to_owned(),
+        -1 => "la semaine dernière".to_owned(),
+        _ => format!(
+            "la semaine du {} {}",
+            monday.day(),
+            month_as_fr_str(monday.month())
+        ),
+    };
+    if !titlecase {
+        return week;
+    }
+    let mut chars = week.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+// "a, b et c".
+pub fn join_human(items: &[&str]) -> String {
+    match items.split_last() {
+        Some((last, others)) if !others.is_empty() => {
+            format!("{} et {last}", others.join(", "))
+        }
+        _ => items.join(", "),
+    }
+}
+
 fn format_human_date(date: Date) -> String {
     let today = now_local().date();
     if date == today {
         return "aujourd'hui".to_owned();
     }
-    if today.next_day().expect("failed to compute human date") == date {
+    if today.next_day() == Some(date) {
         return "demain".to_owned();
     }
     let diff = date - today;
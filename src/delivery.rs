@@ -0,0 +1,209 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use hmac::{Hmac, Mac};
+use itertools::Itertools;
+use reqwest::{Client, Request, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use time::format_description::well_known::Rfc3339;
+use tokio::{sync::RwLock, time::sleep};
+
+use crate::{response::TextRepresentable, utils::now_local};
+
+const MAX_ATTEMPTS: u32 = 5;
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+// Sends outgoing HTTP notifications in the background, retrying with an
+// exponential backoff. Deliveries that never succeed end up in the dead
+// letters.
+#[derive(Clone, Debug)]
+pub struct Deliverer {
+    client: Client,
+    secret: Option<String>,
+    dead_letters: Arc<RwLock<DeadLetters>>,
+}
+
+impl Deliverer {
+    pub fn new(secret: Option<String>, dead_letters: Arc<RwLock<DeadLetters>>) -> Self {
+        Self {
+            client: Client::new(),
+            secret,
+            dead_letters,
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn spawn(&self, request: RequestBuilder) {
+        let deliverer = self.clone();
+        tokio::spawn(async move {
+            let request = match request.build() {
+                Ok(request) => deliverer.sign(request),
+                Err(err) => {
+                    eprintln!("delivery: {err}");
+                    return;
+                }
+            };
+            let url = request.url().to_string();
+            if let Err(error) = deliverer.deliver(request).await {
+                eprintln!("delivery to {url} failed: {error}");
+                deliverer.dead_letters.write().await.record(url, error);
+            }
+        });
+    }
+
+    // Receivers can check `X-Ovr-Signature`, the hex HMAC-SHA256 of the
+    // timestamp header, a dot and the body.
+    fn sign(&self, mut request: Request) -> Request {
+        let Some(secret) = &self.secret else {
+            return request;
+        };
+        let timestamp = now_local().unix_timestamp().to_string();
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("invalid hmac key length");
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(
+            request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .unwrap_or_default(),
+        );
+        let signature = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+
+        let headers = request.headers_mut();
+        headers.insert(
+            "X-Ovr-Timestamp",
+            timestamp.parse().expect("invalid timestamp header"),
+        );
+        headers.insert(
+            "X-Ovr-Signature",
+            format!("sha256={signature}")
+                .parse()
+                .expect("invalid signature header"),
+        );
+        request
+    }
+
+    async fn deliver(&self, request: Request) -> Result<(), String> {
+        let mut delay = FIRST_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let Some(attempt_request) = request.try_clone() else {
+                return Err("request can't be retried".to_owned());
+            };
+            let error = match self.client.execute(attempt_request).await {
+                Ok(response) => match response.error_for_status() {
+                    Ok(_) => return Ok(()),
+                    Err(err) => err.to_string(),
+                },
+                Err(err) => err.to_string(),
+            };
+            if attempt == MAX_ATTEMPTS {
+                return Err(format!("{error} (after {attempt} attempts)"));
+            }
+            sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DeadLetters {
+    entries: Vec<FailedDelivery>,
+    file: Option<File>,
+}
+
+impl DeadLetters {
+    // Same format as the audit log, one JSON entry per line.
+    pub fn open(path: Option<&Path>) -> Result<Self, String> {
+        let Some(path) = path else {
+            return Ok(Self {
+                entries: Vec::new(),
+                file: None,
+            });
+        };
+        let entries = match fs::read_to_string(path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| format!("invalid dead letter log: {err}"))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.to_string()),
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            entries,
+            file: Some(file),
+        })
+    }
+
+    fn record(&mut self, url: String, error: String) {
+        let entry = FailedDelivery {
+            timestamp: now_local()
+                .format(&Rfc3339)
+                .expect("delivery date formatting failed"),
+            url,
+            error,
+        };
+        if let Some(file) = &mut self.file {
+            let line = serde_json::to_string(&entry).expect("delivery serialization failed");
+            if let Err(err) = writeln!(file, "{line}") {
+                eprintln!("failed to write dead letter log: {err}");
+            }
+        }
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> FailedDeliveries {
+        FailedDeliveries {
+            deliveries: self.entries.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FailedDelivery {
+    timestamp: String,
+    url: String,
+    error: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FailedDeliveries {
+    deliveries: Vec<FailedDelivery>,
+}
+
+impl TextRepresentable for FailedDeliveries {
+    fn as_plain_text(&self, _human: bool) -> String {
+        self.deliveries
+            .iter()
+            .map(|delivery| {
+                format!(
+                    "{} {}: {}",
+                    delivery.timestamp, delivery.url, delivery.error
+                )
+            })
+            .join("\n")
+    }
+}
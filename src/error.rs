@@ -1,3 +1,6 @@
+use std::fmt::{self, Display, Formatter};
+
+#[cfg(feature = "server")]
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
@@ -5,9 +8,9 @@ use axum::{
 use serde::{ser::SerializeStruct, Serialize, Serializer};
 use thiserror::Error as ThisError;
 
-use crate::response::TextRepresentable;
+use crate::{text::TextRepresentable, utils::escape, week::DocumentDimensions};
 
-#[derive(ThisError, Debug)]
+#[derive(ThisError, Clone, Debug)]
 pub enum Error {
     #[error("content negotiation failed")]
     ContentNegotiation,
@@ -19,22 +22,86 @@ pub enum Error {
     InvalidJson,
     #[error("invalid pdf")]
     InvalidPdf,
+    #[error("invalid csv")]
+    InvalidCsv,
+    #[error("invalid xlsx")]
+    InvalidXlsx,
+    #[error("invalid ics")]
+    InvalidIcs,
+    #[error("invalid day: {0}")]
+    SchemaViolation(String),
     #[error("no meal found for today")]
     NoMealToday,
     #[error("no next meal found")]
     NoNextMeal,
     #[error("invalid week")]
     InvalidWeek,
+    #[error("invalid month")]
+    InvalidMonth,
+    #[error("unknown category")]
+    InvalidCategory,
     #[error("invalid day")]
     InvalidDay,
+    #[error("invalid cutoff")]
+    InvalidCutoff,
     #[error("week not found")]
     WeekNotFound,
     #[error("day not found")]
     DayNotFound,
+    #[error("job not found")]
+    JobNotFound,
+    #[error("site not found")]
+    SiteNotFound,
+    #[error("schema not found")]
+    SchemaNotFound,
+    #[error("unsupported export version")]
+    UnsupportedExportVersion,
+    #[error("invalid watch")]
+    InvalidWatch,
+    #[error("watch not found")]
+    WatchNotFound,
+    #[error("push notifications disabled")]
+    PushDisabled,
+    #[error("subscription not found")]
+    SubscriptionNotFound,
+    #[error("dish not found")]
+    DishNotFound,
+    #[error("invalid allergen")]
+    InvalidAllergen,
+    #[error("invalid rating")]
+    InvalidRating,
+    #[error("invalid language")]
+    InvalidLanguage,
+    #[error("too many requests")]
+    TooManyRequests,
+    #[error("too many uploads")]
+    Busy,
+    #[error("nothing to undo")]
+    NothingToUndo,
+    #[error("catalogue changed")]
+    CatalogueChanged,
+    #[error("source not found")]
+    SourceNotFound,
+    #[error("route not found")]
+    RouteNotFound,
+    #[error("method not allowed")]
+    MethodNotAllowed,
+    #[error("unauthorized")]
+    Unauthorized,
+    #[error("admin endpoints disabled")]
+    AdminDisabled,
+    #[error("sessions disabled")]
+    SessionsDisabled,
+    #[error("rendering failed: {0}")]
+    Rendering(String),
     #[error("internal error")]
     Internal,
+    // A handler panicked, with the ID of the request.
+    #[error("internal error")]
+    Panic(String),
 }
 
+#[cfg(feature = "server")]
 impl Error {
     pub fn status_code(&self) -> StatusCode {
         match self {
@@ -43,20 +110,51 @@ impl Error {
             Error::InvalidBody => StatusCode::BAD_REQUEST,
             Error::InvalidJson => StatusCode::BAD_REQUEST,
             Error::InvalidPdf => StatusCode::BAD_REQUEST,
+            Error::InvalidCsv => StatusCode::BAD_REQUEST,
+            Error::InvalidXlsx => StatusCode::BAD_REQUEST,
+            Error::InvalidIcs => StatusCode::BAD_REQUEST,
+            Error::SchemaViolation(_) => StatusCode::BAD_REQUEST,
             Error::NoMealToday => StatusCode::NOT_FOUND,
             Error::NoNextMeal => StatusCode::NOT_FOUND,
             Error::InvalidWeek => StatusCode::BAD_REQUEST,
+            Error::InvalidMonth => StatusCode::BAD_REQUEST,
+            Error::InvalidCategory => StatusCode::BAD_REQUEST,
             Error::InvalidDay => StatusCode::BAD_REQUEST,
+            Error::InvalidCutoff => StatusCode::BAD_REQUEST,
             Error::WeekNotFound => StatusCode::NOT_FOUND,
             Error::DayNotFound => StatusCode::NOT_FOUND,
+            Error::JobNotFound => StatusCode::NOT_FOUND,
+            Error::SiteNotFound => StatusCode::NOT_FOUND,
+            Error::SchemaNotFound => StatusCode::NOT_FOUND,
+            Error::UnsupportedExportVersion => StatusCode::BAD_REQUEST,
+            Error::InvalidWatch => StatusCode::BAD_REQUEST,
+            Error::WatchNotFound => StatusCode::NOT_FOUND,
+            Error::PushDisabled => StatusCode::NOT_FOUND,
+            Error::SubscriptionNotFound => StatusCode::NOT_FOUND,
+            Error::DishNotFound => StatusCode::NOT_FOUND,
+            Error::InvalidAllergen => StatusCode::BAD_REQUEST,
+            Error::InvalidRating => StatusCode::BAD_REQUEST,
+            Error::InvalidLanguage => StatusCode::BAD_REQUEST,
+            Error::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            Error::Busy => StatusCode::SERVICE_UNAVAILABLE,
+            Error::NothingToUndo => StatusCode::CONFLICT,
+            Error::CatalogueChanged => StatusCode::PRECONDITION_FAILED,
+            Error::SourceNotFound => StatusCode::NOT_FOUND,
+            Error::RouteNotFound => StatusCode::NOT_FOUND,
+            Error::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::AdminDisabled => StatusCode::FORBIDDEN,
+            Error::SessionsDisabled => StatusCode::FORBIDDEN,
+            Error::Rendering(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Panic(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
-impl From<lopdf::Error> for Error {
-    fn from(_value: lopdf::Error) -> Self {
-        Self::InvalidPdf
+impl From<ParseError> for Error {
+    fn from(value: ParseError) -> Self {
+        value.error()
     }
 }
 
@@ -67,6 +165,9 @@ impl Serialize for Error {
     {
         let mut state = serializer.serialize_struct("Error", 3)?;
         state.serialize_field("error", &self.to_string())?;
+        if let Error::Panic(request_id) = self {
+            state.serialize_field("request_id", request_id)?;
+        }
         state.end()
     }
 }
@@ -79,20 +180,127 @@ impl TextRepresentable for Error {
             Error::NoMealToday => "Aucun repas de prévu pour aujourd'hui.".to_owned(),
             Error::NoNextMeal => "Aucun repas de prévu pour bientôt.".to_owned(),
             Error::InvalidWeek => "Format de semaine incorrect.".to_owned(),
+            Error::InvalidMonth => "Format de mois incorrect.".to_owned(),
+            Error::InvalidCategory => {
+                "Catégorie inconnue : starters, mains, sides, cheeses ou desserts.".to_owned()
+            }
             Error::InvalidDay => "Format de date incorrect.".to_owned(),
+            Error::InvalidCutoff => "Format d'heure incorrect.".to_owned(),
             Error::WeekNotFound => "Aucun menu trouvé pour cette semaine.".to_owned(),
             Error::DayNotFound => "Aucun menu trouvé pour ce jour.".to_owned(),
+            Error::SiteNotFound => "Restaurant inconnu.".to_owned(),
+            Error::InvalidWatch => "Abonnement invalide.".to_owned(),
+            Error::WatchNotFound => "Abonnement introuvable.".to_owned(),
+            Error::DishNotFound => "Plat introuvable.".to_owned(),
+            Error::InvalidAllergen => "Allergène inconnu.".to_owned(),
+            Error::InvalidRating => "Note invalide, entre 1 et 5 étoiles.".to_owned(),
+            Error::InvalidLanguage => "Langue inconnue, fr ou en.".to_owned(),
+            Error::TooManyRequests => "Trop de requêtes, réessayez dans une minute.".to_owned(),
+            Error::Busy => "Trop d'envois en cours, réessayez dans un instant.".to_owned(),
+            Error::NothingToUndo => "Aucun envoi à annuler.".to_owned(),
+            Error::CatalogueChanged => "Les menus ont changé, rechargez-les.".to_owned(),
+            Error::SourceNotFound => "Aucun document d'origine pour cette semaine.".to_owned(),
+            Error::RouteNotFound => "Page introuvable.".to_owned(),
+            Error::MethodNotAllowed => "Méthode non autorisée pour cette page.".to_owned(),
+            Error::SessionsDisabled => "Connexion désactivée.".to_owned(),
+            Error::Panic(request_id) => format!("Erreur interne (requête {request_id})."),
             _ => self.to_string(),
         }
     }
 
     fn as_html(&self) -> String {
-        format!(r#"<div class="error">{}</div>"#, self.as_plain_text(false))
+        format!(
+            r#"<div class="error">{}</div>"#,
+            escape(&self.as_plain_text(false))
+        )
     }
 }
 
+#[cfg(feature = "server")]
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
         (self.status_code(), self.as_plain_text(false)).into_response()
     }
 }
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ParseError {
+    pub stage: ParseStage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<DocumentDimensions>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejected_lines: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidate_columns: Vec<Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<String>,
+}
+
+impl ParseError {
+    pub fn new(stage: ParseStage) -> Self {
+        Self {
+            stage,
+            page: None,
+            rejected_lines: Vec::new(),
+            candidate_columns: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    pub fn error(&self) -> Error {
+        match self.stage {
+            ParseStage::Json => Error::InvalidJson,
+            ParseStage::Schema => Error::SchemaViolation(self.violations.join("; ")),
+            ParseStage::Csv => Error::InvalidCsv,
+            ParseStage::Xlsx => Error::InvalidXlsx,
+            ParseStage::Ics => Error::InvalidIcs,
+            _ => Error::InvalidPdf,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} stage)", self.error(), self.stage.as_str())
+    }
+}
+
+impl From<lopdf::Error> for ParseError {
+    fn from(_value: lopdf::Error) -> Self {
+        Self::new(ParseStage::Layout)
+    }
+}
+
+#[derive(Serialize, Copy, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseStage {
+    Json,
+    Schema,
+    Csv,
+    Xlsx,
+    Ics,
+    Load,
+    Extraction,
+    Layout,
+    Ocr,
+    Columns,
+    Date,
+}
+
+impl ParseStage {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ParseStage::Json => "json",
+            ParseStage::Schema => "schema",
+            ParseStage::Csv => "csv",
+            ParseStage::Xlsx => "xlsx",
+            ParseStage::Ics => "ics",
+            ParseStage::Load => "load",
+            ParseStage::Extraction => "extraction",
+            ParseStage::Layout => "layout",
+            ParseStage::Ocr => "ocr",
+            ParseStage::Columns => "columns",
+            ParseStage::Date => "date",
+        }
+    }
+}
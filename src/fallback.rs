@@ -0,0 +1,93 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use reqwest::{header, Client, StatusCode};
+use serde::Deserialize;
+
+use crate::{catalogue::MergePolicy, config::Config, day::Day, site::Site};
+
+// How long a week missing upstream too isn't asked for again.
+const MISS_RETENTION: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Deserialize, Debug)]
+pub struct FallbackConfig {
+    // Root URL of the upstream instance, like "https://ovr.example.com".
+    pub upstream: String,
+}
+
+#[derive(Deserialize)]
+struct UpstreamWeek {
+    days: Vec<Day>,
+}
+
+// Asks an upstream instance for the weeks missing from the local catalogues,
+// and keeps them locally once found.
+#[derive(Default, Debug)]
+pub struct Fallback {
+    client: Client,
+    misses: Mutex<HashMap<(Option<String>, i32, u8), Instant>>,
+}
+
+impl Fallback {
+    // Whether days of the week were added to the catalogue of `site`. Local
+    // days are never replaced.
+    pub async fn fetch(&self, config: &Config, site: &Site, year: i32, week: u8) -> bool {
+        let Some(fallback) = &config.fallback else {
+            return false;
+        };
+        let key = (site.name.clone(), year, week);
+        {
+            let mut misses = self.misses.lock().expect("fallback misses poisoned");
+            misses.retain(|_, missed| missed.elapsed() < MISS_RETENTION);
+            if misses.contains_key(&key) {
+                return false;
+            }
+        }
+
+        let root = fallback.upstream.trim_end_matches('/');
+        let url = match &site.name {
+            Some(name) => format!("{root}/api/v1/sites/{name}/weeks/{year}-{week:02}"),
+            None => format!("{root}/api/v1/weeks/{year}-{week:02}"),
+        };
+        match self.get(&url).await {
+            Ok(days) if !days.is_empty() => {
+                site.catalogue
+                    .write()
+                    .await
+                    .insert(days, MergePolicy::SkipExisting, false);
+                true
+            }
+            result => {
+                if let Err(err) = result {
+                    eprintln!("fallback: failed to fetch {url}: {err}");
+                }
+                self.misses
+                    .lock()
+                    .expect("fallback misses poisoned")
+                    .insert(key, Instant::now());
+                false
+            }
+        }
+    }
+
+    // Nothing when the week is unknown upstream too.
+    async fn get(&self, url: &str) -> Result<Vec<Day>, reqwest::Error> {
+        let response = self
+            .client
+            .get(url)
+            .header(header::ACCEPT, "application/json")
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        response
+            .error_for_status()?
+            .json::<UpstreamWeek>()
+            .await
+            .map(|week| week.days)
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::OnceLock;
+
+use time::{Date, Month, Weekday};
+
+static CLOSURES: OnceLock<Closures> = OnceLock::new();
+
+#[derive(Debug)]
+struct Closures {
+    public_holidays: bool,
+    weekends: bool,
+    days: Vec<Date>,
+}
+
+// Must be called before the first lookup, later calls fail.
+pub fn configure(public_holidays: bool, weekends: bool, days: Vec<Date>) -> Result<(), String> {
+    CLOSURES
+        .set(Closures {
+            public_holidays,
+            weekends,
+            days,
+        })
+        .map_err(|_| "closures already configured".to_owned())
+}
+
+fn closures() -> &'static Closures {
+    CLOSURES.get_or_init(|| Closures {
+        public_holidays: true,
+        weekends: false,
+        days: Vec::new(),
+    })
+}
+
+// Whether a meal is served on `date`.
+pub fn is_open(date: Date) -> bool {
+    let weekend = matches!(date.weekday(), Weekday::Saturday | Weekday::Sunday);
+    (closures().weekends || !weekend) && closure(date).is_none()
+}
+
+// Why the canteen is shut on `date`, if it is.
+pub fn closure(date: Date) -> Option<&'static str> {
+    let closures = closures();
+    if closures.days.contains(&date) {
+        return Some("Fermeture exceptionnelle");
+    }
+    if closures.public_holidays {
+        public_holiday(date)
+    } else {
+        None
+    }
+}
+
+pub fn public_holiday(date: Date) -> Option<&'static str> {
+    let fixed = match (date.month(), date.day()) {
+        (Month::January, 1) => Some("Jour de l'an"),
+        (Month::May, 1) => Some("Fête du travail"),
+        (Month::May, 8) => Some("Victoire 1945"),
+        (Month::July, 14) => Some("Fête nationale"),
+        (Month::August, 15) => Some("Assomption"),
+        (Month::November, 1) => Some("Toussaint"),
+        (Month::November, 11) => Some("Armistice 1918"),
+        (Month::December, 25) => Some("Noël"),
+        _ => None,
+    };
+    if fixed.is_some() {
+        return fixed;
+    }
+
+    match (date - easter(date.year())).whole_days() {
+        1 => Some("Lundi de Pâques"),
+        39 => Some("Ascension"),
+        50 => Some("Lundi de Pentecôte"),
+        _ => None,
+    }
+}
+
+// Anonymous Gregorian algorithm.
+fn easter(year: i32) -> Date {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    Date::from_calendar_date(
+        year,
+        Month::try_from(month as u8).expect("easter computation failed"),
+        day as u8,
+    )
+    .expect("easter computation failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+
+    #[test]
+    fn easter_sundays() {
+        assert_eq!(easter(2023), date!(2023 - 04 - 09));
+        assert_eq!(easter(2024), date!(2024 - 03 - 31));
+        assert_eq!(easter(2025), date!(2025 - 04 - 20));
+    }
+
+    #[test]
+    fn moving_public_holidays() {
+        assert_eq!(public_holiday(date!(2024 - 04 - 01)), Some("Lundi de Pâques"));
+        assert_eq!(public_holiday(date!(2024 - 05 - 09)), Some("Ascension"));
+        assert_eq!(public_holiday(date!(2024 - 05 - 20)), Some("Lundi de Pentecôte"));
+        assert_eq!(public_holiday(date!(2024 - 05 - 21)), None);
+    }
+
+    #[test]
+    fn closed_on_weekends_and_public_holidays() {
+        assert!(is_open(date!(2023 - 06 - 12)));
+        assert!(is_open(date!(2023 - 06 - 16)));
+        assert!(!is_open(date!(2023 - 06 - 17)));
+        assert!(!is_open(date!(2023 - 06 - 18)));
+        assert!(!is_open(date!(2023 - 07 - 14)));
+    }
+}
@@ -0,0 +1,107 @@
+use std::{fs, path::PathBuf};
+
+use axum::{
+    body::Bytes,
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+// Files replacing the embedded icons, which are picked up by browsers, home
+// screens and notifications.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct IconsConfig {
+    pub favicon: Option<PathBuf>,
+    pub svg: Option<PathBuf>,
+    // 180×180, for iOS home screens.
+    pub apple_touch_icon: Option<PathBuf>,
+    // Required by Android to install the web app.
+    pub icon_192: Option<PathBuf>,
+    pub icon_512: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+struct Icon {
+    path: &'static str,
+    content_type: &'static str,
+    data: Bytes,
+}
+
+// Icons by the path they're served at, read once at startup.
+#[derive(Debug)]
+pub struct Icons(Vec<Icon>);
+
+impl Icons {
+    pub fn load(config: &IconsConfig) -> Result<Self, String> {
+        let icons: [(_, _, _, &'static [u8]); 5] = [
+            (
+                "/favicon.ico",
+                &config.favicon,
+                "image/x-icon",
+                include_bytes!("favicon.ico"),
+            ),
+            (
+                "/icon.svg",
+                &config.svg,
+                "image/svg+xml",
+                include_bytes!("icon.svg"),
+            ),
+            (
+                "/apple-touch-icon.png",
+                &config.apple_touch_icon,
+                "image/png",
+                include_bytes!("apple-touch-icon.png"),
+            ),
+            (
+                "/icon-192.png",
+                &config.icon_192,
+                "image/png",
+                include_bytes!("icon-192.png"),
+            ),
+            (
+                "/icon-512.png",
+                &config.icon_512,
+                "image/png",
+                include_bytes!("icon-512.png"),
+            ),
+        ];
+        icons
+            .into_iter()
+            .map(|(path, file, content_type, default)| {
+                let data = match file {
+                    Some(file) => fs::read(file)
+                        .map_err(|err| format!("failed to read icon {}: {err}", file.display()))?
+                        .into(),
+                    None => Bytes::from_static(default),
+                };
+                Ok(Icon {
+                    path,
+                    content_type,
+                    data,
+                })
+            })
+            .collect::<Result<_, String>>()
+            .map(Self)
+    }
+
+    pub fn response(&self, path: &str) -> Option<Response> {
+        let icon = self.0.iter().find(|icon| icon.path == path)?;
+        Some(
+            (
+                [
+                    (
+                        header::CONTENT_TYPE,
+                        HeaderValue::from_static(icon.content_type),
+                    ),
+                    (
+                        header::CACHE_CONTROL,
+                        HeaderValue::from_static("public, max-age=86400"),
+                    ),
+                ],
+                icon.data.clone(),
+            )
+                .into_response(),
+        )
+    }
+}
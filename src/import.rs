@@ -0,0 +1,151 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use itertools::Itertools;
+
+use crate::{
+    catalogue::{Catalogue, CatalogueUpdate},
+    config::Config,
+    upload::FileKind,
+};
+
+// Where the loaded documents of an import directory are moved.
+const DONE_DIR: &str = "done";
+
+// A document given on its own, or found in an import directory.
+struct Document {
+    path: PathBuf,
+    dir: Option<PathBuf>,
+}
+
+// Documents of `paths`, directories being searched recursively for files of
+// a known kind, except in their `done` folder.
+fn documents(paths: &[PathBuf]) -> Result<Vec<Document>, String> {
+    let mut documents = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut found = Vec::new();
+            walk(path, true, &mut found)?;
+            found.sort();
+            documents.extend(found.into_iter().map(|found| Document {
+                path: found,
+                dir: Some(path.clone()),
+            }));
+        } else {
+            documents.push(Document {
+                path: path.clone(),
+                dir: None,
+            });
+        }
+    }
+    Ok(documents)
+}
+
+fn walk(dir: &Path, root: bool, found: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|err| format!("failed to list {}: {err}", dir.display()))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|err| format!("failed to list {}: {err}", dir.display()))?
+            .path();
+        if path.is_dir() {
+            if !(root && path.file_name().is_some_and(|name| name == DONE_DIR)) {
+                walk(&path, false, found)?;
+            }
+        } else if FileKind::from_file_name(&path.to_string_lossy()).is_some() {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Documents loaded at startup.
+pub struct Import {
+    pub catalogue: Catalogue,
+    pub updates: CatalogueUpdate,
+    documents: Vec<Document>,
+    failed: Vec<(PathBuf, String)>,
+}
+
+impl Import {
+    // Each document is parsed on its own thread, then inserted in the order
+    // of `paths`. Documents given on their own must be valid, invalid ones
+    // of directories are only reported.
+    pub fn load(paths: &[PathBuf], config: &Config) -> Result<Self, String> {
+        let documents = documents(paths)?;
+        let parsed = thread::scope(|scope| {
+            let parsers = documents
+                .iter()
+                .map(|document| {
+                    scope.spawn(move || {
+                        let data = fs::read(&document.path).map_err(|err| err.to_string())?;
+                        FileKind::from_file_name(&document.path.to_string_lossy())
+                            .unwrap_or(FileKind::Pdf)
+                            .parse(&data, config)
+                            .map_err(|err| err.to_string())
+                    })
+                })
+                .collect_vec();
+            parsers
+                .into_iter()
+                .map(|parser| parser.join().expect("document parser panicked"))
+                .collect_vec()
+        });
+
+        let mut import = Self {
+            catalogue: Catalogue::new(),
+            updates: CatalogueUpdate::default(),
+            documents: Vec::new(),
+            failed: Vec::new(),
+        };
+        for (document, days) in documents.into_iter().zip(parsed) {
+            match days {
+                Ok(days) => {
+                    import.updates += import.catalogue.insert(days, config.merge_policy, false);
+                    import.documents.push(document);
+                }
+                Err(err) if document.dir.is_some() => import.failed.push((document.path, err)),
+                Err(err) => return Err(format!("{}: {err}", document.path.display())),
+            }
+        }
+        Ok(import)
+    }
+
+    // Like "12 documents loaded, 1 failed: menus/2023-24.pdf (invalid PDF)".
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} documents loaded", self.documents.len());
+        if !self.failed.is_empty() {
+            summary += &format!(
+                ", {} failed: {}",
+                self.failed.len(),
+                self.failed
+                    .iter()
+                    .map(|(path, err)| format!("{} ({err})", path.display()))
+                    .join(", ")
+            );
+        }
+        summary
+    }
+
+    // Moves the loaded documents of import directories to their `done`
+    // folder, under the same relative path, so they aren't loaded again.
+    pub fn move_done(&self) -> Result<(), String> {
+        for document in &self.documents {
+            let Some(dir) = &document.dir else {
+                continue;
+            };
+            let relative = document.path.strip_prefix(dir).unwrap_or(&document.path);
+            let destination = dir.join(DONE_DIR).join(relative);
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|err| format!("failed to create {}: {err}", parent.display()))?;
+            }
+            fs::rename(&document.path, &destination)
+                .map_err(|err| format!("failed to move {}: {err}", document.path.display()))?;
+        }
+        Ok(())
+    }
+}
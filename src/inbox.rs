@@ -0,0 +1,177 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{async_trait, body::Bytes};
+use imap::{ClientBuilder, Connection, Session};
+use mail_parser::{MessageParser, MimeHeaders};
+use serde::Deserialize;
+use tokio::task;
+
+use crate::{
+    ingestion::{Ingester, IngestionSource},
+    upload::{FileKind, UploadFile},
+};
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ImapConfig {
+    pub host: String,
+    #[serde(default = "ImapConfig::default_port")]
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    #[serde(default = "ImapConfig::default_folder")]
+    pub folder: String,
+    // Only messages whose subject contains it, like "Menu de la semaine".
+    pub subject: Option<String>,
+    // Processed messages are moved there, and only marked as read otherwise.
+    pub processed_folder: Option<String>,
+    // Name of the site the menus are added to, the default one otherwise.
+    pub site: Option<String>,
+    #[serde(default = "ImapConfig::default_interval")]
+    pub interval: u64,
+}
+
+impl ImapConfig {
+    fn default_port() -> u16 {
+        993
+    }
+
+    fn default_folder() -> String {
+        "INBOX".to_owned()
+    }
+
+    fn default_interval() -> u64 {
+        300
+    }
+}
+
+// A message with at least one PDF attachment.
+struct Mail {
+    uid: u32,
+    attachments: Vec<UploadFile>,
+}
+
+// Reads unread messages with PDF attachments, adds the attachments to the
+// catalogue, then marks the messages as processed.
+pub struct Inbox {
+    config: Arc<ImapConfig>,
+}
+
+impl Inbox {
+    pub fn new(config: &ImapConfig) -> Self {
+        Self {
+            config: Arc::new(config.clone()),
+        }
+    }
+}
+
+#[async_trait]
+impl IngestionSource for Inbox {
+    fn name(&self) -> String {
+        format!("imap {}@{}", self.config.username, self.config.host)
+    }
+
+    fn site(&self) -> Option<&str> {
+        self.config.site.as_deref()
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.config.interval)
+    }
+
+    async fn poll(&mut self, ingester: &Ingester) -> Result<(), String> {
+        let config = Arc::clone(&self.config);
+        let (session, mails) = task::spawn_blocking(move || fetch(&config))
+            .await
+            .map_err(|err| err.to_string())?
+            .map_err(|err| format!("failed to fetch messages: {err}"))?;
+        let mut processed = Vec::new();
+        for mail in mails {
+            for file in mail.attachments {
+                // Failures are in the audit log.
+                let _ = ingester.ingest(file).await;
+            }
+            processed.push(mail.uid);
+        }
+        let config = Arc::clone(&self.config);
+        task::spawn_blocking(move || mark_processed(session, &config, &processed))
+            .await
+            .map_err(|err| err.to_string())?
+            .map_err(|err| format!("failed to mark messages as processed: {err}"))
+    }
+}
+
+fn fetch(config: &ImapConfig) -> imap::Result<(Session<Connection>, Vec<Mail>)> {
+    let client = ClientBuilder::new(&config.host, config.port).connect()?;
+    let mut session = client
+        .login(&config.username, &config.password)
+        .map_err(|(err, _)| err)?;
+    session.select(&config.folder)?;
+
+    let query = match &config.subject {
+        Some(subject) => format!("UNSEEN SUBJECT {}", quote(subject)),
+        None => "UNSEEN".to_owned(),
+    };
+    let uids = session.uid_search(query)?;
+    if uids.is_empty() {
+        return Ok((session, Vec::new()));
+    }
+    let set = uids
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    let fetches = session.uid_fetch(set, "(UID BODY.PEEK[])")?;
+    let mails = fetches
+        .iter()
+        .filter_map(|fetch| {
+            let message = MessageParser::default().parse(fetch.body()?)?;
+            let attachments = message
+                .attachments()
+                .filter(|part| {
+                    part.content_type()
+                        .is_some_and(|content_type| content_type.subtype() == Some("pdf"))
+                        || part
+                            .attachment_name()
+                            .is_some_and(|name| name.to_lowercase().ends_with(".pdf"))
+                })
+                .map(|part| {
+                    UploadFile::new(
+                        part.attachment_name().map(ToOwned::to_owned),
+                        FileKind::Pdf,
+                        Bytes::copy_from_slice(part.contents()),
+                    )
+                })
+                .collect::<Vec<_>>();
+            Some(Mail {
+                uid: fetch.uid?,
+                attachments,
+            })
+        })
+        .filter(|mail| !mail.attachments.is_empty())
+        .collect();
+    Ok((session, mails))
+}
+
+fn mark_processed(
+    mut session: Session<Connection>,
+    config: &ImapConfig,
+    uids: &[u32],
+) -> imap::Result<()> {
+    if !uids.is_empty() {
+        let set = uids
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        session.uid_store(&set, "+FLAGS (\\Seen)")?;
+        if let Some(folder) = &config.processed_folder {
+            session.uid_mv(&set, folder)?;
+        }
+    }
+    session.logout()
+}
+
+// An IMAP quoted string.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
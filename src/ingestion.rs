@@ -0,0 +1,249 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::async_trait;
+use itertools::Itertools;
+use serde::{Serialize, Serializer};
+use time::format_description::well_known::Rfc3339;
+use tokio::{sync::RwLock, time::interval};
+use tracing::{info_span, Instrument};
+
+use crate::{
+    audit::{AuditAction, AuditLog, AuditSource},
+    catalogue::CatalogueUpdate,
+    config::Config,
+    day::Day,
+    error::Error,
+    inbox::Inbox,
+    response::TextRepresentable,
+    site::{Site, Sites},
+    upload::UploadFile,
+    utils::now_local,
+    vendor::VendorApi,
+    webpage::Scraper,
+};
+
+// Somewhere the server fetches menus from on its own, polled every
+// `interval`.
+#[async_trait]
+pub trait IngestionSource: Send {
+    // Like "imap menus@example.com", shown in `/sources` and the audit log.
+    fn name(&self) -> String;
+
+    // Name of the site the documents are added to, the default one otherwise.
+    fn site(&self) -> Option<&str>;
+
+    fn interval(&self) -> Duration;
+
+    // Looks for new documents and adds them with `ingester`. Documents that
+    // fail to parse are only listed in the audit log, errors are for the
+    // source itself being unreachable or broken.
+    async fn poll(&mut self, ingester: &Ingester) -> Result<(), String>;
+}
+
+// The sources enabled in the configuration.
+fn configured(config: &Config) -> Result<Vec<Box<dyn IngestionSource>>, String> {
+    let mut sources = Vec::<Box<dyn IngestionSource>>::new();
+    if let Some(imap) = &config.imap {
+        sources.push(Box::new(Inbox::new(imap)));
+    }
+    for scraper in &config.scrapers {
+        sources.push(Box::new(Scraper::new(scraper)?));
+    }
+    if let Some(vendor_api) = &config.vendor_api {
+        sources.push(Box::new(VendorApi::new(vendor_api)));
+    }
+    Ok(sources)
+}
+
+// Adds the documents of a source to the catalogue of its site.
+pub struct Ingester {
+    site: Site,
+    config: Arc<Config>,
+    audit: Arc<RwLock<AuditLog>>,
+    source: AuditSource,
+}
+
+impl Ingester {
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // Whether `day` is already in the catalogue as is.
+    pub async fn contains(&self, day: &Day) -> bool {
+        self.site.catalogue.read().await.contains(day)
+    }
+
+    // Like an upload with the default options.
+    pub async fn ingest(&self, file: UploadFile) -> Result<CatalogueUpdate, Error> {
+        let name = file.name().map(ToOwned::to_owned);
+        let hash = file.hash();
+        let unchanged = self.site.catalogue.read().await.is_unchanged(&hash);
+        let result = if unchanged {
+            Ok(CatalogueUpdate::unchanged())
+        } else {
+            match file.spawn_parse(Arc::clone(&self.config)).await {
+                Ok((file, Ok(days))) => {
+                    self.site.sources.store(&file, &days).await;
+                    let mut catalogue = self.site.catalogue.write().await;
+                    catalogue.remember_document(hash, days.clone());
+                    Ok(catalogue.insert(
+                        days,
+                        self.config.merge_policy,
+                        self.config.require_force_on_conflict,
+                    ))
+                }
+                Ok((_, Err(err))) => Err(err.error()),
+                Err(_) => Err(Error::Internal),
+            }
+        };
+        self.audit.write().await.record(
+            &self.source,
+            AuditAction::Fetch,
+            self.site.name.as_deref(),
+            name.as_deref(),
+            result.as_ref(),
+        );
+        result
+    }
+}
+
+// The outcome of the latest polls of each source.
+#[derive(Default, Debug)]
+pub struct Ingestion {
+    sources: Vec<SourceHealth>,
+}
+
+impl Ingestion {
+    pub fn health(&self) -> IngestionHealth {
+        IngestionHealth {
+            sources: self.sources.clone(),
+        }
+    }
+
+    fn record(&mut self, index: usize, result: Result<(), String>) {
+        let Some(source) = self.sources.get_mut(index) else {
+            return;
+        };
+        let now = now_local()
+            .format(&Rfc3339)
+            .expect("ingestion date formatting failed");
+        match result {
+            Ok(()) => {
+                source.status = SourceStatus::Healthy;
+                source.last_success = Some(now.clone());
+                source.error = None;
+            }
+            Err(err) => {
+                source.status = SourceStatus::Failing;
+                source.error = Some(err);
+            }
+        }
+        source.last_attempt = Some(now);
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SourceHealth {
+    name: String,
+    site: Option<String>,
+    status: SourceStatus,
+    last_attempt: Option<String>,
+    last_success: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum SourceStatus {
+    // Not polled yet.
+    Pending,
+    Healthy,
+    Failing,
+}
+
+impl SourceStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SourceStatus::Pending => "pending",
+            SourceStatus::Healthy => "healthy",
+            SourceStatus::Failing => "failing",
+        }
+    }
+}
+
+impl Serialize for SourceStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct IngestionHealth {
+    sources: Vec<SourceHealth>,
+}
+
+impl TextRepresentable for IngestionHealth {
+    fn as_plain_text(&self, _human: bool) -> String {
+        self.sources
+            .iter()
+            .map(|source| {
+                let mut line = format!("{} {}", source.name, source.status.as_str());
+                if let Some(success) = &source.last_success {
+                    line += &format!(" (last success {success})");
+                }
+                if let Some(error) = &source.error {
+                    line += &format!(": {error}");
+                }
+                line
+            })
+            .join("\n")
+    }
+}
+
+// Polls every configured source in its own task. Fails on invalid
+// configurations.
+pub fn spawn(
+    sites: Arc<Sites>,
+    config: Arc<Config>,
+    audit: Arc<RwLock<AuditLog>>,
+) -> Result<Arc<RwLock<Ingestion>>, String> {
+    let mut ingestion = Ingestion::default();
+    let mut tasks = Vec::new();
+    for (index, source) in configured(&config)?.into_iter().enumerate() {
+        let site = sites
+            .get(source.site())
+            .ok_or_else(|| format!("unknown site {:?} for {}", source.site(), source.name()))?;
+        ingestion.sources.push(SourceHealth {
+            name: source.name(),
+            site: site.name.clone(),
+            status: SourceStatus::Pending,
+            last_attempt: None,
+            last_success: None,
+            error: None,
+        });
+        let ingester = Ingester {
+            site,
+            config: Arc::clone(&config),
+            audit: Arc::clone(&audit),
+            source: AuditSource::new(source.name()),
+        };
+        tasks.push((index, source, ingester));
+    }
+
+    let ingestion = Arc::new(RwLock::new(ingestion));
+    for (index, mut source, ingester) in tasks {
+        let ingestion = Arc::clone(&ingestion);
+        tokio::spawn(async move {
+            let mut interval = interval(source.interval().max(Duration::from_secs(1)));
+            loop {
+                interval.tick().await;
+                let span = info_span!("ingestion", source = %source.name());
+                let result = source.poll(&ingester).instrument(span).await;
+                if let Err(err) = &result {
+                    eprintln!("{}: {err}", source.name());
+                }
+                ingestion.write().await.record(index, result);
+            }
+        });
+    }
+    Ok(ingestion)
+}
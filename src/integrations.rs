@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use time::{Date, Time};
+
+use crate::{
+    catalogue::Catalogue,
+    day::Day,
+    error::Error,
+    mqtt::MqttConfig,
+    response::TextRepresentable,
+    utils::{format_date, now_local, parse_date},
+};
+
+const NO_MENU: &str = "Aucun menu";
+
+// Flat so a REST sensor can use `json_attributes` without templates.
+#[derive(Serialize, Debug)]
+pub struct HomeAssistantSensor {
+    state: String,
+    date: Option<String>,
+    dishes: Vec<String>,
+    notes: Vec<String>,
+}
+
+impl HomeAssistantSensor {
+    pub fn new(day: Option<&Day>) -> Self {
+        let Some(day) = day else {
+            return Self {
+                state: NO_MENU.to_owned(),
+                date: None,
+                dishes: Vec::new(),
+                notes: Vec::new(),
+            };
+        };
+        let dishes = day.dishes_ref();
+        let main = day.main_dish().or_else(|| dishes.first());
+        Self {
+            state: main.map_or(NO_MENU, |dish| dish.name()).to_owned(),
+            date: Some(format_date(day.date())),
+            dishes: dishes.iter().map(|dish| dish.name().to_owned()).collect(),
+            notes: day.notes().to_vec(),
+        }
+    }
+}
+
+pub const HOME_ASSISTANT_DISCOVERY_TOPIC: &str = "homeassistant/sensor/ovr/config";
+
+// MQTT discovery payload of a sensor reading the topic of the MQTT publisher.
+pub fn home_assistant_discovery(config: Option<&MqttConfig>) -> Value {
+    let topic = config.map_or("ovr/today", |config| config.topic.as_str());
+    json!({
+        "name": "Menu du jour",
+        "unique_id": "ovr_today",
+        "icon": "mdi:silverware-fork-knife",
+        "state_topic": topic,
+        "value_template": format!("{{{{ value_json.dishes[0] if value_json else '{NO_MENU}' }}}}"),
+        "json_attributes_topic": topic,
+        "json_attributes_template": "{{ value_json | tojson if value_json else '{}' }}",
+    })
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AlexaEnvelope {
+    request: AlexaRequest,
+    context: Option<AlexaContext>,
+}
+
+impl AlexaEnvelope {
+    pub fn application_id(&self) -> Option<&str> {
+        Some(&self.context.as_ref()?.system.application.application_id)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type")]
+enum AlexaRequest {
+    LaunchRequest,
+    IntentRequest {
+        intent: AlexaIntent,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlexaIntent {
+    name: String,
+    #[serde(default)]
+    slots: HashMap<String, AlexaSlot>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlexaSlot {
+    value: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlexaContext {
+    #[serde(rename = "System")]
+    system: AlexaSystem,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlexaSystem {
+    application: AlexaApplication,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlexaApplication {
+    #[serde(rename = "applicationId")]
+    application_id: String,
+}
+
+// Any intent with a `date` slot (AMAZON.DATE) asks for that day, the launch
+// request and other intents for today.
+pub fn alexa_response(envelope: &AlexaEnvelope, catalogue: &Catalogue) -> Value {
+    let text = match &envelope.request {
+        AlexaRequest::LaunchRequest => Some(menu_speech(catalogue, None)),
+        AlexaRequest::IntentRequest { intent } => match intent.name.as_str() {
+            "AMAZON.HelpIntent" => {
+                Some("Demandez le menu d'aujourd'hui ou d'un autre jour.".to_owned())
+            }
+            "AMAZON.StopIntent" | "AMAZON.CancelIntent" => Some("Bon appétit !".to_owned()),
+            // Dates that aren't a single day (like "2023-W24") fall back to today.
+            _ => Some(menu_speech(
+                catalogue,
+                intent
+                    .slots
+                    .get("date")
+                    .and_then(|slot| slot.value.as_deref())
+                    .and_then(parse_date),
+            )),
+        },
+        AlexaRequest::Other => None,
+    };
+    json!({
+        "version": "1.0",
+        "response": match text {
+            Some(text) => json!({
+                "outputSpeech": { "type": "PlainText", "text": text },
+                "shouldEndSession": true,
+            }),
+            None => json!({}),
+        },
+    })
+}
+
+pub fn menu_speech(catalogue: &Catalogue, date: Option<Date>) -> String {
+    match date {
+        Some(date) if date != now_local().date() => catalogue.day(date),
+        _ => catalogue.today().ok_or(Error::NoMealToday),
+    }
+    .map_or_else(|err| err.as_plain_text(true), |day| day.as_plain_text(true))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DialogflowRequest {
+    #[serde(rename = "queryResult")]
+    query_result: DialogflowQueryResult,
+}
+
+#[derive(Deserialize, Debug)]
+struct DialogflowQueryResult {
+    intent: DialogflowIntent,
+    #[serde(default)]
+    parameters: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct DialogflowIntent {
+    #[serde(rename = "displayName")]
+    display_name: String,
+}
+
+// Intents are matched by display name: "next", "find-dish" (with a `dish`
+// parameter), anything else answers the day of the `date` parameter or today.
+pub fn dialogflow_response(
+    request: &DialogflowRequest,
+    catalogue: &Catalogue,
+    cutoff: Time,
+) -> Value {
+    let parameter = |name: &str| {
+        request
+            .query_result
+            .parameters
+            .get(name)
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+    };
+    let text = match request.query_result.intent.display_name.as_str() {
+        "next" => upcoming_speech(catalogue.next(cutoff)),
+        "find-dish" => match parameter("dish") {
+            Some(dish) => {
+                upcoming_speech(catalogue.find_dish_next(vec![dish.to_owned()], &[], cutoff))
+            }
+            None => Error::NoNextMeal.as_plain_text(true),
+        },
+        // Dates come as "2023-06-12T12:00:00+02:00".
+        _ => menu_speech(
+            catalogue,
+            parameter("date")
+                .and_then(|date| date.get(..10))
+                .and_then(parse_date),
+        ),
+    };
+    json!({ "fulfillmentText": text })
+}
+
+pub fn upcoming_speech(day: Option<Day>) -> String {
+    day.ok_or(Error::NoNextMeal)
+        .map_or_else(|err| err.as_plain_text(true), |day| day.as_plain_text(true))
+}
@@ -0,0 +1,248 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use tokio::sync::RwLock;
+use tracing::{info_span, Instrument};
+use uuid::Uuid;
+
+use crate::{
+    audit::{AuditAction, AuditLog, AuditSource},
+    catalogue::CatalogueUpdate,
+    config::Config,
+    error::{Error, ParseError},
+    response::TextRepresentable,
+    site::Site,
+    upload::{PendingFile, Upload},
+};
+
+const FINISHED_JOB_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Default, Debug)]
+pub struct Jobs {
+    jobs: HashMap<Uuid, Job>,
+}
+
+impl Jobs {
+    pub async fn spawn(
+        jobs: Arc<RwLock<Self>>,
+        site: Site,
+        config: Arc<Config>,
+        audit: Arc<RwLock<AuditLog>>,
+        source: AuditSource,
+        upload: Upload,
+    ) -> Job {
+        let Upload {
+            files,
+            options,
+            slot,
+        } = upload;
+        let policy = options.policy.unwrap_or(config.merge_policy);
+        let protect = config.require_force_on_conflict && !options.force;
+        let job = Job {
+            id: Uuid::new_v4(),
+            files: files
+                .iter()
+                .map(|file| JobFile {
+                    name: file.name().map(ToOwned::to_owned),
+                    state: FileState::Pending,
+                    diagnostics: None,
+                })
+                .collect(),
+            finished: None,
+        };
+        {
+            let mut jobs_lock = jobs.write().await;
+            jobs_lock.prune();
+            jobs_lock.jobs.insert(job.id, job.clone());
+        }
+
+        let id = job.id;
+        tokio::spawn(async move {
+            let pending = PendingFile::start_all(files, &*site.catalogue.read().await, &config);
+            // Undone as a whole, whatever the number of files.
+            let upload = site.catalogue.write().await.start_upload();
+            for (index, file) in pending.into_iter().enumerate() {
+                jobs.write().await.set_state(id, index, FileState::Processing, None);
+                let PendingFile {
+                    name,
+                    hash,
+                    parsing,
+                } = file;
+                let (result, diagnostics) = match parsing {
+                    None => (Ok(CatalogueUpdate::unchanged()), None),
+                    Some(parsing) => match parsing.await {
+                        Ok((file, Ok(days))) => {
+                            let update = {
+                                let mut catalogue = site.catalogue.write().await;
+                                catalogue.remember_document(hash, days.clone());
+                                catalogue.insert_as(upload, days.clone(), policy, protect)
+                            };
+                            site.sources.store(&file, &days).await;
+                            (Ok(update), None)
+                        }
+                        Ok((_, Err(err))) => (Err(err.error()), options.debug.then_some(err)),
+                        Err(_) => (Err(Error::Internal), None),
+                    },
+                };
+                audit.write().await.record(
+                    &source,
+                    AuditAction::Upload,
+                    site.name.as_deref(),
+                    name.as_deref(),
+                    result.as_ref(),
+                );
+                jobs.write()
+                    .await
+                    .set_state(id, index, FileState::Finished(result), diagnostics);
+            }
+            if let Some(job) = jobs.write().await.jobs.get_mut(&id) {
+                job.finished = Some(Instant::now());
+            }
+            drop(slot);
+        }
+        .instrument(info_span!("upload job", %id)));
+        job
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<Job> {
+        self.jobs.get(&id).cloned()
+    }
+
+    fn set_state(
+        &mut self,
+        id: Uuid,
+        index: usize,
+        state: FileState,
+        diagnostics: Option<ParseError>,
+    ) {
+        if let Some(file) = self
+            .jobs
+            .get_mut(&id)
+            .and_then(|job| job.files.get_mut(index))
+        {
+            file.state = state;
+            file.diagnostics = diagnostics;
+        }
+    }
+
+    fn prune(&mut self) {
+        self.jobs.retain(|_, job| {
+            job.finished
+                .map_or(true, |finished| finished.elapsed() < FINISHED_JOB_RETENTION)
+        });
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Job {
+    id: Uuid,
+    files: Vec<JobFile>,
+    finished: Option<Instant>,
+}
+
+impl Job {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+#[derive(Clone, Debug)]
+struct JobFile {
+    name: Option<String>,
+    state: FileState,
+    diagnostics: Option<ParseError>,
+}
+
+#[derive(Clone, Debug)]
+enum FileState {
+    Pending,
+    Processing,
+    Finished(Result<CatalogueUpdate, Error>),
+}
+
+impl FileState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FileState::Pending => "pending",
+            FileState::Processing => "processing",
+            FileState::Finished(Ok(_)) => "done",
+            FileState::Finished(Err(_)) => "failed",
+        }
+    }
+}
+
+impl Serialize for Job {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct File<'a> {
+            name: Option<&'a str>,
+            status: &'static str,
+            #[serde(flatten)]
+            update: Option<&'a CatalogueUpdate>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            error: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            diagnostics: Option<&'a ParseError>,
+        }
+
+        let mut state = serializer.serialize_struct("Job", 3)?;
+        state.serialize_field("id", &self.id.to_string())?;
+        state.serialize_field("finished", &self.finished.is_some())?;
+        state.serialize_field(
+            "files",
+            &self
+                .files
+                .iter()
+                .map(|file| File {
+                    name: file.name.as_deref(),
+                    status: file.state.as_str(),
+                    update: match &file.state {
+                        FileState::Finished(Ok(update)) => Some(update),
+                        _ => None,
+                    },
+                    error: match &file.state {
+                        FileState::Finished(Err(err)) => Some(err.to_string()),
+                        _ => None,
+                    },
+                    diagnostics: file.diagnostics.as_ref(),
+                })
+                .collect_vec(),
+        )?;
+        state.end()
+    }
+}
+
+impl TextRepresentable for Job {
+    fn as_plain_text(&self, _human: bool) -> String {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| {
+                let name = file
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("#{}", index + 1));
+                match &file.state {
+                    FileState::Finished(Ok(update)) => format!(
+                        "{name}: {} ({} inserted, {} replaced)",
+                        file.state.as_str(),
+                        update.inserted.len(),
+                        update.replaced.len()
+                    ),
+                    FileState::Finished(Err(err)) => {
+                        format!("{name}: {} ({err})", file.state.as_str())
+                    }
+                    _ => format!("{name}: {}", file.state.as_str()),
+                }
+            })
+            .join("\n")
+    }
+}
@@ -0,0 +1,26 @@
+// The menu parsers alone, without the server and its dependencies, so they
+// can be built for `wasm32-unknown-unknown`:
+//
+//     wasm-pack build --target web --no-default-features --features wasm
+//
+// The binary declares the same modules itself and doesn't use this crate.
+
+#![allow(dead_code)]
+
+mod allergens;
+mod day;
+mod error;
+mod holidays;
+mod normalize;
+mod nutrition;
+#[cfg(feature = "ocr")]
+mod ocr;
+mod schema;
+mod text;
+mod utils;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod week;
+
+// For the benchmarks, see `benches/parsing.rs`.
+pub use week::{parse_csv, parse_ics, parse_json, parse_pdf, ParserConfig};
@@ -0,0 +1,108 @@
+use lettre::{
+    message::{Mailbox, MessageBuilder, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde::Deserialize;
+
+use crate::response::{html_page, TextRepresentable};
+
+#[derive(Deserialize, Debug)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    pub smtp_port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub recipients: Vec<String>,
+    pub site: Option<String>,
+    #[serde(default = "EmailConfig::default_weekly")]
+    pub weekly: bool,
+    #[serde(default)]
+    pub daily: bool,
+}
+
+impl EmailConfig {
+    const fn default_weekly() -> bool {
+        true
+    }
+}
+
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    recipients: Vec<Mailbox>,
+}
+
+impl Mailer {
+    pub fn new(config: &EmailConfig) -> Result<Self, String> {
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
+            .map_err(|err| err.to_string())?;
+        if let Some(port) = config.smtp_port {
+            transport = transport.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        Ok(Self {
+            transport: transport.build(),
+            from: config
+                .from
+                .parse()
+                .map_err(|_| "invalid email sender".to_owned())?,
+            recipients: config
+                .recipients
+                .iter()
+                .map(|recipient| recipient.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| "invalid email recipient".to_owned())?,
+        })
+    }
+
+    // Recipients are in Bcc so they don't see each other.
+    pub async fn send(
+        &self,
+        subject: &str,
+        content: &impl TextRepresentable,
+    ) -> Result<(), String> {
+        let mut builder = Message::builder().to(self.from.clone());
+        for recipient in &self.recipients {
+            builder = builder.bcc(recipient.clone());
+        }
+        self.deliver(builder, subject, content).await
+    }
+
+    pub async fn send_to(
+        &self,
+        recipient: &str,
+        subject: &str,
+        content: &impl TextRepresentable,
+    ) -> Result<(), String> {
+        let recipient = recipient
+            .parse()
+            .map_err(|_| "invalid email recipient".to_owned())?;
+        self.deliver(Message::builder().to(recipient), subject, content)
+            .await
+    }
+
+    async fn deliver(
+        &self,
+        builder: MessageBuilder,
+        subject: &str,
+        content: &impl TextRepresentable,
+    ) -> Result<(), String> {
+        let message = builder
+            .from(self.from.clone())
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(
+                content.as_plain_text(false),
+                html_page(&content.as_html(), 1.0, false),
+            ))
+            .map_err(|err| err.to_string())?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+}
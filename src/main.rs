@@ -1,247 +1,2579 @@
 use std::{
-    env::args,
     fs,
+    io::{self, Write},
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
     sync::Arc,
 };
 
 use axum::{
     body::{Body, Bytes},
-    extract::{FromRef, FromRequest, Multipart, Path, Query, State},
-    http::{header, HeaderValue, Request},
-    middleware::map_response,
-    response::{IntoResponse, Response},
-    routing::{get, post},
-    Router, Server,
+    extract::{FromRef, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, Request, StatusCode, Uri},
+    middleware::{from_fn, from_fn_with_state, map_response},
+    response::{Html, IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Form, Json, Router, Server,
 };
-use either::Either;
+use clap::{Args, Parser, Subcommand};
 use http_negotiator::{ContentTypeNegotiation, Negotiator};
 use serde::Deserialize;
-use tokio::sync::RwLock;
+use time::Time;
+use tokio::{sync::RwLock, task};
+use tower_http::{catch_panic::CatchPanicLayer, trace::TraceLayer};
+use utoipa::OpenApi;
+use uuid::Uuid;
 
+#[cfg(feature = "ocr")]
+use crate::week::ParserConfig;
 use crate::{
-    catalogue::{Catalogue, CatalogueUpdate},
-    day::Day,
+    allergens::Allergen,
+    audit::{AuditAction, AuditLog, AuditSource},
+    auth::{Admin, RateLimiter, Uploader},
+    build_info::BuildInfo,
+    cache::{CachedRoute, ResponseCache},
+    catalogue::{
+        AdminIndex, CalendarFilter, Catalogue, CatalogueExport, CatalogueUpdate, CatalogueWeek,
+        week_of, DayHistory, MergePolicy,
+    },
+    config::Config,
+    connections::ConnectionLimit,
+    day::{Day, DishCategory, UpcomingDay},
+    delivery::DeadLetters,
     error::Error,
-    response::{ApiResponse, ResponseType, ResponseTypeRaw, TextRepresentable},
-    utils::parse_date,
+    fallback::Fallback,
+    icons::Icons,
+    import::Import,
+    ingestion::Ingestion,
+    integrations::{
+        alexa_response, dialogflow_response, home_assistant_discovery, AlexaEnvelope,
+        DialogflowRequest, HomeAssistantSensor,
+    },
+    jobs::Jobs,
+    openapi::{
+        AlexaRequest, AlexaResponse, AllergensRequest as AllergensRequestSchema, ApiDoc,
+        AuditResponse, CatalogueExport as CatalogueExportSchema, CatalogueResponse,
+        CatalogueSnapshot, DayHistoryResponse, DayResponse, DeliveriesResponse,
+        DialogflowRequest as DialogflowRequestSchema, DialogflowResponse, DishRatingsResponse,
+        DishesResponse, ErrorResponse, HomeAssistantSensor as HomeAssistantSensorSchema,
+        JobResponse, LeaderboardResponse, LoginRequest, MergedDishesResponse, MonthResponse,
+        PersonalDayResponse, Preferences as PreferencesSchema, ProfileResponse, PushKeyResponse,
+        PushSubscription as PushSubscriptionSchema, RatedDayResponse,
+        RatingRequest as RatingRequestSchema, RsvpRequest as RsvpRequestSchema, RsvpResponse,
+        SitesResponse, SourcesResponse, SuccessResponse, UnsubscribeRequest, UpcomingDayResponse,
+        UpdateResponse, UploadResponse, VersionResponse, WatchListResponse,
+        WatchRequest as WatchRequestSchema, WatchResponse, WeekResponse,
+    },
+    profiles::{Me, PersonalDay, Preferences, Profile, Profiles},
+    proxy::Origin,
+    push::{PushKey, PushSubscription, PushSubscriptions},
+    ratings::{DishRatings, RatedDay, RatingRequest, Ratings},
+    response::{
+        html_page, legacy_shape, method_not_allowed, ApiResponse, CalendarNegotiator, ErrorFormat,
+        ResponseType, ResponseTypeRaw, TextRepresentable, WithCalendar,
+    },
+    rsvp::{Attendance, RsvpRequest, Rsvps},
+    site::{IfMatch, Site, Sites},
+    sources::Sources,
+    storage::Storage,
+    text::Language,
+    translation::{LanguageQuery, Translator},
+    upload::{
+        FileKind, PendingFile, Upload, UploadFile, UploadOptions, UploadReport, UploadSlot,
+        UploadSlots,
+    },
+    utils::{parse_date, parse_month, parse_time, parse_week, set_timezone, DEFAULT_TIMEZONE},
+    watches::{Watch, WatchRequest, Watches},
 };
 
+mod allergens;
+mod audit;
+mod auth;
+mod branding;
+mod build_info;
+mod cache;
+mod caldav;
 mod catalogue;
+mod config;
+mod connections;
+mod crawlers;
 mod day;
+mod delivery;
 mod error;
+mod fallback;
+mod holidays;
+mod icons;
+mod import;
+mod inbox;
+mod ingestion;
+mod integrations;
+mod jobs;
+mod mailer;
+mod matrix;
+mod mqtt;
+mod normalize;
+mod notifier;
+mod nutrition;
+#[cfg(feature = "ocr")]
+mod ocr;
+mod openapi;
+mod panics;
+mod profiles;
+mod proxy;
+mod push;
+mod ratings;
+mod replication;
 mod response;
+mod rsvp;
+mod schema;
+mod search;
+mod site;
+mod sources;
+mod storage;
+mod systemd;
+#[cfg(feature = "otlp")]
+mod telemetry;
+mod text;
+mod translation;
+mod upload;
 mod utils;
+mod vendor;
+mod watches;
+mod webpage;
 mod week;
 
 #[derive(FromRef, Clone)]
 struct AppState {
-    catalogue: Arc<RwLock<Catalogue>>,
+    sites: Arc<Sites>,
+    config: Arc<Config>,
+    jobs: Arc<RwLock<Jobs>>,
+    audit: Arc<RwLock<AuditLog>>,
+    ingestion: Arc<RwLock<Ingestion>>,
+    fallback: Arc<Fallback>,
+    cache: Arc<ResponseCache>,
+    watches: Arc<RwLock<Watches>>,
+    subscriptions: Arc<RwLock<PushSubscriptions>>,
+    dead_letters: Arc<RwLock<DeadLetters>>,
+    profiles: Arc<RwLock<Profiles>>,
+    ratings: Arc<RwLock<Ratings>>,
+    rsvps: Arc<RwLock<Rsvps>>,
+    translator: Arc<Translator>,
+    icons: Arc<Icons>,
+    limiter: Arc<RateLimiter>,
+    uploads: Arc<UploadSlots>,
     negotiator: Arc<Negotiator<ContentTypeNegotiation, ResponseTypeRaw>>,
+    calendar_negotiator: CalendarNegotiator,
+}
+
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Options {
+    /// TOML configuration file.
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+    /// IANA timezone used for "today" and calendar events [default: Europe/Paris].
+    #[arg(long, global = true)]
+    timezone: Option<String>,
+    /// Time after which today's meal is over, like 13:30.
+    #[arg(long, global = true)]
+    cutoff: Option<String>,
+    /// Root URL of the instance used in links, like https://ovr.example.com.
+    #[arg(long, global = true)]
+    public_url: Option<String>,
+    /// Run OCR on PDFs without a usable text layer.
+    #[cfg(feature = "ocr")]
+    #[arg(long, global = true)]
+    ocr: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+    // Without a subcommand, for existing setups.
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start the server, the default.
+    Serve(ServeArgs),
+    /// Print the days of menus as JSON.
+    Parse {
+        /// Menus to parse (PDF, JSON, CSV, XLSX or ICS).
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Print menus as an iCalendar.
+    Ics {
+        /// Menus to parse (PDF, JSON, CSV, XLSX or ICS).
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Upload menus to a running instance.
+    Push(PushArgs),
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// Port to listen on.
+    #[arg(short, long, default_value_t = 8080)]
+    port: u16,
+    /// Reverse proxies whose X-Forwarded-* headers are used, like 127.0.0.1.
+    #[arg(long, value_delimiter = ',')]
+    trusted_proxies: Vec<IpAddr>,
+    /// Move the menus loaded from directories to their `done` folder.
+    #[arg(long)]
+    move_imported: bool,
+    /// Menus to load at startup (PDF, JSON, CSV, XLSX or ICS), or
+    /// directories of menus.
+    documents: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct PushArgs {
+    /// Root URL of the instance, like https://ovr.example.com.
+    #[arg(long)]
+    url: String,
+    /// Site to upload to, the default one otherwise.
+    #[arg(long)]
+    site: Option<String>,
+    /// Menus to upload (PDF, JSON, CSV, XLSX or ICS).
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
-    let mut catalogue = Catalogue::new();
-    let mut updates = CatalogueUpdate::default();
-    for doc in args().skip(1) {
-        let data = fs::read(&doc).map_err(|err| err.to_string())?;
-        let week = if doc.ends_with(".json") {
-            week::parse_json(&data)
-        } else {
-            week::parse_pdf(&data)
-        }.map_err(|err| err.to_string())?;
-        updates += catalogue.insert(week);
+    let options = Options::parse();
+    let config = Config::load(options.config.as_deref())?;
+    set_timezone(
+        options
+            .timezone
+            .as_deref()
+            .or(config.timezone.as_deref())
+            .unwrap_or(DEFAULT_TIMEZONE),
+    )?;
+    let config = match options.cutoff.as_deref() {
+        Some(cutoff) => Config {
+            cutoff: parse_time(cutoff).ok_or_else(|| "invalid cutoff time".to_owned())?,
+            ..config
+        },
+        None => config,
+    };
+    let config = Config {
+        public_url: options.public_url.or(config.public_url),
+        ..config
+    };
+    holidays::configure(
+        config.public_holidays,
+        config.open_on_weekends,
+        config.closures.clone(),
+    )?;
+    allergens::configure(&config.allergen_keywords)?;
+    day::configure(config.cutoff)?;
+    branding::configure(config.branding.clone())?;
+    if let Some(path) = &config.nutrition_table {
+        nutrition::configure(path)?;
+    }
+    #[cfg(feature = "ocr")]
+    let config = Config {
+        parser: ParserConfig {
+            ocr: config.parser.ocr || options.ocr,
+            ..config.parser
+        },
+        ..config
+    };
+
+    match options.command {
+        Some(Command::Parse { files }) => {
+            let Import { catalogue, .. } = Import::load(&files, &config)?;
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&catalogue).expect("catalogue serialization failed")
+            );
+            Ok(())
+        }
+        Some(Command::Ics { files }) => {
+            let Import { catalogue, .. } = Import::load(&files, &config)?;
+            let calendar = catalogue.ics(
+                "Menus",
+                &config.calendar_namespace,
+                config.calendar_main_dish_summary,
+                Origin::public(&config)
+                    .map(|origin| origin.site_url(None))
+                    .as_deref(),
+            )
+            .map_err(|err| err.to_string())?;
+            io::stdout()
+                .write_all(&calendar)
+                .map_err(|err| err.to_string())
+        }
+        Some(Command::Push(args)) => push(args).await,
+        Some(Command::Serve(args)) => serve(config, args).await,
+        None => serve(config, options.serve).await,
+    }
+}
+
+// Sends each file as a single upload, stopping at the first rejected one.
+async fn push(args: PushArgs) -> Result<(), String> {
+    let root = args.url.trim_end_matches('/');
+    let url = match &args.site {
+        Some(site) => format!("{root}/sites/{site}/upload"),
+        None => format!("{root}/upload"),
+    };
+    let client = reqwest::Client::new();
+    for file in &args.files {
+        let data = fs::read(file).map_err(|err| err.to_string())?;
+        let kind = FileKind::from_file_name(&file.to_string_lossy()).unwrap_or(FileKind::Pdf);
+        let response = client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, kind.content_type())
+            .header(reqwest::header::ACCEPT, "text/plain")
+            .body(data)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        let status = response.status();
+        let body = response.text().await.map_err(|err| err.to_string())?;
+        println!("{}: {body}", file.display());
+        if !status.is_success() {
+            return Err(format!("{} rejected: {status}", file.display()));
+        }
+    }
+    Ok(())
+}
+
+async fn serve(mut config: Config, args: ServeArgs) -> Result<(), String> {
+    #[cfg(feature = "otlp")]
+    telemetry::init(config.telemetry.as_ref())?;
+    config.trusted_proxies.extend(args.trusted_proxies);
+    let documents = args
+        .documents
+        .iter()
+        .chain(&config.import_dir)
+        .cloned()
+        .collect::<Vec<_>>();
+    let import = Import::load(&documents, &config)?;
+    if !import.updates.is_empty() {
+        println!("{}", import.updates.as_plain_text(false));
+    }
+    if !documents.is_empty() {
+        println!("{}", import.summary());
+    }
+    if args.move_imported || config.move_imported {
+        import.move_done()?;
     }
-    if !updates.is_empty() {
-        println!("{}", updates.as_plain_text(false));
+    let catalogue = import.catalogue;
+    let audit = Arc::new(RwLock::new(AuditLog::open(config.audit_log.as_deref())?));
+    let storage = config.s3.as_ref().map(Storage::s3).transpose()?.map(Arc::new);
+    let sources = match (&storage, &config.sources_dir) {
+        (Some(storage), _) => Sources::new(Some(Arc::clone(storage)), "sources"),
+        (None, Some(dir)) => Sources::new(Some(Arc::new(Storage::Directory(dir.clone()))), ""),
+        (None, None) => Sources::new(None, ""),
+    };
+    let sites = Arc::new(Sites::new(
+        config.default_site.clone(),
+        catalogue,
+        &config.sites,
+        sources,
+    ));
+    let watches = Arc::new(RwLock::new(Watches::open(config.watches_file.as_deref())?));
+    let subscriptions = Arc::new(RwLock::new(PushSubscriptions::open(
+        config.push_subscriptions_file.as_deref(),
+    )?));
+    let dead_letters = Arc::new(RwLock::new(DeadLetters::open(
+        config.dead_letter_log.as_deref(),
+    )?));
+    let profiles = Arc::new(RwLock::new(Profiles::open(
+        config.profiles_file.as_deref(),
+    )?));
+    let ratings = Arc::new(RwLock::new(Ratings::open(config.ratings_log.as_deref())?));
+    let rsvps = Arc::new(RwLock::new(Rsvps::open(config.rsvp_file.as_deref())?));
+    let translator = Arc::new(Translator::new(&config)?);
+    let icons = Arc::new(Icons::load(&config.icons)?);
+    if let Some(storage) = &storage {
+        storage::restore(&sites, &config, storage).await?;
     }
+    let config = Arc::new(config);
+    if let Some(storage) = storage {
+        storage::spawn(Arc::clone(&sites), Arc::clone(&config), storage);
+    }
+    let cache = Arc::new(ResponseCache::default());
+    cache::spawn(Arc::clone(&sites), Arc::clone(&config), Arc::clone(&cache));
+    matrix::spawn(Arc::clone(&sites), Arc::clone(&config));
+    caldav::spawn(Arc::clone(&sites), Arc::clone(&config));
+    replication::spawn(Arc::clone(&sites), Arc::clone(&config), Arc::clone(&audit));
+    let ingestion =
+        ingestion::spawn(Arc::clone(&sites), Arc::clone(&config), Arc::clone(&audit))?;
+    notifier::spawn(
+        Arc::clone(&sites),
+        Arc::clone(&config),
+        Arc::clone(&watches),
+        Arc::clone(&subscriptions),
+        Arc::clone(&dead_letters),
+    )?;
 
-    Server::bind(&SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 8080))
+    let uploads = Arc::new(UploadSlots::new(&config));
+    let state = AppState {
+        sites,
+        config,
+        jobs: Arc::new(RwLock::new(Jobs::default())),
+        audit,
+        ingestion,
+        fallback: Arc::new(Fallback::default()),
+        cache,
+        watches,
+        subscriptions,
+        dead_letters,
+        profiles,
+        ratings,
+        rsvps,
+        translator,
+        icons,
+        limiter: Arc::new(RateLimiter::default()),
+        uploads,
+        negotiator: Arc::new(
+            Negotiator::new([
+                ResponseTypeRaw::Json,
+                ResponseTypeRaw::Text,
+                ResponseTypeRaw::Html,
+            ])
+            .expect("invalid content-type negotiator"),
+        ),
+        calendar_negotiator: CalendarNegotiator(Arc::new(
+            Negotiator::new([
+                ResponseTypeRaw::Json,
+                ResponseTypeRaw::Text,
+                ResponseTypeRaw::Html,
+                ResponseTypeRaw::Calendar,
+            ])
+            .expect("invalid content-type negotiator"),
+        )),
+    };
+    let builder = match systemd::listener()? {
+        Some(listener) => Server::from_tcp(listener).map_err(|err| err.to_string())?,
+        None => Server::bind(&SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), args.port)),
+    };
+    let http = &state.config.http;
+    let mut builder = builder
         .http1_title_case_headers(true)
-        .serve(
-            Router::new()
-                .route("/", get(index_handler).post(upload_handler))
-                .route("/upload", post(upload_handler))
-                .route("/today", get(today_handler))
-                .route("/next", get(next_handler))
-                .route("/find", get(find_handler))
-                .route("/weeks/:week", get(week_handler))
-                .route("/days/:day", get(day_handler))
-                .route("/calendar.ics", get(ics_handler))
-                .with_state(AppState {
-                    catalogue: Arc::new(RwLock::new(catalogue)),
-                    negotiator: Arc::new(
-                        Negotiator::new([
-                            ResponseTypeRaw::Json,
-                            ResponseTypeRaw::Text,
-                            ResponseTypeRaw::Html,
-                        ])
-                        .expect("invalid content-type negotiator"),
-                    ),
-                })
-                .layer(map_response(|mut resp: Response| async {
-                    resp.headers_mut().insert(
-                        header::SERVER,
-                        HeaderValue::from_static(concat!("OVR v", env!("CARGO_PKG_VERSION"))),
-                    );
-                    resp
-                }))
-                .into_make_service(),
-        )
-        .await
-        .unwrap_err();
+        .http1_keepalive(http.keep_alive)
+        .http1_only(!http.http2)
+        .tcp_keepalive(http.tcp_keepalive());
+    if let Some(timeout) = http.header_read_timeout() {
+        builder = builder.http1_header_read_timeout(timeout);
+    }
+    let max_connections = http.max_connections;
+    let app = Router::new()
+        .merge(api_router(&state).layer(from_fn(legacy_shape)))
+        .nest("/api/v1", api_router(&state))
+        .route("/openapi.json", get(openapi_handler))
+        .route("/docs", get(docs_handler))
+        .route("/login", get(login_page_handler).post(login_handler))
+        .route("/logout", post(logout_handler))
+        .route("/sw.js", get(service_worker_handler))
+        .route("/app.js", get(app_script_handler))
+        .route("/manifest.webmanifest", get(manifest_handler))
+        .route("/favicon.ico", get(icon_handler))
+        .route("/icon.svg", get(icon_handler))
+        .route("/apple-touch-icon.png", get(icon_handler))
+        .route("/icon-192.png", get(icon_handler))
+        .route("/icon-512.png", get(icon_handler))
+        .route("/robots.txt", get(robots_handler))
+        .route("/sitemap.xml", get(sitemap_handler))
+        .route("/schema/:file", get(schema_handler))
+        .fallback(not_found_handler)
+        .layer(from_fn_with_state(state.clone(), method_not_allowed))
+        .layer(from_fn_with_state(state.clone(), auth::guard))
+        .layer(CatchPanicLayer::custom(panics::response))
+        .layer(from_fn_with_state(state.clone(), panics::request_id))
+        .with_state(state)
+        .layer(map_response(|mut resp: Response| async {
+            resp.headers_mut().insert(
+                header::SERVER,
+                HeaderValue::from_static(concat!("OVR v", env!("CARGO_PKG_VERSION"))),
+            );
+            resp
+        }))
+        .layer(TraceLayer::new_for_http())
+        .into_make_service_with_connect_info::<SocketAddr>();
+    let server = builder.serve(ConnectionLimit::new(app, max_connections));
+    systemd::notify_ready();
+    server.await.unwrap_err();
 
     Ok(())
 }
 
+// Served both at the root, for existing clients, and under `/api/v1`.
+fn api_router(state: &AppState) -> Router<AppState> {
+    site_router(state)
+        .nest("/sites/:site", site_router(state))
+        .route("/sites", get(sites_handler))
+        .route("/version", get(version_handler))
+        .route("/jobs/:id", get(job_handler))
+        .route("/audit", get(audit_handler))
+        .route("/sources", get(ingestion_handler))
+        .route("/deliveries", get(deliveries_handler))
+        .route("/watches", get(watches_handler).post(watch_handler))
+        .route("/watches/:id", delete(unwatch_handler))
+        .route("/profiles", post(create_profile_handler))
+        .route(
+            "/me",
+            get(profile_handler)
+                .put(update_profile_handler)
+                .delete(delete_profile_handler),
+        )
+        .route("/push/key", get(push_key_handler))
+        .route(
+            "/push/subscriptions",
+            post(subscribe_handler).delete(unsubscribe_handler),
+        )
+}
+
+// Routes served for the default site and under `/sites/:site`.
+fn site_router(state: &AppState) -> Router<AppState> {
+    Router::new()
+        .route("/", get(index_handler).post(upload_handler))
+        .route("/upload", get(upload_page_handler).post(upload_handler))
+        .route("/today", get(today_handler))
+        .route("/next", get(next_handler))
+        .route("/find", get(find_handler))
+        .route("/dishes", get(dishes_handler))
+        .route("/weeks/:week", get(week_handler).delete(delete_week_handler))
+        .route("/weeks/:week/source.pdf", get(source_handler))
+        .route("/months/:month", get(month_handler))
+        .route("/days/:day", get(day_handler).delete(delete_day_handler))
+        .route("/days/:day/history", get(history_handler))
+        .route("/duplicates", get(merged_dishes_handler))
+        .route("/days/:day/dishes/:index/allergens", put(allergens_handler))
+        .route(
+            "/days/:day/dishes/:index/rating",
+            get(dish_rating_handler).post(rate_handler),
+        )
+        .route("/stats/ratings", get(leaderboard_handler))
+        .route(
+            "/days/:day/rsvp",
+            get(attendance_handler)
+                .post(rsvp_handler)
+                .delete(cancel_rsvp_handler),
+        )
+        .route("/me/today", get(personal_today_handler))
+        .route("/me/next", get(personal_next_handler))
+        .route("/calendar.ics", get(ics_handler))
+        .route("/export", get(export_handler))
+        .route("/snapshot", get(snapshot_handler))
+        .route("/import", post(import_handler))
+        .route("/undo", post(undo_handler))
+        .route("/reparse", post(reparse_handler))
+        .route("/integrations/home-assistant", get(home_assistant_handler))
+        .route("/integrations/alexa", post(alexa_handler))
+        .route("/integrations/dialogflow", post(dialogflow_handler))
+        .route(
+            "/integrations/home-assistant/discovery",
+            get(home_assistant_discovery_handler),
+        )
+        .route_layer(from_fn_with_state(state.clone(), site::version_header))
+}
+
+#[utoipa::path(
+    get,
+    path = "/",
+    params(("upcoming" = Option<bool>, Query, description = "Only from the next meal on")),
+    responses(
+        (status = 200, description = "Every day (weeks list in HTML)", body = CatalogueResponse),
+    )
+)]
 async fn index_handler(
-    State(catalogue): State<Arc<RwLock<Catalogue>>>,
-    response_type: ResponseType,
+    site: Site,
+    State(config): State<Arc<Config>>,
+    State(cache): State<Arc<ResponseCache>>,
+    WithCalendar(response_type): WithCalendar,
+    origin: Origin,
+    headers: HeaderMap,
+    Query(UpcomingQuery { upcoming }): Query<UpcomingQuery>,
 ) -> impl IntoResponse {
-    ApiResponse {
-        response_type,
-        data: Ok(if matches!(response_type, ResponseType::Html(_, _)) {
-            Either::Left(catalogue.read().await.weeks())
+    // Not cached, only admins seeing their controls.
+    if matches!(response_type, ResponseType::Html(_, _)) && auth::is_admin(&headers, &config) {
+        let catalogue = site.catalogue.read().await;
+        let catalogue = if upcoming {
+            catalogue.upcoming(config.cutoff)
         } else {
-            Either::Right(catalogue.read().await.clone())
-        }),
+            catalogue.clone()
+        };
+        return ApiResponse {
+            response_type,
+            data: Ok(AdminIndex::new(&catalogue, site.path_prefix())),
+        }
+        .render();
     }
+    let route = match response_type {
+        ResponseType::Calendar => CachedRoute::Calendar(
+            origin,
+            CalendarFilter {
+                upcoming,
+                category: None,
+            },
+        ),
+        response_type => CachedRoute::Index(response_type, upcoming),
+    };
+    cache.respond(&site, &config, route).await
+}
+
+#[derive(Deserialize)]
+struct UpcomingQuery {
+    #[serde(default)]
+    upcoming: bool,
 }
 
+#[utoipa::path(
+    post,
+    path = "/upload",
+    params(
+        ("async" = Option<bool>, Query, description = "Parse in the background and return a job"),
+        ("debug" = Option<bool>, Query, description = "Include parse diagnostics of failed files"),
+        ("force" = Option<bool>, Query, description = "Replace conflicting days anyway"),
+        ("policy" = Option<String>, Query, description = "replace, skip_existing or merge_dishes"),
+    ),
+    request_body(
+        content = Vec<u8>,
+        description = "A PDF, JSON, CSV, XLSX or ICS file, or a multipart form of files",
+        content_type = "multipart/form-data",
+    ),
+    responses(
+        (status = 200, description = "Every file was processed", body = UploadResponse),
+        (status = 202, description = "Job started", body = JobResponse),
+        (status = 207, description = "Some files failed", body = UploadResponse),
+        (status = 400, description = "Invalid document", body = ErrorResponse),
+        (status = 409, description = "Conflicting days were kept", body = UploadResponse),
+        (status = 412, description = "Catalogue changed since If-Match", body = ErrorResponse),
+        (status = 503, description = "Too many uploads being parsed", body = ErrorResponse),
+    )
+)]
 async fn upload_handler(
-    State(catalogue): State<Arc<RwLock<Catalogue>>>,
+    _: Uploader,
+    site: Site,
+    State(config): State<Arc<Config>>,
+    State(jobs): State<Arc<RwLock<Jobs>>>,
+    State(audit): State<Arc<RwLock<AuditLog>>>,
+    source: AuditSource,
+    if_match: IfMatch,
+    slot: UploadSlot,
+    Query(options): Query<UploadOptions>,
+    response_type: ResponseType,
     request: Request<Body>,
-) -> impl IntoResponse {
+) -> Response {
     async fn process(
-        catalogue: Arc<RwLock<Catalogue>>,
-        request: Request<Body>,
-    ) -> Result<CatalogueUpdate, Error> {
-        let mut catalogue_lock = catalogue.write().await;
-        let mut updates = CatalogueUpdate::default();
-        if request
-            .headers()
-            .get(header::CONTENT_TYPE)
-            .and_then(|h| h.to_str().ok())
-            .is_some_and(|h| h.starts_with("multipart/form-data"))
-        {
-            let mut multipart = Multipart::from_request(request, &())
-                .await
-                .map_err(|_| Error::InvalidBody)?;
-            while let Some(field) = multipart
-                .next_field()
-                .await
-                .map_err(|_| Error::InvalidBody)?
-            {
-                let days = match field.headers().get(header::CONTENT_TYPE)
-                    .and_then(|h| h.to_str().ok())
-                {
-                    Some("application/json" | "application/octet-stream") => {
-                        let data = field.bytes().await.map_err(|_| Error::InvalidBody)?;
-                        week::parse_json(&data)?
-                    }
-                    Some("application/pdf") | None | _ => {
-                        let data = field.bytes().await.map_err(|_| Error::InvalidBody)?;
-                        week::parse_pdf(&data)?
-                    }
-                };
-                updates += catalogue_lock.insert(days);
+        site: Site,
+        config: Arc<Config>,
+        audit: Arc<RwLock<AuditLog>>,
+        source: &AuditSource,
+        if_match: &IfMatch,
+        upload: Upload,
+    ) -> Result<UploadReport, Error> {
+        let Upload {
+            files,
+            options,
+            slot: _slot,
+        } = upload;
+        let policy = options.policy.unwrap_or(config.merge_policy);
+        let protect = config.require_force_on_conflict && !options.force;
+        // Parsed before the catalogue is locked, so reads go on meanwhile.
+        let pending = {
+            let catalogue = site.catalogue.read().await;
+            if_match.check(&catalogue)?;
+            PendingFile::start_all(files, &catalogue, &config)
+        };
+        let mut parsed = Vec::with_capacity(pending.len());
+        for file in pending {
+            let days = match file.parsing {
+                Some(parsing) => Some(parsing.await.map_err(|_| Error::Internal)?),
+                None => None,
+            };
+            parsed.push((file.name, file.hash, days));
+        }
+
+        let mut catalogue_lock = site.catalogue.write().await;
+        if_match.check(&catalogue_lock)?;
+        let mut audit_lock = audit.write().await;
+        // Undone as a whole, whatever the number of files.
+        let upload = catalogue_lock.start_upload();
+        let mut report = UploadReport::new(site.path_prefix());
+        let mut sources = Vec::new();
+        for (name, hash, days) in parsed {
+            let result = match days {
+                None => Ok(CatalogueUpdate::unchanged()),
+                Some((file, Ok(days))) => {
+                    catalogue_lock.remember_document(hash, days.clone());
+                    let update = catalogue_lock.insert_as(upload, days.clone(), policy, protect);
+                    sources.push((file, days));
+                    Ok(update)
+                }
+                Some((_, Err(err))) => Err(err),
+            };
+            let site_name = site.name.as_deref();
+            match &result {
+                Ok(update) => audit_lock.record(
+                    source,
+                    AuditAction::Upload,
+                    site_name,
+                    name.as_deref(),
+                    Ok(update),
+                ),
+                Err(err) => audit_lock.record(
+                    source,
+                    AuditAction::Upload,
+                    site_name,
+                    name.as_deref(),
+                    Err(&err.error()),
+                ),
             }
-        } else {
-            let data = Bytes::from_request(request, &())
-                .await
-                .map_err(|_| Error::InvalidBody)?;
-            let days = week::parse_pdf(&data)?;
-            updates += catalogue_lock.insert(days);
+            report.push(name, result, options.debug);
+        }
+        // Kept once the locks are released, storage being slow.
+        drop(audit_lock);
+        drop(catalogue_lock);
+        for (file, days) in sources {
+            site.sources.store(&file, &days).await;
+        }
+        Ok(report)
+    }
+
+    let files = match UploadFile::read_all(request).await {
+        Ok(files) => files,
+        Err(err) => {
+            return ApiResponse::<()> {
+                response_type,
+                data: Err(err),
+            }
+            .into_response()
+        }
+    };
+
+    if options.asynchronous {
+        // Checked before the job starts, uploads in between still apply.
+        if let Err(err) = if_match.check(&*site.catalogue.read().await) {
+            return ApiResponse::<()> {
+                response_type,
+                data: Err(err),
+            }
+            .into_response();
         }
-        Ok(updates)
+        let upload = Upload {
+            files,
+            options,
+            slot,
+        };
+        let job = Jobs::spawn(jobs, site, config, audit, source, upload).await;
+        return (
+            StatusCode::ACCEPTED,
+            [(header::LOCATION, format!("/jobs/{}", job.id()))],
+            ApiResponse {
+                response_type,
+                data: Ok(job),
+            },
+        )
+            .into_response();
     }
 
+    let upload = Upload {
+        files,
+        options,
+        slot,
+    };
+    let data = process(site, config, audit, &source, &if_match, upload)
+        .await
+        .and_then(UploadReport::into_result);
+    (
+        match &data {
+            Ok(report) => report.status_code(),
+            Err(err) => err.status_code(),
+        },
+        ApiResponse {
+            response_type,
+            data,
+        },
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    params(("id" = String, Path, description = "Job identifier")),
+    responses(
+        (status = 200, description = "Job progress", body = JobResponse),
+        (status = 404, description = "Unknown job", body = ErrorResponse),
+    )
+)]
+async fn job_handler(
+    State(jobs): State<Arc<RwLock<Jobs>>>,
+    response_type: ResponseType,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let job = match Uuid::parse_str(&id) {
+        Ok(id) => jobs.read().await.get(id),
+        Err(_) => None,
+    };
     ApiResponse {
-        response_type: ResponseType::Json(false),
-        data: process(catalogue, request).await,
+        response_type,
+        data: job.ok_or(Error::JobNotFound),
     }
 }
 
+async fn upload_page_handler() -> impl IntoResponse {
+    Html(include_str!("upload.html"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/today",
+    params(("lang" = Option<String>, Query, description = "`en` to translate dish names")),
+    responses(
+        (status = 200, description = "Today's menu", body = DayResponse),
+        (status = 400, description = "Unknown language", body = ErrorResponse),
+        (status = 404, description = "No meal today", body = ErrorResponse),
+    )
+)]
 async fn today_handler(
-    State(catalogue): State<Arc<RwLock<Catalogue>>>,
+    site: Site,
+    State(config): State<Arc<Config>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(translator): State<Arc<Translator>>,
     response_type: ResponseType,
-) -> impl IntoResponse {
+    Query(lang): Query<LanguageQuery>,
+) -> Response {
+    async fn process(
+        site: Site,
+        translator: &Translator,
+        lang: LanguageQuery,
+    ) -> Result<Day, Error> {
+        let language = lang.language()?;
+        let day = site.catalogue.read().await.today().ok_or(Error::NoMealToday)?;
+        Ok(translate_day(translator, language, day)
+            .await
+            .in_site(&site.path_prefix()))
+    }
+    if matches!(lang.language(), Ok(Language::French)) {
+        return cache
+            .respond(&site, &config, CachedRoute::Today(response_type))
+            .await
+            .into_response();
+    }
     ApiResponse {
         response_type,
-        data: catalogue.read().await.today().ok_or(Error::NoMealToday),
+        data: process(site, &translator, lang).await,
+    }
+    .into_response()
+}
+
+async fn translate_day(translator: &Translator, language: Language, day: Day) -> Day {
+    let translations = translator.translate(language, day.texts()).await;
+    day.translated(language, &translations)
+}
+
+#[derive(Deserialize)]
+struct NextQuery {
+    cutoff: Option<String>,
+}
+
+fn cutoff_time(cutoff: Option<&str>, config: &Config) -> Result<Time, Error> {
+    match cutoff {
+        Some(cutoff) => parse_time(cutoff).ok_or(Error::InvalidCutoff),
+        None => Ok(config.cutoff),
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/next",
+    params(
+        ("cutoff" = Option<String>, Query, description = "End of today's meal, like 13:30"),
+        ("lang" = Option<String>, Query, description = "`en` to translate dish names"),
+    ),
+    responses(
+        (status = 200, description = "Next served menu", body = UpcomingDayResponse),
+        (status = 400, description = "Invalid cutoff or language", body = ErrorResponse),
+        (status = 404, description = "No next meal", body = ErrorResponse),
+    )
+)]
 async fn next_handler(
-    State(catalogue): State<Arc<RwLock<Catalogue>>>,
+    site: Site,
+    State(config): State<Arc<Config>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(translator): State<Arc<Translator>>,
     response_type: ResponseType,
-) -> impl IntoResponse {
+    Query(query): Query<NextQuery>,
+    Query(lang): Query<LanguageQuery>,
+) -> Response {
+    async fn process(
+        site: Site,
+        config: &Config,
+        translator: &Translator,
+        query: NextQuery,
+        lang: LanguageQuery,
+    ) -> Result<UpcomingDay, Error> {
+        let cutoff = cutoff_time(query.cutoff.as_deref(), config)?;
+        let language = lang.language()?;
+        let day = site.catalogue
+            .read()
+            .await
+            .next(cutoff)
+            .ok_or(Error::NoNextMeal)?;
+        Ok(UpcomingDay(
+            translate_day(translator, language, day)
+                .await
+                .in_site(&site.path_prefix()),
+        ))
+    }
+    if query.cutoff.is_none() && matches!(lang.language(), Ok(Language::French)) {
+        return cache
+            .respond(&site, &config, CachedRoute::Next(response_type))
+            .await
+            .into_response();
+    }
     ApiResponse {
         response_type,
-        data: catalogue.read().await.next().ok_or(Error::NoNextMeal),
+        data: process(site, &config, &translator, query, lang).await,
     }
+    .into_response()
 }
 
 #[derive(Deserialize)]
 struct FindQuery {
     dish: String,
+    without: Option<String>,
+    cutoff: Option<String>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/find",
+    params(
+        ("dish" = String, Query, description = "Comma separated dishes that must all be served"),
+        ("without" = Option<String>, Query, description = "Comma separated allergens to avoid"),
+        ("cutoff" = Option<String>, Query, description = "End of today's meal, like 13:30"),
+    ),
+    responses(
+        (status = 200, description = "Next menu with the dishes", body = UpcomingDayResponse),
+        (status = 400, description = "Unknown allergen", body = ErrorResponse),
+        (status = 404, description = "No such meal", body = ErrorResponse),
+    )
+)]
 async fn find_handler(
-    State(catalogue): State<Arc<RwLock<Catalogue>>>,
+    site: Site,
+    State(config): State<Arc<Config>>,
     response_type: ResponseType,
     Query(query): Query<FindQuery>,
+) -> impl IntoResponse {
+    async fn process(site: Site, config: &Config, query: FindQuery) -> Result<UpcomingDay, Error> {
+        let cutoff = cutoff_time(query.cutoff.as_deref(), config)?;
+        let without = query
+            .without
+            .iter()
+            .flat_map(|without| without.split(','))
+            .filter(|allergen| !allergen.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Allergen>, _>>()?;
+        site.catalogue
+            .read()
+            .await
+            .find_dish_next(
+                query.dish.split(',').map(|d| d.to_owned()).collect(),
+                &without,
+                cutoff,
+            )
+            .map(|day| UpcomingDay(day.in_site(&site.path_prefix())))
+            .ok_or(Error::NoNextMeal)
+    }
+    ApiResponse {
+        response_type,
+        data: process(site, &config, query).await,
+    }
+}
+
+#[derive(Deserialize)]
+struct DishesQuery {
+    q: Option<String>,
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/dishes",
+    params(
+        ("q" = Option<String>, Query, description = "Word beginnings, to autocomplete dishes"),
+        ("limit" = Option<usize>, Query, description = "Maximum dishes, 20 by default"),
+    ),
+    responses(
+        (status = 200, description = "Known dishes, the most served first", body = DishesResponse),
+    )
+)]
+async fn dishes_handler(
+    Site { catalogue, .. }: Site,
+    response_type: ResponseType,
+    Query(query): Query<DishesQuery>,
 ) -> impl IntoResponse {
     ApiResponse {
         response_type,
-        data: catalogue
+        data: Ok(catalogue
             .read()
             .await
-            .find_dish_next(query.dish.split(',').map(|d| d.to_owned()).collect())
-            .ok_or(Error::NoNextMeal),
+            .dishes(query.q.as_deref(), query.limit.unwrap_or(20))),
     }
 }
 
+// Named so the `site` parameter of nested routes is ignored.
+#[derive(Deserialize)]
+struct WeekPath {
+    week: String,
+}
+
+#[derive(Deserialize)]
+struct DayPath {
+    day: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/weeks/{week}",
+    params(
+        ("week" = String, Path, description = "ISO week, like 2023-24, or current"),
+        ("lang" = Option<String>, Query, description = "`en` to translate dish names"),
+        ("upcoming" = Option<bool>, Query, description = "Only from the next meal on"),
+    ),
+    responses(
+        (status = 200, description = "Days of the week", body = WeekResponse),
+        (status = 400, description = "Unknown language", body = ErrorResponse),
+        (status = 404, description = "Unknown week", body = ErrorResponse),
+    )
+)]
 async fn week_handler(
-    State(catalogue): State<Arc<RwLock<Catalogue>>>,
+    site: Site,
+    State(config): State<Arc<Config>>,
+    State(fallback): State<Arc<Fallback>>,
+    State(translator): State<Arc<Translator>>,
+    WithCalendar(response_type): WithCalendar,
+    origin: Origin,
+    Path(WeekPath { week }): Path<WeekPath>,
+    Query(lang): Query<LanguageQuery>,
+    Query(UpcomingQuery { upcoming }): Query<UpcomingQuery>,
+) -> Response {
+    async fn process(
+        site: Site,
+        config: &Config,
+        fallback: &Fallback,
+        translator: &Translator,
+        week: String,
+        lang: LanguageQuery,
+        upcoming: bool,
+    ) -> Result<CatalogueWeek, Error> {
+        let language = lang.language()?;
+        let (year, week) = parse_week(&week).ok_or(Error::InvalidWeek)?;
+        let mut days = site.catalogue.read().await.week(year, week);
+        if matches!(days, Err(Error::WeekNotFound))
+            && fallback.fetch(config, &site, year, week).await
+        {
+            days = site.catalogue.read().await.week(year, week);
+        }
+        let days = days?;
+        let adjacent = site.catalogue.read().await.adjacent_weeks(year, week);
+        let translations = translator.translate(language, days.texts()).await;
+        let week = CatalogueWeek::new(
+            (year, week),
+            days.translated(language, &translations),
+            adjacent,
+        )
+        .in_site(&site.path_prefix());
+        Ok(if upcoming {
+            week.upcoming(config.cutoff)
+        } else {
+            week
+        })
+    }
+    let data = process(site.clone(), &config, &fallback, &translator, week, lang, upcoming).await;
+    match (response_type, data) {
+        (ResponseType::Calendar, Ok(week)) => {
+            cache::calendar(&site, week.days(), &config, &origin).into_response()
+        }
+        (response_type, data) => ApiResponse {
+            response_type,
+            data,
+        }
+        .into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct MonthPath {
+    month: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/months/{month}",
+    params(("month" = String, Path, description = "Month, like 2023-06")),
+    responses(
+        (status = 200, description = "Days of the month (calendar in HTML)", body = MonthResponse),
+        (status = 400, description = "Invalid month", body = ErrorResponse),
+    )
+)]
+async fn month_handler(
+    site: Site,
     response_type: ResponseType,
-    Path(week): Path<String>,
+    Path(MonthPath { month }): Path<MonthPath>,
 ) -> impl IntoResponse {
-    async fn process(catalogue: Arc<RwLock<Catalogue>>, week: String) -> Result<Catalogue, Error> {
-        let (year, week) = week.split_once('-').ok_or(Error::InvalidWeek)?;
-        catalogue.read().await.week(
-            year.parse().map_err(|_| Error::InvalidWeek)?,
-            week.parse().map_err(|_| Error::InvalidWeek)?,
+    ApiResponse {
+        response_type,
+        data: match parse_month(&month) {
+            Some((year, month)) => Ok(site.catalogue
+                .read()
+                .await
+                .month(year, month)
+                .in_site(&site.path_prefix())),
+            None => Err(Error::InvalidMonth),
+        },
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/weeks/{week}/source.pdf",
+    params(("week" = String, Path, description = "ISO week, like 2023-24")),
+    responses(
+        (
+            status = 200,
+            description = "PDF uploaded for the week",
+            body = Vec<u8>,
+            content_type = "application/pdf",
+        ),
+        (status = 404, description = "No PDF kept for the week", body = ErrorResponse),
+    )
+)]
+async fn source_handler(
+    Site { sources, .. }: Site,
+    Path(WeekPath { week }): Path<WeekPath>,
+) -> Response {
+    let data = match parse_week(&week) {
+        Some((year, week)) => sources.get(year, week).await,
+        None => Err(Error::InvalidWeek),
+    };
+    match data {
+        Ok(data) => (
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static("application/pdf")),
+                (
+                    header::CONTENT_DISPOSITION,
+                    HeaderValue::from_str(&format!(r#"inline; filename="ovr-{week}.pdf""#))
+                        .unwrap_or_else(|_| HeaderValue::from_static("inline")),
+                ),
+            ],
+            data,
         )
+            .into_response(),
+        Err(err) => ApiResponse::<()> {
+            response_type: ResponseType::Json(false),
+            data: Err(err),
+        }
+        .into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/days/{day}",
+    params(
+        ("day" = String, Path, description = "Date, like 2023-06-12"),
+        ("lang" = Option<String>, Query, description = "`en` to translate dish names"),
+    ),
+    responses(
+        (status = 200, description = "Menu of the day", body = RatedDayResponse),
+        (status = 400, description = "Unknown language", body = ErrorResponse),
+        (status = 404, description = "Unknown day", body = ErrorResponse),
+    )
+)]
+async fn day_handler(
+    site: Site,
+    State(config): State<Arc<Config>>,
+    State(fallback): State<Arc<Fallback>>,
+    State(ratings): State<Arc<RwLock<Ratings>>>,
+    State(translator): State<Arc<Translator>>,
+    // Calendar apps may only accept the ICS variant.
+    response_type: Result<WithCalendar, Response>,
+    format: ErrorFormat,
+    origin: Origin,
+    Path(DayPath { day: date }): Path<DayPath>,
+    Query(lang): Query<LanguageQuery>,
+) -> Response {
+    async fn process(
+        site: Site,
+        config: &Config,
+        fallback: &Fallback,
+        ratings: Arc<RwLock<Ratings>>,
+        translator: &Translator,
+        date: String,
+        lang: LanguageQuery,
+    ) -> Result<RatedDay, Error> {
+        let date = parse_date(&date).ok_or(Error::InvalidDay)?;
+        let language = lang.language()?;
+        let mut day = site.catalogue.read().await.day(date);
+        let (year, week) = week_of(date);
+        if matches!(day, Err(Error::DayNotFound))
+            && fallback.fetch(config, &site, year, week).await
+        {
+            day = site.catalogue.read().await.day(date);
+        }
+        let day = day?;
+        let scores = ratings.read().await.day(site.name.as_deref(), &day);
+        // Ratings are kept by French name.
+        let translations = translator.translate(language, day.texts()).await;
+        let scores = scores
+            .into_iter()
+            .map(|(dish, score)| (translations.get(&dish).cloned().unwrap_or(dish), score))
+            .collect();
+        Ok(RatedDay::new(
+            day.translated(language, &translations).in_site(&site.path_prefix()),
+            scores,
+        ))
     }
+    // The router can't match a suffix after the date.
+    if let Some(date) = date.strip_suffix(".ics") {
+        return day_ics_handler(site, &config, origin, format, date).await;
+    }
+    let response_type = match response_type {
+        Ok(WithCalendar(ResponseType::Calendar)) => {
+            return day_ics_handler(site, &config, origin, format, &date).await
+        }
+        Ok(WithCalendar(response_type)) => response_type,
+        Err(rejection) => return rejection,
+    };
     ApiResponse {
         response_type,
-        data: process(catalogue, week).await,
+        data: process(site, &config, &fallback, ratings, &translator, date, lang).await,
     }
+    .into_response()
 }
 
-async fn day_handler(
-    State(catalogue): State<Arc<RwLock<Catalogue>>>,
+#[utoipa::path(
+    get,
+    path = "/days/{day}.ics",
+    params(("day" = String, Path, description = "Date, like 2023-06-12")),
+    responses(
+        (status = 200, description = "Lunch event", body = String, content_type = "text/calendar"),
+        (status = 400, description = "Invalid date", body = ErrorResponse),
+        (status = 404, description = "Unknown or closed day", body = ErrorResponse),
+    )
+)]
+async fn day_ics_handler(
+    site: Site,
+    config: &Config,
+    origin: Origin,
+    format: ErrorFormat,
+    date: &str,
+) -> Response {
+    let root = origin.site_url(site.name.as_deref());
+    let event = match parse_date(date) {
+        Some(date) => site.catalogue.read().await.day_ics(
+            date,
+            &config.calendar_namespace,
+            config.calendar_main_dish_summary,
+            Some(&root),
+        ),
+        None => Err(Error::InvalidDay),
+    };
+    match event {
+        Ok(event) => (
+            [
+                (
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("text/calendar; charset=utf-8"),
+                ),
+                (
+                    header::CONTENT_DISPOSITION,
+                    HeaderValue::from_str(&format!(r#"attachment; filename="ovr-{date}.ics""#))
+                        .unwrap_or_else(|_| HeaderValue::from_static("attachment")),
+                ),
+            ],
+            event,
+        )
+            .into_response(),
+        Err(err) => format.respond(err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/days/{day}/history",
+    params(("day" = String, Path, description = "Date, like 2023-06-12")),
+    responses(
+        (status = 200, description = "Current and previous versions", body = DayHistoryResponse),
+        (status = 404, description = "Unknown day", body = ErrorResponse),
+    )
+)]
+async fn history_handler(
+    site: Site,
     response_type: ResponseType,
-    Path(date): Path<String>,
+    Path(DayPath { day: date }): Path<DayPath>,
 ) -> impl IntoResponse {
-    async fn process(catalogue: Arc<RwLock<Catalogue>>, date: String) -> Result<Day, Error> {
+    async fn process(site: Site, date: String) -> Result<DayHistory, Error> {
         let date = parse_date(&date).ok_or(Error::InvalidDay)?;
-        catalogue.read().await.day(date)
+        let history = site.catalogue.read().await.history(date)?;
+        Ok(history.in_site(&site.path_prefix()))
     }
     ApiResponse {
         response_type,
-        data: process(catalogue, date).await,
+        data: process(site, date).await,
     }
 }
 
-async fn ics_handler(State(catalogue): State<Arc<RwLock<Catalogue>>>) -> impl IntoResponse {
-    (
-        [(
-            header::CONTENT_TYPE,
-            HeaderValue::from_static("text/calendar"),
-        )],
-        catalogue.read().await.ics(),
+#[utoipa::path(
+    get,
+    path = "/duplicates",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Merged near-duplicates", body = MergedDishesResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+    )
+)]
+async fn merged_dishes_handler(
+    _: Admin,
+    Site { catalogue, .. }: Site,
+    response_type: ResponseType,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type,
+        data: Ok(catalogue.read().await.merged_dishes()),
+    }
+}
+
+#[derive(Deserialize)]
+struct DishPath {
+    day: String,
+    index: usize,
+}
+
+#[derive(Deserialize)]
+struct AllergensRequest {
+    allergens: Option<Vec<Allergen>>,
+}
+
+#[utoipa::path(
+    put,
+    path = "/days/{day}/dishes/{index}/allergens",
+    security(("admin_token" = [])),
+    params(
+        ("day" = String, Path, description = "Date, like 2023-06-12"),
+        ("index" = usize, Path, description = "Position of the dish in the day, from 0"),
+    ),
+    request_body = AllergensRequestSchema,
+    responses(
+        (status = 200, description = "Day with the new allergens", body = DayResponse),
+        (status = 400, description = "Invalid allergens", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 404, description = "Unknown day or dish", body = ErrorResponse),
+        (status = 412, description = "Catalogue changed since If-Match", body = ErrorResponse),
+    )
+)]
+async fn allergens_handler(
+    _: Admin,
+    site: Site,
+    State(audit): State<Arc<RwLock<AuditLog>>>,
+    source: AuditSource,
+    if_match: IfMatch,
+    Path(DishPath { day: date, index }): Path<DishPath>,
+    body: Bytes,
+) -> impl IntoResponse {
+    async fn process(
+        catalogue: Arc<RwLock<Catalogue>>,
+        if_match: IfMatch,
+        date: String,
+        index: usize,
+        body: Bytes,
+    ) -> Result<Day, Error> {
+        let date = parse_date(&date).ok_or(Error::InvalidDay)?;
+        let request =
+            serde_json::from_slice::<AllergensRequest>(&body).map_err(|_| Error::InvalidJson)?;
+        let mut catalogue = catalogue.write().await;
+        if_match.check(&catalogue)?;
+        catalogue.set_allergens(date, index, request.allergens)
+    }
+
+    let data = process(Arc::clone(&site.catalogue), if_match, date, index, body).await;
+    if let Ok(day) = &data {
+        let update = CatalogueUpdate {
+            replaced: vec![day.date()],
+            ..CatalogueUpdate::default()
+        };
+        audit.write().await.record(
+            &source,
+            AuditAction::Edit,
+            site.name.as_deref(),
+            None,
+            Ok(&update),
+        );
+    }
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/days/{day}/dishes/{index}/rating",
+    params(
+        ("day" = String, Path, description = "Date, like 2023-06-12"),
+        ("index" = usize, Path, description = "Position of the dish in the day, from 0"),
+    ),
+    request_body = RatingRequestSchema,
+    responses(
+        (status = 200, description = "Ratings of the dish", body = DishRatingsResponse),
+        (status = 400, description = "Invalid rating", body = ErrorResponse),
+        (status = 404, description = "Unknown day or dish", body = ErrorResponse),
+    )
+)]
+async fn rate_handler(
+    site: Site,
+    State(ratings): State<Arc<RwLock<Ratings>>>,
+    Path(DishPath { day: date, index }): Path<DishPath>,
+    body: Bytes,
+) -> impl IntoResponse {
+    async fn process(
+        site: Site,
+        ratings: Arc<RwLock<Ratings>>,
+        date: String,
+        index: usize,
+        body: Bytes,
+    ) -> Result<DishRatings, Error> {
+        let date = parse_date(&date).ok_or(Error::InvalidDay)?;
+        let request =
+            serde_json::from_slice::<RatingRequest>(&body).map_err(|_| Error::InvalidJson)?;
+        let day = site.catalogue.read().await.day(date)?;
+        ratings
+            .write()
+            .await
+            .add(site.name.as_deref(), &day, index, request)
+    }
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data: process(site, ratings, date, index, body).await,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/days/{day}/dishes/{index}/rating",
+    params(
+        ("day" = String, Path, description = "Date, like 2023-06-12"),
+        ("index" = usize, Path, description = "Position of the dish in the day, from 0"),
+    ),
+    responses(
+        (status = 200, description = "Score and comments of the dish", body = DishRatingsResponse),
+        (status = 404, description = "Unknown day or dish", body = ErrorResponse),
+    )
+)]
+async fn dish_rating_handler(
+    site: Site,
+    State(ratings): State<Arc<RwLock<Ratings>>>,
+    response_type: ResponseType,
+    Path(DishPath { day: date, index }): Path<DishPath>,
+) -> impl IntoResponse {
+    async fn process(
+        site: Site,
+        ratings: Arc<RwLock<Ratings>>,
+        date: String,
+        index: usize,
+    ) -> Result<DishRatings, Error> {
+        let date = parse_date(&date).ok_or(Error::InvalidDay)?;
+        let day = site.catalogue.read().await.day(date)?;
+        let dish = day.dishes_ref().get(index).ok_or(Error::DishNotFound)?;
+        Ok(ratings.read().await.dish(site.name.as_deref(), dish.name()))
+    }
+    ApiResponse {
+        response_type,
+        data: process(site, ratings, date, index).await,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/days/{day}/rsvp",
+    params(("day" = String, Path, description = "Date, like 2023-06-12")),
+    responses(
+        (status = 200, description = "People going to lunch", body = RsvpResponse),
+        (status = 400, description = "Invalid day", body = ErrorResponse),
+    )
+)]
+async fn attendance_handler(
+    Site { name, .. }: Site,
+    State(rsvps): State<Arc<RwLock<Rsvps>>>,
+    response_type: ResponseType,
+    Path(DayPath { day: date }): Path<DayPath>,
+) -> impl IntoResponse {
+    let data = match parse_date(&date) {
+        Some(date) => Ok(rsvps.read().await.attendance(name.as_deref(), date)),
+        None => Err(Error::InvalidDay),
+    };
+    ApiResponse {
+        response_type,
+        data,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/days/{day}/rsvp",
+    security(("profile_token" = [])),
+    params(("day" = String, Path, description = "Date, like 2023-06-12")),
+    request_body = RsvpRequestSchema,
+    responses(
+        (status = 200, description = "Counted in, only once per token", body = RsvpResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+        (status = 404, description = "Unknown day", body = ErrorResponse),
+    )
+)]
+async fn rsvp_handler(
+    Me(profile): Me,
+    site: Site,
+    State(rsvps): State<Arc<RwLock<Rsvps>>>,
+    Path(DayPath { day: date }): Path<DayPath>,
+    body: Bytes,
+) -> impl IntoResponse {
+    async fn process(
+        profile: Profile,
+        site: Site,
+        rsvps: Arc<RwLock<Rsvps>>,
+        date: String,
+        body: Bytes,
+    ) -> Result<Attendance, Error> {
+        let date = parse_date(&date).ok_or(Error::InvalidDay)?;
+        site.catalogue.read().await.day(date)?;
+        let request = if body.is_empty() {
+            RsvpRequest::default()
+        } else {
+            serde_json::from_slice(&body).map_err(|_| Error::InvalidJson)?
+        };
+        rsvps
+            .write()
+            .await
+            .add(site.name.as_deref(), date, &profile.token, request)
+    }
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data: process(profile, site, rsvps, date, body).await,
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/days/{day}/rsvp",
+    security(("profile_token" = [])),
+    params(("day" = String, Path, description = "Date, like 2023-06-12")),
+    responses(
+        (status = 200, description = "Counted out", body = RsvpResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
     )
+)]
+async fn cancel_rsvp_handler(
+    Me(profile): Me,
+    Site { name, .. }: Site,
+    State(rsvps): State<Arc<RwLock<Rsvps>>>,
+    Path(DayPath { day: date }): Path<DayPath>,
+) -> impl IntoResponse {
+    let data = match parse_date(&date) {
+        Some(date) => rsvps
+            .write()
+            .await
+            .remove(name.as_deref(), date, &profile.token),
+        None => Err(Error::InvalidDay),
+    };
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data,
+    }
+}
+
+#[derive(Deserialize)]
+struct LeaderboardQuery {
+    min_count: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats/ratings",
+    params(("min_count" = Option<usize>, Query, description = "Minimum ratings, 1 by default")),
+    responses((status = 200, description = "Best rated dishes first", body = LeaderboardResponse))
+)]
+async fn leaderboard_handler(
+    Site { name, .. }: Site,
+    State(ratings): State<Arc<RwLock<Ratings>>>,
+    response_type: ResponseType,
+    Query(query): Query<LeaderboardQuery>,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type,
+        data: Ok(ratings
+            .read()
+            .await
+            .leaderboard(name.as_deref(), query.min_count.unwrap_or(1))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/calendar.ics",
+    params(
+        ("upcoming" = Option<bool>, Query, description = "Only from the next meal on"),
+        ("category" = Option<String>, Query, description = "Only one category, like mains"),
+    ),
+    responses(
+        (status = 200, description = "Lunch events", body = String, content_type = "text/calendar"),
+        (status = 400, description = "Unknown category", body = ErrorResponse),
+    )
+)]
+async fn ics_handler(
+    State(config): State<Arc<Config>>,
+    State(cache): State<Arc<ResponseCache>>,
+    site: Site,
+    origin: Origin,
+    format: ErrorFormat,
+    Query(query): Query<CalendarQuery>,
+) -> Response {
+    let category = query
+        .category
+        .as_deref()
+        .map(|field| DishCategory::from_field_str(field).ok_or(Error::InvalidCategory))
+        .transpose();
+    let category = match category {
+        Ok(category) => category,
+        Err(err) => return format.respond(err),
+    };
+    let filter = CalendarFilter {
+        upcoming: query.upcoming,
+        category,
+    };
+    cache
+        .respond(&site, &config, CachedRoute::Calendar(origin, filter))
+        .await
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct CalendarQuery {
+    #[serde(default)]
+    upcoming: bool,
+    category: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/export",
+    responses((status = 200, description = "Backup of every day", body = CatalogueExportSchema))
+)]
+async fn export_handler(Site { catalogue, .. }: Site) -> impl IntoResponse {
+    (
+        [(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static(r#"attachment; filename="ovr-export.json""#),
+        )],
+        Json(catalogue.read().await.export()),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/snapshot",
+    responses((
+        status = 200,
+        description = "Every day with the catalogue version",
+        body = CatalogueSnapshot,
+        headers(("X-Catalogue-Version" = String, description = "Also sent by every read route")),
+    ))
+)]
+async fn snapshot_handler(Site { catalogue, .. }: Site) -> impl IntoResponse {
+    Json(catalogue.read().await.snapshot())
+}
+
+#[utoipa::path(
+    get,
+    path = "/integrations/home-assistant",
+    responses(
+        (status = 200, description = "Today's menu as a sensor", body = HomeAssistantSensorSchema),
+    )
+)]
+async fn home_assistant_handler(Site { catalogue, .. }: Site) -> impl IntoResponse {
+    Json(HomeAssistantSensor::new(catalogue.read().await.today().as_ref()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/integrations/home-assistant/discovery",
+    responses((status = 200, description = "MQTT discovery payload of the sensor"))
+)]
+async fn home_assistant_discovery_handler(State(config): State<Arc<Config>>) -> impl IntoResponse {
+    Json(home_assistant_discovery(config.mqtt.as_ref()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/integrations/alexa",
+    request_body = AlexaRequest,
+    responses(
+        (status = 200, description = "Alexa Skills Kit response", body = AlexaResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 401, description = "Other skill", body = ErrorResponse),
+    )
+)]
+async fn alexa_handler(
+    Site { catalogue, .. }: Site,
+    State(config): State<Arc<Config>>,
+    body: Bytes,
+) -> Response {
+    let envelope = match serde_json::from_slice::<AlexaEnvelope>(&body) {
+        Ok(envelope) => envelope,
+        Err(_) => return Error::InvalidJson.into_response(),
+    };
+    if let Some(skill_id) = &config.alexa_skill_id {
+        if envelope.application_id() != Some(skill_id.as_str()) {
+            return Error::Unauthorized.into_response();
+        }
+    }
+    Json(alexa_response(&envelope, &catalogue.read().await)).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/integrations/dialogflow",
+    request_body = DialogflowRequestSchema,
+    responses(
+        (status = 200, description = "Fulfillment text", body = DialogflowResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+    )
+)]
+async fn dialogflow_handler(
+    Site { catalogue, .. }: Site,
+    State(config): State<Arc<Config>>,
+    body: Bytes,
+) -> Response {
+    match serde_json::from_slice::<DialogflowRequest>(&body) {
+        Ok(request) => Json(dialogflow_response(
+            &request,
+            &catalogue.read().await,
+            config.cutoff,
+        ))
+        .into_response(),
+        Err(_) => Error::InvalidJson.into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/import",
+    security(("admin_token" = [])),
+    request_body = CatalogueExportSchema,
+    responses(
+        (status = 200, description = "Catalogue replaced", body = UpdateResponse),
+        (status = 400, description = "Invalid backup", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 412, description = "Catalogue changed since If-Match", body = ErrorResponse),
+    )
+)]
+async fn import_handler(
+    _: Admin,
+    site: Site,
+    State(audit): State<Arc<RwLock<AuditLog>>>,
+    source: AuditSource,
+    if_match: IfMatch,
+    body: Bytes,
+) -> impl IntoResponse {
+    async fn process(
+        catalogue: Arc<RwLock<Catalogue>>,
+        if_match: IfMatch,
+        body: Bytes,
+    ) -> Result<CatalogueUpdate, Error> {
+        let export =
+            serde_json::from_slice::<CatalogueExport>(&body).map_err(|_| Error::InvalidJson)?;
+        if export.version != CatalogueExport::VERSION {
+            return Err(Error::UnsupportedExportVersion);
+        }
+        let mut catalogue = catalogue.write().await;
+        if_match.check(&catalogue)?;
+        Ok(catalogue.restore(export.days))
+    }
+
+    let data = process(Arc::clone(&site.catalogue), if_match, body).await;
+    audit.write().await.record(
+        &source,
+        AuditAction::Import,
+        site.name.as_deref(),
+        None,
+        data.as_ref(),
+    );
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/undo",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Last upload reverted", body = UpdateResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 409, description = "Nothing to undo", body = ErrorResponse),
+        (status = 412, description = "Catalogue changed since If-Match", body = ErrorResponse),
+    )
+)]
+async fn undo_handler(
+    _: Admin,
+    site: Site,
+    State(audit): State<Arc<RwLock<AuditLog>>>,
+    source: AuditSource,
+    if_match: IfMatch,
+) -> impl IntoResponse {
+    let data = {
+        let mut catalogue = site.catalogue.write().await;
+        if_match.check(&catalogue).and_then(|_| catalogue.undo())
+    };
+    if let Ok(update) = &data {
+        audit.write().await.record(
+            &source,
+            AuditAction::Undo,
+            site.name.as_deref(),
+            None,
+            Ok(update),
+        );
+    }
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data,
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/days/{day}",
+    security(("admin_token" = [])),
+    params(("day" = String, Path, description = "Date, like 2023-06-12")),
+    responses(
+        (status = 200, description = "Day deleted", body = UpdateResponse),
+        (status = 400, description = "Invalid date", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 404, description = "Unknown day", body = ErrorResponse),
+        (status = 412, description = "Catalogue changed since If-Match", body = ErrorResponse),
+    )
+)]
+async fn delete_day_handler(
+    _: Admin,
+    site: Site,
+    State(audit): State<Arc<RwLock<AuditLog>>>,
+    source: AuditSource,
+    if_match: IfMatch,
+    Path(DayPath { day }): Path<DayPath>,
+) -> impl IntoResponse {
+    let data = match parse_date(&day) {
+        Some(date) => {
+            let mut catalogue = site.catalogue.write().await;
+            if_match
+                .check(&catalogue)
+                .and_then(|_| catalogue.remove_day(date))
+        }
+        None => Err(Error::InvalidDay),
+    };
+    deleted(&site, &audit, &source, data).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/weeks/{week}",
+    security(("admin_token" = [])),
+    params(("week" = String, Path, description = "ISO week, like 2023-24")),
+    responses(
+        (status = 200, description = "Days of the week deleted", body = UpdateResponse),
+        (status = 400, description = "Invalid week", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 404, description = "Unknown week", body = ErrorResponse),
+        (status = 412, description = "Catalogue changed since If-Match", body = ErrorResponse),
+    )
+)]
+async fn delete_week_handler(
+    _: Admin,
+    site: Site,
+    State(audit): State<Arc<RwLock<AuditLog>>>,
+    source: AuditSource,
+    if_match: IfMatch,
+    Path(WeekPath { week }): Path<WeekPath>,
+) -> impl IntoResponse {
+    let data = match parse_week(&week) {
+        Some((year, week)) => {
+            let mut catalogue = site.catalogue.write().await;
+            if_match
+                .check(&catalogue)
+                .and_then(|_| catalogue.remove_week(year, week))
+        }
+        None => Err(Error::InvalidWeek),
+    };
+    deleted(&site, &audit, &source, data).await
+}
+
+async fn deleted(
+    site: &Site,
+    audit: &RwLock<AuditLog>,
+    source: &AuditSource,
+    data: Result<CatalogueUpdate, Error>,
+) -> ApiResponse<CatalogueUpdate> {
+    if let Ok(update) = &data {
+        audit.write().await.record(
+            source,
+            AuditAction::Delete,
+            site.name.as_deref(),
+            None,
+            Ok(update),
+        );
+    }
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/reparse",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Every kept PDF was parsed again", body = UploadResponse),
+        (status = 207, description = "Some PDFs failed", body = UploadResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 503, description = "Too many uploads being parsed", body = ErrorResponse),
+    )
+)]
+async fn reparse_handler(
+    _: Admin,
+    _slot: UploadSlot,
+    site: Site,
+    State(config): State<Arc<Config>>,
+    State(audit): State<Arc<RwLock<AuditLog>>>,
+    source: AuditSource,
+) -> Response {
+    // Read and parsed before the catalogue is locked, so reads go on
+    // meanwhile.
+    let parsing = site
+        .sources
+        .all()
+        .await
+        .into_iter()
+        .map(|(name, data)| {
+            let config = Arc::clone(&config);
+            let parsing = task::spawn_blocking(move || FileKind::Pdf.parse(&data, &config));
+            (name, parsing)
+        })
+        .collect::<Vec<_>>();
+    let mut parsed = Vec::with_capacity(parsing.len());
+    for (name, parsing) in parsing {
+        match parsing.await {
+            Ok(days) => parsed.push((name, days)),
+            Err(_) => {
+                return ApiResponse::<()> {
+                    response_type: ResponseType::Json(false),
+                    data: Err(Error::Internal),
+                }
+                .into_response()
+            }
+        }
+    }
+
+    let mut catalogue = site.catalogue.write().await;
+    let mut audit = audit.write().await;
+    // Undone as a whole, like an upload of every file.
+    let upload = catalogue.start_upload();
+    let mut report = UploadReport::new(site.path_prefix());
+    for (name, days) in parsed {
+        let result =
+            days.map(|days| catalogue.insert_as(upload, days, MergePolicy::Replace, false));
+        match &result {
+            Ok(update) => audit.record(
+                &source,
+                AuditAction::Reparse,
+                site.name.as_deref(),
+                Some(&name),
+                Ok(update),
+            ),
+            Err(err) => audit.record(
+                &source,
+                AuditAction::Reparse,
+                site.name.as_deref(),
+                Some(&name),
+                Err(&err.error()),
+            ),
+        }
+        report.push(Some(name), result, false);
+    }
+    (
+        report.status_code(),
+        ApiResponse {
+            response_type: ResponseType::Json(false),
+            data: Ok(report),
+        },
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/sites",
+    responses((status = 200, description = "Today's menu of every site", body = SitesResponse))
+)]
+async fn sites_handler(
+    State(sites): State<Arc<Sites>>,
+    response_type: ResponseType,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type,
+        data: Ok(sites.overview().await),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/version",
+    responses((status = 200, description = "Running build", body = VersionResponse))
+)]
+async fn version_handler(
+    State(sites): State<Arc<Sites>>,
+    response_type: ResponseType,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type,
+        data: Ok(BuildInfo::new(sites.count())),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Every catalogue change", body = AuditResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+    )
+)]
+async fn audit_handler(
+    _: Admin,
+    State(audit): State<Arc<RwLock<AuditLog>>>,
+    response_type: ResponseType,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type,
+        data: Ok(audit.read().await.entries()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/sources",
+    responses(
+        (status = 200, description = "Health of every ingestion source", body = SourcesResponse),
+    )
+)]
+async fn ingestion_handler(
+    State(ingestion): State<Arc<RwLock<Ingestion>>>,
+    response_type: ResponseType,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type,
+        data: Ok(ingestion.read().await.health()),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/deliveries",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Failed notifications", body = DeliveriesResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+    )
+)]
+async fn deliveries_handler(
+    _: Admin,
+    State(dead_letters): State<Arc<RwLock<DeadLetters>>>,
+    response_type: ResponseType,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type,
+        data: Ok(dead_letters.read().await.entries()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/watches",
+    request_body = WatchRequestSchema,
+    responses(
+        (status = 200, description = "Watch created", body = WatchResponse),
+        (status = 400, description = "Invalid watch", body = ErrorResponse),
+        (status = 404, description = "Unknown site", body = ErrorResponse),
+    )
+)]
+async fn watch_handler(
+    State(sites): State<Arc<Sites>>,
+    State(config): State<Arc<Config>>,
+    State(watches): State<Arc<RwLock<Watches>>>,
+    body: Bytes,
+) -> impl IntoResponse {
+    async fn process(
+        sites: Arc<Sites>,
+        config: Arc<Config>,
+        watches: Arc<RwLock<Watches>>,
+        body: Bytes,
+    ) -> Result<Watch, Error> {
+        let request =
+            serde_json::from_slice::<WatchRequest>(&body).map_err(|_| Error::InvalidJson)?;
+        sites.get(request.site.as_deref()).ok_or(Error::SiteNotFound)?;
+        watches.write().await.add(
+            request,
+            config.email.is_some(),
+            config.web_push.is_some(),
+        )
+    }
+
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data: process(sites, config, watches, body).await,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/watches",
+    security(("admin_token" = [])),
+    responses(
+        (status = 200, description = "Every watch", body = WatchListResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+    )
+)]
+async fn watches_handler(
+    _: Admin,
+    State(watches): State<Arc<RwLock<Watches>>>,
+    response_type: ResponseType,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type,
+        data: Ok(watches.read().await.list()),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/watches/{id}",
+    params(("id" = String, Path, description = "Watch identifier")),
+    responses(
+        (status = 200, description = "Watch removed", body = WatchResponse),
+        (status = 404, description = "Unknown watch", body = ErrorResponse),
+    )
+)]
+async fn unwatch_handler(
+    State(watches): State<Arc<RwLock<Watches>>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data: watches.write().await.remove(&id),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/push/key",
+    responses(
+        (status = 200, description = "VAPID public key", body = PushKeyResponse),
+        (status = 404, description = "Web Push disabled", body = ErrorResponse),
+    )
+)]
+async fn push_key_handler(
+    State(config): State<Arc<Config>>,
+    response_type: ResponseType,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type,
+        data: config
+            .web_push
+            .as_ref()
+            .map(|web_push| PushKey {
+                public_key: web_push.public_key.clone(),
+            })
+            .ok_or(Error::PushDisabled),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/push/subscriptions",
+    request_body = PushSubscriptionSchema,
+    responses(
+        (status = 200, description = "Subscribed to today's menu", body = SuccessResponse),
+        (status = 400, description = "Invalid subscription", body = ErrorResponse),
+        (status = 404, description = "Web Push disabled or unknown site", body = ErrorResponse),
+    )
+)]
+async fn subscribe_handler(
+    State(sites): State<Arc<Sites>>,
+    State(config): State<Arc<Config>>,
+    State(subscriptions): State<Arc<RwLock<PushSubscriptions>>>,
+    body: Bytes,
+) -> impl IntoResponse {
+    async fn process(
+        sites: Arc<Sites>,
+        config: Arc<Config>,
+        subscriptions: Arc<RwLock<PushSubscriptions>>,
+        body: Bytes,
+    ) -> Result<(), Error> {
+        config.web_push.as_ref().ok_or(Error::PushDisabled)?;
+        let subscription =
+            serde_json::from_slice::<PushSubscription>(&body).map_err(|_| Error::InvalidJson)?;
+        sites.get(subscription.site.as_deref()).ok_or(Error::SiteNotFound)?;
+        subscriptions.write().await.add(subscription)
+    }
+
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data: process(sites, config, subscriptions, body).await,
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/push/subscriptions",
+    request_body = UnsubscribeRequest,
+    responses(
+        (status = 200, description = "Subscription removed", body = SuccessResponse),
+        (status = 404, description = "Unknown subscription", body = ErrorResponse),
+    )
+)]
+async fn unsubscribe_handler(
+    State(subscriptions): State<Arc<RwLock<PushSubscriptions>>>,
+    body: Bytes,
+) -> impl IntoResponse {
+    #[derive(Deserialize)]
+    struct Unsubscribe {
+        endpoint: String,
+    }
+
+    let data = match serde_json::from_slice::<Unsubscribe>(&body) {
+        Ok(Unsubscribe { endpoint }) => subscriptions.write().await.remove(&endpoint),
+        Err(_) => Err(Error::InvalidJson),
+    };
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/profiles",
+    request_body = PreferencesSchema,
+    responses(
+        (status = 200, description = "Profile created, keep its token", body = ProfileResponse),
+        (status = 400, description = "Invalid preferences", body = ErrorResponse),
+    )
+)]
+async fn create_profile_handler(
+    State(profiles): State<Arc<RwLock<Profiles>>>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let data = match serde_json::from_slice::<Preferences>(&body) {
+        Ok(preferences) => profiles.write().await.create(preferences),
+        Err(_) => Err(Error::InvalidJson),
+    };
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me",
+    security(("profile_token" = [])),
+    responses(
+        (status = 200, description = "Preferences of the profile", body = ProfileResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+    )
+)]
+async fn profile_handler(Me(profile): Me) -> impl IntoResponse {
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data: Ok(profile),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/me",
+    security(("profile_token" = [])),
+    request_body = PreferencesSchema,
+    responses(
+        (status = 200, description = "Preferences replaced", body = ProfileResponse),
+        (status = 400, description = "Invalid preferences", body = ErrorResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+    )
+)]
+async fn update_profile_handler(
+    Me(profile): Me,
+    State(profiles): State<Arc<RwLock<Profiles>>>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let data = match serde_json::from_slice::<Preferences>(&body) {
+        Ok(preferences) => profiles.write().await.update(&profile.token, preferences),
+        Err(_) => Err(Error::InvalidJson),
+    };
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data,
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/me",
+    security(("profile_token" = [])),
+    responses(
+        (status = 200, description = "Profile deleted", body = SuccessResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+    )
+)]
+async fn delete_profile_handler(
+    Me(profile): Me,
+    State(profiles): State<Arc<RwLock<Profiles>>>,
+) -> impl IntoResponse {
+    ApiResponse {
+        response_type: ResponseType::Json(false),
+        data: profiles.write().await.remove(&profile.token),
+    }
+}
+
+#[derive(Deserialize)]
+struct PersonalQuery {
+    #[serde(default)]
+    strip: bool,
+    cutoff: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/today",
+    security(("profile_token" = [])),
+    params(("strip" = Option<bool>, Query, description = "Remove avoided dishes")),
+    responses(
+        (status = 200, description = "Today's menu for the profile", body = PersonalDayResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+        (status = 404, description = "No meal today", body = ErrorResponse),
+    )
+)]
+async fn personal_today_handler(
+    Me(profile): Me,
+    site: Site,
+    response_type: ResponseType,
+    Query(query): Query<PersonalQuery>,
+) -> impl IntoResponse {
+    let root = site.path_prefix();
+    ApiResponse {
+        response_type,
+        data: site.catalogue
+            .read()
+            .await
+            .today()
+            .map(|day| PersonalDay::new(day.in_site(&root), &profile.preferences, query.strip))
+            .ok_or(Error::NoMealToday),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/next",
+    security(("profile_token" = [])),
+    params(
+        ("strip" = Option<bool>, Query, description = "Remove avoided dishes"),
+        ("cutoff" = Option<String>, Query, description = "End of today's meal, like 13:30"),
+    ),
+    responses(
+        (status = 200, description = "Next menu for the profile", body = PersonalDayResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+        (status = 404, description = "No next meal", body = ErrorResponse),
+    )
+)]
+async fn personal_next_handler(
+    Me(profile): Me,
+    site: Site,
+    State(config): State<Arc<Config>>,
+    response_type: ResponseType,
+    Query(query): Query<PersonalQuery>,
+) -> impl IntoResponse {
+    async fn process(
+        site: Site,
+        config: &Config,
+        profile: Profile,
+        query: PersonalQuery,
+    ) -> Result<PersonalDay, Error> {
+        let cutoff = cutoff_time(query.cutoff.as_deref(), config)?;
+        let root = site.path_prefix();
+        site.catalogue
+            .read()
+            .await
+            .next(cutoff)
+            .map(|day| PersonalDay::new(day.in_site(&root), &profile.preferences, query.strip))
+            .ok_or(Error::NoNextMeal)
+    }
+    ApiResponse {
+        response_type,
+        data: process(site, &config, profile, query).await,
+    }
+}
+
+async fn service_worker_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/javascript")],
+        include_str!("sw.js"),
+    )
+}
+
+// Registers the service worker and shows the Web Push button.
+async fn app_script_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/javascript")],
+        include_str!("app.js"),
+    )
+}
+
+async fn manifest_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/manifest+json")],
+        include_str!("manifest.webmanifest"),
+    )
+}
+
+async fn icon_handler(State(icons): State<Arc<Icons>>, uri: Uri) -> Response {
+    icons
+        .response(uri.path())
+        .unwrap_or_else(|| StatusCode::NOT_FOUND.into_response())
+}
+
+async fn robots_handler(State(config): State<Arc<Config>>, origin: Origin) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        config.crawlers.robots_txt(&origin),
+    )
+}
+
+async fn sitemap_handler(
+    State(config): State<Arc<Config>>,
+    State(sites): State<Arc<Sites>>,
+    origin: Origin,
+) -> Response {
+    if !config.crawlers.sitemap {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    (
+        [(header::CONTENT_TYPE, "application/xml")],
+        crawlers::sitemap(&sites, &origin).await,
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/login",
+    responses(
+        (status = 200, description = "Admin login form", body = String, content_type = "text/html"),
+    )
+)]
+async fn login_page_handler() -> impl IntoResponse {
+    Html(html_page(include_str!("login.html"), 1.0, true))
+}
+
+#[derive(Deserialize)]
+struct LoginForm {
+    token: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/login",
+    request_body(content = LoginRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Session cookie set, redirected to the index"),
+        (status = 401, description = "Invalid token", body = ErrorResponse),
+        (status = 403, description = "No session secret configured", body = ErrorResponse),
+    )
+)]
+async fn login_handler(
+    State(config): State<Arc<Config>>,
+    format: ErrorFormat,
+    origin: Origin,
+    form: Option<Form<LoginForm>>,
+) -> Response {
+    let token = form.map(|Form(LoginForm { token })| token).unwrap_or_default();
+    match auth::login(&token, &config, origin.is_secure()) {
+        Ok(cookie) => (
+            StatusCode::SEE_OTHER,
+            [
+                (header::LOCATION, HeaderValue::from_static("/")),
+                (header::SET_COOKIE, cookie),
+            ],
+        )
+            .into_response(),
+        Err(err) => format.respond(err),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/logout",
+    responses(
+        (status = 303, description = "Session cookie cleared, redirected to the index"),
+    )
+)]
+async fn logout_handler(format: ErrorFormat, origin: Origin) -> Response {
+    match auth::logout(origin.is_secure()) {
+        Ok(cookie) => (
+            StatusCode::SEE_OTHER,
+            [
+                (header::LOCATION, HeaderValue::from_static("/")),
+                (header::SET_COOKIE, cookie),
+            ],
+        )
+            .into_response(),
+        Err(err) => format.respond(err),
+    }
+}
+
+async fn openapi_handler() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
+
+async fn docs_handler() -> impl IntoResponse {
+    Html(include_str!("docs.html"))
+}
+
+async fn not_found_handler(format: ErrorFormat) -> Response {
+    format.respond(Error::RouteNotFound)
+}
+
+async fn schema_handler(Path(file): Path<String>) -> Response {
+    match schema::get(&file) {
+        Some(schema) => (
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/schema+json"),
+            )],
+            schema,
+        )
+            .into_response(),
+        None => ApiResponse::<()> {
+            response_type: ResponseType::Json(false),
+            data: Err(Error::SchemaNotFound),
+        }
+        .into_response(),
+    }
 }
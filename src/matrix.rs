@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use matrix_sdk::{
+    config::SyncSettings,
+    room::Room,
+    ruma::{
+        events::room::message::{
+            MessageType, OriginalSyncRoomMessageEvent, RoomMessageEventContent,
+        },
+        OwnedRoomOrAliasId,
+    },
+    Client,
+};
+use serde::Deserialize;
+use tokio::time::{interval, Duration};
+
+use crate::{
+    config::Config,
+    holidays,
+    integrations::{menu_speech, upcoming_speech},
+    response::TextRepresentable,
+    site::Sites,
+    utils::now_local,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct MatrixConfig {
+    pub homeserver: String,
+    pub username: String,
+    pub password: String,
+    pub rooms: Vec<String>,
+    pub site: Option<String>,
+    // Posts today's menu in every room at `announce_at` on open days.
+    #[serde(default = "MatrixConfig::default_daily")]
+    pub daily: bool,
+}
+
+impl MatrixConfig {
+    const fn default_daily() -> bool {
+        true
+    }
+}
+
+// Failures are only logged, the rest of the server doesn't depend on the bot.
+pub fn spawn(sites: Arc<Sites>, config: Arc<Config>) {
+    if config.matrix.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(err) = run(sites, config).await {
+            eprintln!("matrix: {err}");
+        }
+    });
+}
+
+async fn run(sites: Arc<Sites>, config: Arc<Config>) -> Result<(), String> {
+    let matrix = config
+        .matrix
+        .as_ref()
+        .expect("matrix configuration missing");
+    let client = Client::builder()
+        .homeserver_url(&matrix.homeserver)
+        .build()
+        .await
+        .map_err(|err| err.to_string())?;
+    client
+        .login_username(&matrix.username, &matrix.password)
+        .initial_device_display_name("ovr")
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let mut rooms = Vec::with_capacity(matrix.rooms.len());
+    for room in &matrix.rooms {
+        let Ok(room) = OwnedRoomOrAliasId::try_from(room.as_str()) else {
+            eprintln!("matrix: invalid room {room}");
+            continue;
+        };
+        rooms.push(
+            client
+                .join_room_by_id_or_alias(&room, &[])
+                .await
+                .map_err(|err| err.to_string())?
+                .room_id()
+                .to_owned(),
+        );
+    }
+
+    {
+        let sites = Arc::clone(&sites);
+        let config = Arc::clone(&config);
+        client.add_event_handler(move |event: OriginalSyncRoomMessageEvent, room: Room| {
+            let sites = Arc::clone(&sites);
+            let config = Arc::clone(&config);
+            async move {
+                let (Room::Joined(room), MessageType::Text(text)) = (room, event.content.msgtype)
+                else {
+                    return;
+                };
+                let Some(reply) = answer(&sites, &config, &text.body).await else {
+                    return;
+                };
+                if let Err(err) = room
+                    .send(RoomMessageEventContent::text_plain(reply), None)
+                    .await
+                {
+                    eprintln!("matrix: {err}");
+                }
+            }
+        });
+    }
+
+    if matrix.daily {
+        let client = client.clone();
+        let sites = Arc::clone(&sites);
+        let config = Arc::clone(&config);
+        tokio::spawn(async move {
+            let matrix = config
+                .matrix
+                .as_ref()
+                .expect("matrix configuration missing");
+            let mut posted_on =
+                (now_local().time() >= config.announce_at).then(|| now_local().date());
+            let mut interval = interval(Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                let now = now_local();
+                if now.time() < config.announce_at
+                    || posted_on == Some(now.date())
+                    || !holidays::is_open(now.date())
+                {
+                    continue;
+                }
+                posted_on = Some(now.date());
+                let Some(site) = sites.get(matrix.site.as_deref()) else {
+                    continue;
+                };
+                let Ok(day) = site.catalogue.read().await.day(now.date()) else {
+                    continue;
+                };
+                let content = RoomMessageEventContent::text_plain(day.as_plain_text(true));
+                for room in &rooms {
+                    let Some(room) = client.get_joined_room(room) else {
+                        continue;
+                    };
+                    if let Err(err) = room.send(content.clone(), None).await {
+                        eprintln!("matrix: {err}");
+                    }
+                }
+            }
+        });
+    }
+
+    client
+        .sync(SyncSettings::default())
+        .await
+        .map_err(|err| err.to_string())
+}
+
+// "!menu", "!menu demain" or "!menu find <dish>".
+async fn answer(sites: &Sites, config: &Config, message: &str) -> Option<String> {
+    let arguments = message.trim().strip_prefix("!menu")?;
+    if !arguments.is_empty() && !arguments.starts_with(' ') {
+        return None;
+    }
+    let matrix = config.matrix.as_ref()?;
+    let site = sites.get(matrix.site.as_deref())?;
+    let catalogue = site.catalogue.read().await;
+    Some(
+        match arguments
+            .trim()
+            .split_once(' ')
+            .unwrap_or((arguments.trim(), ""))
+        {
+            ("", _) => menu_speech(&catalogue, None),
+            ("demain", _) => menu_speech(&catalogue, now_local().date().next_day()),
+            ("find", dish) if !dish.trim().is_empty() => upcoming_speech(
+                catalogue.find_dish_next(vec![dish.trim().to_owned()], &[], config.cutoff),
+            ),
+            _ => "Commandes : !menu, !menu demain, !menu find <plat>".to_owned(),
+        },
+    )
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, ClientError, MqttOptions, QoS};
+use serde::Deserialize;
+use tokio::time::sleep;
+
+use crate::{
+    day::Day,
+    integrations::{home_assistant_discovery, HOME_ASSISTANT_DISCOVERY_TOPIC},
+};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize, Debug)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "MqttConfig::default_port")]
+    pub port: u16,
+    #[serde(default = "MqttConfig::default_client_id")]
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default = "MqttConfig::default_topic")]
+    pub topic: String,
+    pub site: Option<String>,
+    // Announces the sensor to Home Assistant.
+    #[serde(default)]
+    pub discovery: bool,
+}
+
+impl MqttConfig {
+    const fn default_port() -> u16 {
+        1883
+    }
+
+    fn default_client_id() -> String {
+        "ovr".to_owned()
+    }
+
+    fn default_topic() -> String {
+        "ovr/today".to_owned()
+    }
+}
+
+pub struct Mqtt {
+    client: AsyncClient,
+    topic: String,
+}
+
+impl Mqtt {
+    // The connection is kept (and re-established) by a background task.
+    pub fn connect(config: &MqttConfig) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            options.set_credentials(username, password);
+        }
+        let (client, mut event_loop) = AsyncClient::new(options, 10);
+        if config.discovery {
+            let payload = home_assistant_discovery(Some(config)).to_string();
+            if let Err(err) = client.try_publish(
+                HOME_ASSISTANT_DISCOVERY_TOPIC,
+                QoS::AtLeastOnce,
+                true,
+                payload,
+            ) {
+                eprintln!("mqtt: {err}");
+            }
+        }
+        tokio::spawn(async move {
+            loop {
+                if let Err(err) = event_loop.poll().await {
+                    eprintln!("mqtt: {err}");
+                    sleep(RECONNECT_DELAY).await;
+                }
+            }
+        });
+        Self {
+            client,
+            topic: config.topic.clone(),
+        }
+    }
+
+    // Retained, so subscribers get today's menu as soon as they connect. `null`
+    // when there's no menu today.
+    pub async fn publish(&self, day: Option<&Day>) -> Result<(), ClientError> {
+        let payload = serde_json::to_vec(&day).expect("mqtt payload serialization failed");
+        self.client
+            .publish(&self.topic, QoS::AtLeastOnce, true, payload)
+            .await
+    }
+}
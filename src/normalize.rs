@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+// Clean-up of dish names applied to every upload, before notes are
+// extracted.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct NormalizationConfig {
+    pub collapse_whitespace: bool,
+    // Joins words cut at the end of a PDF line, like "Pom- mes".
+    pub repair_hyphenation: bool,
+    pub casing: Casing,
+    // Whole words, matched ignoring case, like `"bourguigon" = "bourguignon"`.
+    pub corrections: HashMap<String, String>,
+    // Keeps the name as it was read, shown as `raw` in JSON.
+    pub keep_raw: bool,
+    // Dishes of a day at least this similar are merged into the first one,
+    // 1.0 only merges names differing by case and punctuation.
+    pub similarity_threshold: f64,
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            collapse_whitespace: true,
+            repair_hyphenation: true,
+            casing: Casing::default(),
+            corrections: HashMap::new(),
+            keep_raw: false,
+            similarity_threshold: 0.9,
+        }
+    }
+}
+
+#[derive(Deserialize, Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Casing {
+    #[default]
+    Keep,
+    // "SALADE VERTE" and "salade verte" both become "Salade verte".
+    Sentence,
+}
+
+impl NormalizationConfig {
+    pub fn normalize(&self, name: &str) -> String {
+        let mut name = name.to_owned();
+        if self.repair_hyphenation {
+            name = repair_hyphenation(&name);
+        }
+        if self.collapse_whitespace {
+            name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if !self.corrections.is_empty() {
+            name = self.correct(&name);
+        }
+        if self.casing == Casing::Sentence {
+            name = sentence_case(&name);
+        }
+        name
+    }
+
+    pub fn is_duplicate(&self, a: &str, b: &str) -> bool {
+        similarity(a, b) >= self.similarity_threshold
+    }
+
+    fn correct(&self, name: &str) -> String {
+        let corrections = self
+            .corrections
+            .iter()
+            .map(|(typo, correction)| (typo.to_lowercase(), correction.as_str()))
+            .collect::<HashMap<_, _>>();
+        name.split(' ')
+            .map(|word| {
+                corrections
+                    .get(&word.to_lowercase())
+                    .copied()
+                    .unwrap_or(word)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// A hyphen right after a letter and followed by spaces then a lowercase
+// letter is a line break in the middle of a word. "Chou-fleur" and
+// "Poisson - riz" are left alone.
+fn repair_hyphenation(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut repaired = String::with_capacity(name.len());
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index] == '-' && index > 0 && chars[index - 1].is_alphabetic() {
+            let next = chars[index + 1..]
+                .iter()
+                .position(|c| !c.is_whitespace())
+                .map(|offset| index + 1 + offset);
+            if let Some(next) = next.filter(|&next| next > index + 1) {
+                if chars[next].is_lowercase() {
+                    index = next;
+                    continue;
+                }
+            }
+        }
+        repaired.push(chars[index]);
+        index += 1;
+    }
+    repaired
+}
+
+fn sentence_case(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.as_str().to_lowercase().chars())
+            .collect(),
+        None => String::new(),
+    }
+}
+
+// Normalized Levenshtein distance between the names, ignoring case and
+// punctuation: 1.0 for "Salade verte" and "salade verte.".
+fn similarity(a: &str, b: &str) -> f64 {
+    let a = comparison_key(a);
+    let b = comparison_key(b);
+    let length = a.len().max(b.len());
+    if length == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(&a, &b) as f64 / length as f64
+}
+
+fn comparison_key(name: &str) -> Vec<char> {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect()
+}
+
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,449 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use itertools::Itertools;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use time::Date;
+use tokio::{sync::RwLock, time::interval};
+
+use crate::{
+    catalogue::week_of,
+    config::Config,
+    day::Day,
+    delivery::{DeadLetters, Deliverer},
+    holidays,
+    mailer::Mailer,
+    mqtt::Mqtt,
+    push::{PushError, PushSubscription, PushSubscriptions, Pusher},
+    response::TextRepresentable,
+    site::Sites,
+    utils::{format_date, now_local},
+    watches::{WatchTarget, Watches},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+const COLOR: u32 = 0x15b154;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub kind: WebhookKind,
+    pub site: Option<String>,
+    #[serde(default)]
+    pub day: AnnouncedDay,
+    // Bearer token for ntfy, application token for Gotify.
+    pub token: Option<String>,
+    // Only announce days with one of these dishes.
+    #[serde(default)]
+    pub watch: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookKind {
+    #[default]
+    Discord,
+    Teams,
+    Mattermost,
+    Ntfy,
+    Gotify,
+}
+
+#[derive(Deserialize, Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnouncedDay {
+    #[default]
+    Today,
+    Tomorrow,
+}
+
+// Announced once a day at `announce_at`, then again whenever the menu of that
+// day changes. A restart after `announce_at` doesn't repeat the announcement.
+pub fn spawn(
+    sites: Arc<Sites>,
+    config: Arc<Config>,
+    watches: Arc<RwLock<Watches>>,
+    subscriptions: Arc<RwLock<PushSubscriptions>>,
+    dead_letters: Arc<RwLock<DeadLetters>>,
+) -> Result<(), String> {
+    let mut notifier = Notifier {
+        deliverer: Deliverer::new(config.webhook_secret.clone(), dead_letters),
+        announced: vec![None; config.webhooks.len()],
+        mailer: config.email.as_ref().map(Mailer::new).transpose()?,
+        mailed_on: None,
+        known_weeks: None,
+        watched: HashSet::new(),
+        known_watches: HashSet::new(),
+        pusher: config.web_push.as_ref().map(Pusher::new).transpose()?,
+        pushed_on: None,
+        mqtt: config.mqtt.as_ref().map(Mqtt::connect),
+        published: None,
+    };
+    tokio::spawn(async move {
+        let mut first = true;
+        let mut interval = interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            notifier.announce(&sites, &config, first).await;
+            notifier.mail(&sites, &config, first).await;
+            notifier.push(&sites, &config, &subscriptions, first).await;
+            notifier.publish(&sites, &config).await;
+            notifier.watch(&sites, &watches).await;
+            first = false;
+        }
+    });
+    Ok(())
+}
+
+struct Notifier {
+    deliverer: Deliverer,
+    announced: Vec<Option<(Date, Option<Day>)>>,
+    mailer: Option<Mailer>,
+    mailed_on: Option<Date>,
+    known_weeks: Option<Vec<Date>>,
+    // Watch ids with the days they were notified about.
+    watched: HashSet<(String, Date)>,
+    known_watches: HashSet<String>,
+    pusher: Option<Pusher>,
+    pushed_on: Option<Date>,
+    mqtt: Option<Mqtt>,
+    published: Option<(Date, Option<Day>)>,
+}
+
+impl Notifier {
+    async fn announce(&mut self, sites: &Sites, config: &Config, first: bool) {
+        let now = now_local();
+        if now.time() < config.announce_at {
+            return;
+        }
+        for (webhook, last) in config.webhooks.iter().zip(&mut self.announced) {
+            let Some(date) = announced_date(now.date(), webhook.day) else {
+                continue;
+            };
+            if !holidays::is_open(date) {
+                continue;
+            }
+            let Some(site) = sites.get(webhook.site.as_deref()) else {
+                eprintln!("webhook: unknown site {:?}", webhook.site);
+                continue;
+            };
+            let day = site
+                .catalogue
+                .read()
+                .await
+                .day(date)
+                .ok()
+                .filter(|day| is_watched(day, &webhook.watch));
+            let current = Some((date, day));
+            if *last == current {
+                continue;
+            }
+            if let (false, Some((_, Some(day)))) = (first, &current) {
+                self.deliverer.spawn(request(
+                    self.deliverer.client(),
+                    webhook.kind,
+                    &webhook.url,
+                    webhook.token.as_deref(),
+                    day,
+                ));
+            }
+            *last = current;
+        }
+    }
+
+    // The daily email follows `announce_at` like webhooks, the weekly one is
+    // sent as soon as a week that wasn't known shows up.
+    async fn mail(&mut self, sites: &Sites, config: &Config, first: bool) {
+        let (Some(mailer), Some(email)) = (&self.mailer, &config.email) else {
+            return;
+        };
+        let Some(site) = sites.get(email.site.as_deref()) else {
+            eprintln!("email: unknown site {:?}", email.site);
+            return;
+        };
+        let now = now_local();
+        // Not holding the lock while talking to the SMTP server.
+        let (today, mondays, weeks) = {
+            let catalogue = site.catalogue.read().await;
+            let mondays = catalogue.weeks().mondays().to_vec();
+            let known = self.known_weeks.as_deref().unwrap_or(&mondays);
+            let weeks = mondays
+                .iter()
+                .filter(|monday| !known.contains(monday))
+                .filter_map(|monday| {
+                    let (year, week) = week_of(*monday);
+                    let week = catalogue.week(year, week).ok()?;
+                    Some((*monday, week))
+                })
+                .collect_vec();
+            (catalogue.day(now.date()).ok(), mondays, weeks)
+        };
+
+        if email.daily && now.time() >= config.announce_at && self.mailed_on != Some(now.date()) {
+            if let (false, Some(day)) = (first, today) {
+                let subject = format!("Menu du {}", day.long_date().to_lowercase());
+                if let Err(err) = mailer.send(&subject, &day).await {
+                    eprintln!("email: {err}");
+                    return;
+                }
+            }
+            self.mailed_on = Some(now.date());
+        }
+
+        if email.weekly {
+            for (monday, week) in weeks {
+                let subject = format!("Menus de la semaine du {}", format_date(monday));
+                if let Err(err) = mailer.send(&subject, &week).await {
+                    eprintln!("email: {err}");
+                    return;
+                }
+            }
+            self.known_weeks = Some(mondays);
+        }
+    }
+
+    async fn push(
+        &mut self,
+        sites: &Sites,
+        config: &Config,
+        subscriptions: &RwLock<PushSubscriptions>,
+        first: bool,
+    ) {
+        let Some(pusher) = &self.pusher else {
+            return;
+        };
+        let now = now_local();
+        if now.time() < config.announce_at || self.pushed_on == Some(now.date()) {
+            return;
+        }
+        self.pushed_on = Some(now.date());
+        if first || !holidays::is_open(now.date()) {
+            return;
+        }
+
+        let list = subscriptions.read().await.list();
+        for subscription in list {
+            let Some(site) = sites.get(subscription.site.as_deref()) else {
+                continue;
+            };
+            let Ok(day) = site.catalogue.read().await.day(now.date()) else {
+                continue;
+            };
+            match pusher.send(&subscription, &day).await {
+                Ok(()) => (),
+                Err(PushError::Expired) => {
+                    let _ = subscriptions.write().await.remove(&subscription.endpoint);
+                }
+                Err(PushError::Other(err)) => eprintln!("web push: {err}"),
+            }
+        }
+    }
+
+    // Unlike announcements, the menu is also published at startup, it
+    // replaces the retained one.
+    async fn publish(&mut self, sites: &Sites, config: &Config) {
+        let (Some(mqtt), Some(mqtt_config)) = (&self.mqtt, &config.mqtt) else {
+            return;
+        };
+        let Some(site) = sites.get(mqtt_config.site.as_deref()) else {
+            eprintln!("mqtt: unknown site {:?}", mqtt_config.site);
+            return;
+        };
+        let now = now_local();
+        let day = site.catalogue.read().await.day(now.date()).ok();
+        let current = Some((now.date(), day));
+        if self.published == current
+            || (self.published.is_some() && now.time() < config.announce_at)
+        {
+            return;
+        }
+        if let Some((_, day)) = &current {
+            if let Err(err) = mqtt.publish(day.as_ref()).await {
+                eprintln!("mqtt: {err}");
+                return;
+            }
+        }
+        self.published = current;
+    }
+
+    // Days already in the catalogue when a watch is first seen (created or
+    // loaded at startup) aren't notified, only the ones uploaded later.
+    async fn watch(&mut self, sites: &Sites, watches: &RwLock<Watches>) {
+        let watches = watches.read().await.list();
+        let today = now_local().date();
+        for watch in watches.watches() {
+            let Some(site) = sites.get(watch.site.as_deref()) else {
+                continue;
+            };
+            let days = site
+                .catalogue
+                .read()
+                .await
+                .days_from(today)
+                .filter(|day| {
+                    day.dishes_ref()
+                        .iter()
+                        .any(|dish| watch.matches(dish.name()))
+                })
+                .cloned()
+                .collect_vec();
+            let notify = self.known_watches.contains(&watch.id);
+            for day in days {
+                if !self.watched.insert((watch.id.clone(), day.date())) || !notify {
+                    continue;
+                }
+                let client = self.deliverer.client();
+                match &watch.target {
+                    WatchTarget::Webhook { url, kind } => {
+                        self.deliverer
+                            .spawn(request(client, *kind, url, None, &day));
+                    }
+                    WatchTarget::Ntfy { url, token } => self.deliverer.spawn(request(
+                        client,
+                        WebhookKind::Ntfy,
+                        url,
+                        token.as_deref(),
+                        &day,
+                    )),
+                    WatchTarget::WebPush { endpoint, keys } => {
+                        if let Some(pusher) = &self.pusher {
+                            let subscription = PushSubscription {
+                                endpoint: endpoint.clone(),
+                                keys: keys.clone(),
+                                site: watch.site.clone(),
+                            };
+                            if let Err(PushError::Other(err)) =
+                                pusher.send(&subscription, &day).await
+                            {
+                                eprintln!("watch web push: {err}");
+                            }
+                        }
+                    }
+                    WatchTarget::Email { address } => {
+                        if let Some(mailer) = &self.mailer {
+                            let subject = format!("{} : {}", watch.dish, day.long_date());
+                            if let Err(err) = mailer.send_to(address, &subject, &day).await {
+                                eprintln!("watch email: {err}");
+                            }
+                        }
+                    }
+                }
+            }
+            self.known_watches.insert(watch.id.clone());
+        }
+    }
+}
+
+fn announced_date(today: Date, day: AnnouncedDay) -> Option<Date> {
+    match day {
+        AnnouncedDay::Today => Some(today),
+        AnnouncedDay::Tomorrow => today.next_day(),
+    }
+}
+
+fn is_watched(day: &Day, watch: &[String]) -> bool {
+    watch.is_empty()
+        || day.dishes_ref().iter().any(|dish| {
+            let dish = dish.name().to_lowercase();
+            watch.iter().any(|term| dish.contains(&term.to_lowercase()))
+        })
+}
+
+fn request(
+    client: &Client,
+    kind: WebhookKind,
+    url: &str,
+    token: Option<&str>,
+    day: &Day,
+) -> RequestBuilder {
+    let request = client.post(url);
+    let request = match kind {
+        WebhookKind::Discord => request.json(&discord_message(day)),
+        WebhookKind::Teams => request.json(&teams_message(day)),
+        WebhookKind::Mattermost => request.json(&mattermost_message(day)),
+        // Headers must be ASCII, month names aren't.
+        WebhookKind::Ntfy => request
+            .query(&[("title", day.long_date())])
+            .body(day.as_plain_text(true)),
+        WebhookKind::Gotify => request.json(&json!({
+            "title": day.long_date(),
+            "message": day.as_plain_text(true),
+        })),
+    };
+    match (token, kind) {
+        (Some(token), WebhookKind::Gotify) => request.header("X-Gotify-Key", token),
+        (Some(token), _) => request.bearer_auth(token),
+        (None, _) => request,
+    }
+}
+
+fn discord_message(day: &Day) -> Value {
+    let fields = sections(day)
+        .map(|(title, dishes)| json!({ "name": title, "value": dishes }))
+        .collect::<Vec<_>>();
+    json!({
+        "embeds": [{
+            "title": day.long_date(),
+            "description": day.notes().join("\n"),
+            "color": COLOR,
+            "fields": fields,
+        }]
+    })
+}
+
+fn teams_message(day: &Day) -> Value {
+    let mut body = vec![json!({
+        "type": "TextBlock",
+        "text": day.long_date(),
+        "size": "Large",
+        "weight": "Bolder",
+    })];
+    for note in day.notes() {
+        body.push(json!({ "type": "TextBlock", "text": note, "wrap": true, "isSubtle": true }));
+    }
+    for (title, dishes) in sections(day) {
+        body.push(json!({ "type": "TextBlock", "text": title, "weight": "Bolder" }));
+        body.push(json!({ "type": "TextBlock", "text": dishes, "wrap": true, "spacing": "None" }));
+    }
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "body": body,
+            },
+        }]
+    })
+}
+
+fn mattermost_message(day: &Day) -> Value {
+    let fields = sections(day)
+        .map(|(title, dishes)| json!({ "title": title, "value": dishes, "short": false }))
+        .collect::<Vec<_>>();
+    json!({
+        "attachments": [{
+            "fallback": day.as_plain_text(true),
+            "color": format!("#{COLOR:06x}"),
+            "title": day.long_date(),
+            "text": day.notes().join("\n"),
+            "fields": fields,
+        }]
+    })
+}
+
+// Category titles with their dishes, one per line.
+fn sections(day: &Day) -> impl Iterator<Item = (&'static str, String)> + '_ {
+    day.dishes_by_category()
+        .into_iter()
+        .map(|(category, dishes)| {
+            (
+                category.map_or("Au menu", |category| category.as_fr_str()),
+                dishes.join("\n"),
+            )
+        })
+}
@@ -0,0 +1,92 @@
+use std::{path::Path, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+static TABLE: OnceLock<Vec<Entry>> = OnceLock::new();
+
+// Rough values for one portion, from a table like CIQUAL or Open Food Facts
+// reduced to common dishes and ingredients.
+#[derive(Serialize, Copy, Clone, PartialEq, Debug)]
+pub struct Estimate {
+    kcal: f64,
+    protein: f64,
+    carbs: f64,
+    fat: f64,
+}
+
+impl Estimate {
+    pub fn as_fr_str(&self) -> String {
+        format!(
+            "≈ {:.0} kcal, protéines {:.0} g, glucides {:.0} g, lipides {:.0} g",
+            self.kcal, self.protein, self.carbs, self.fat
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    words: Vec<String>,
+    estimate: Estimate,
+}
+
+#[derive(Deserialize)]
+struct Row {
+    dish: String,
+    kcal: f64,
+    protein: f64,
+    carbs: f64,
+    fat: f64,
+}
+
+impl From<Row> for Entry {
+    fn from(row: Row) -> Self {
+        Self {
+            words: split_words(&row.dish),
+            estimate: Estimate {
+                kcal: row.kcal,
+                protein: row.protein,
+                carbs: row.carbs,
+                fat: row.fat,
+            },
+        }
+    }
+}
+
+// Loads the CSV table, with a `dish,kcal,protein,carbs,fat` header. Without
+// it, dishes have no estimate.
+pub fn configure(path: &Path) -> Result<(), String> {
+    let entries = csv::Reader::from_path(path)
+        .map_err(|err| format!("invalid nutrition table: {err}"))?
+        .into_deserialize::<Row>()
+        .map(|row| row.map(Entry::from))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("invalid nutrition table: {err}"))?;
+    TABLE
+        .set(entries)
+        .map_err(|_| "nutrition table already configured".to_owned())
+}
+
+// The most specific entry whose words all follow each other in the dish
+// name, so "Poulet rôti" wins over "Poulet" for "Poulet rôti et frites".
+pub fn estimate(dish: &str) -> Option<Estimate> {
+    let words = split_words(dish);
+    TABLE
+        .get()?
+        .iter()
+        .filter(|entry| {
+            !entry.words.is_empty()
+                && words
+                    .windows(entry.words.len())
+                    .any(|window| window == entry.words.as_slice())
+        })
+        .max_by_key(|entry| entry.words.len())
+        .map(|entry| entry.estimate)
+}
+
+fn split_words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
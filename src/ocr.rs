@@ -0,0 +1,94 @@
+use leptess::{capi::TessPageIteratorLevel_RIL_TEXTLINE, LepTess};
+use lopdf::{Document, Object, Stream};
+
+use crate::{
+    error::{ParseError, ParseStage},
+    week::{DocumentDimensions, TextRun},
+};
+
+const LANGUAGE: &str = "fra";
+
+// Scanned menus embed the page as a single JPEG, which Leptonica can decode
+// directly. Other image encodings are not supported.
+pub fn extract_text_runs(
+    document: &Document,
+    dimensions: DocumentDimensions,
+) -> Result<Vec<TextRun>, ParseError> {
+    let error = || ParseError {
+        page: Some(dimensions),
+        ..ParseError::new(ParseStage::Ocr)
+    };
+
+    let image = page_image(document).ok_or_else(error)?;
+    let (image_width, image_height) = image_dimensions(image).ok_or_else(error)?;
+    let mut tesseract = LepTess::new(None, LANGUAGE).map_err(|_| error())?;
+    tesseract
+        .set_image_from_mem(&image.content)
+        .map_err(|_| error())?;
+    let boxes = tesseract
+        .get_component_boxes(TessPageIteratorLevel_RIL_TEXTLINE, true)
+        .ok_or_else(error)?;
+
+    let mut runs = Vec::new();
+    for line in &boxes {
+        tesseract.set_rectangle(&line);
+        let Ok(text) = tesseract.get_utf8_text() else {
+            continue;
+        };
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        // Text runs are positioned on their baseline, in page units.
+        let geometry = line.get_geometry();
+        runs.push(TextRun {
+            top: (geometry.y + geometry.h) as f64 * dimensions.height as f64 / image_height as f64,
+            left: geometry.x as f64 * dimensions.width as f64 / image_width as f64,
+            text: text.to_owned(),
+        });
+    }
+    Ok(runs)
+}
+
+fn page_image(document: &Document) -> Option<&Stream> {
+    let page_id = document.page_iter().next()?;
+    let (resources, resource_ids) = document.get_page_resources(page_id);
+    resources
+        .into_iter()
+        .chain(
+            resource_ids
+                .into_iter()
+                .filter_map(|id| document.get_dictionary(id).ok()),
+        )
+        .filter_map(|resources| resolve(document, resources.get(b"XObject").ok()?)?.as_dict().ok())
+        .flat_map(|xobjects| xobjects.iter())
+        .filter_map(|(_, xobject)| resolve(document, xobject)?.as_stream().ok())
+        .filter(|stream| {
+            stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Image".as_slice())
+                && stream.dict.get(b"Filter").and_then(Object::as_name).ok()
+                    == Some(b"DCTDecode".as_slice())
+        })
+        .max_by_key(|stream| {
+            image_dimensions(stream).map(|(width, height)| width as u64 * height as u64)
+        })
+}
+
+fn image_dimensions(image: &Stream) -> Option<(u32, u32)> {
+    let dimension = |key: &[u8]| {
+        image
+            .dict
+            .get(key)
+            .and_then(Object::as_i64)
+            .ok()
+            .filter(|&n| n > 0)
+            .map(|n| n as u32)
+    };
+    Some((dimension(b"Width")?, dimension(b"Height")?))
+}
+
+fn resolve<'a>(document: &'a Document, object: &'a Object) -> Option<&'a Object> {
+    match object {
+        Object::Reference(id) => document.get_object(*id).ok(),
+        object => Some(object),
+    }
+}
@@ -0,0 +1,671 @@
+// Documentation-only mirrors of the JSON payloads. Most types are serialized
+// by hand, so their shapes are described here rather than derived.
+
+#![allow(dead_code)]
+
+use utoipa::{
+    openapi::security::{Http, HttpAuthScheme, SecurityScheme},
+    Modify, OpenApi, ToSchema,
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "OVR",
+        description = "Canteen menus. Responses are negotiated with the Accept header (JSON, text or HTML, and iCalendar for `/`, weeks and days) or the `format` query parameter. Every per-site route is also available under `/sites/{site}`, and every route under `/api/v1`. JSON responses carry `success` and `api_version`."
+    ),
+    paths(
+        crate::index_handler,
+        crate::upload_handler,
+        crate::today_handler,
+        crate::next_handler,
+        crate::find_handler,
+        crate::dishes_handler,
+        crate::week_handler,
+        crate::source_handler,
+        crate::month_handler,
+        crate::day_handler,
+        crate::day_ics_handler,
+        crate::history_handler,
+        crate::merged_dishes_handler,
+        crate::allergens_handler,
+        crate::rate_handler,
+        crate::dish_rating_handler,
+        crate::leaderboard_handler,
+        crate::attendance_handler,
+        crate::rsvp_handler,
+        crate::cancel_rsvp_handler,
+        crate::job_handler,
+        crate::ics_handler,
+        crate::export_handler,
+        crate::snapshot_handler,
+        crate::import_handler,
+        crate::undo_handler,
+        crate::delete_day_handler,
+        crate::delete_week_handler,
+        crate::login_page_handler,
+        crate::login_handler,
+        crate::logout_handler,
+        crate::reparse_handler,
+        crate::sites_handler,
+        crate::version_handler,
+        crate::audit_handler,
+        crate::ingestion_handler,
+        crate::deliveries_handler,
+        crate::watch_handler,
+        crate::watches_handler,
+        crate::unwatch_handler,
+        crate::create_profile_handler,
+        crate::profile_handler,
+        crate::update_profile_handler,
+        crate::delete_profile_handler,
+        crate::personal_today_handler,
+        crate::personal_next_handler,
+        crate::push_key_handler,
+        crate::subscribe_handler,
+        crate::unsubscribe_handler,
+        crate::home_assistant_handler,
+        crate::home_assistant_discovery_handler,
+        crate::alexa_handler,
+        crate::dialogflow_handler,
+    ),
+    components(schemas(
+        Day,
+        DayResponse,
+        AllergensRequest,
+        LoginRequest,
+        RatedDayResponse,
+        RatingRequest,
+        DishRatingsResponse,
+        RankedDish,
+        LeaderboardResponse,
+        MergedDish,
+        MergedDishesResponse,
+        ServedDish,
+        DishesResponse,
+        RsvpRequest,
+        RsvpResponse,
+        UpcomingDayResponse,
+        CatalogueResponse,
+        WeekResponse,
+        MonthResponse,
+        CatalogueExport,
+        CatalogueSnapshot,
+        DayDiff,
+        UpdateResponse,
+        FileReport,
+        UploadResponse,
+        JobFile,
+        JobResponse,
+        Revision,
+        DayHistoryResponse,
+        SiteToday,
+        SitesResponse,
+        VersionResponse,
+        AuditEntry,
+        AuditResponse,
+        SourceHealth,
+        SourcesResponse,
+        FailedDelivery,
+        DeliveriesResponse,
+        WatchTarget,
+        WatchRequest,
+        Watch,
+        WatchResponse,
+        WatchListResponse,
+        Preferences,
+        ProfileResponse,
+        PersonalDayResponse,
+        PushKeyResponse,
+        PushKeys,
+        PushSubscription,
+        UnsubscribeRequest,
+        SuccessResponse,
+        HomeAssistantSensor,
+        AlexaRequest,
+        AlexaResponse,
+        DialogflowRequest,
+        DialogflowResponse,
+        ErrorResponse,
+    )),
+    modifiers(&Tokens)
+)]
+pub struct ApiDoc;
+
+struct Tokens;
+
+impl Modify for Tokens {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "admin_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+            // Also accepted as the `token` query parameter.
+            components.add_security_scheme(
+                "profile_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+    }
+}
+
+#[derive(ToSchema)]
+pub struct Day {
+    #[schema(example = "2023-06-12")]
+    date: String,
+    // In the language of the dishes, `lang=en` giving "Monday".
+    #[schema(example = "lundi")]
+    weekday: String,
+    // Until the end of today's meal, the configured cutoff.
+    is_today: bool,
+    // Calendar days, negative for past days.
+    days_from_now: i64,
+    dishes: Vec<String>,
+    // The dishes again by section of the menu, when known, under `/api/v1`.
+    starters: Option<Vec<String>>,
+    mains: Option<Vec<String>>,
+    sides: Option<Vec<String>>,
+    cheeses: Option<Vec<String>>,
+    desserts: Option<Vec<String>>,
+    notes: Option<Vec<String>>,
+    // Allergens by dish name, only for dishes that have some.
+    #[schema(value_type = Option<Object>, example = json!({"Gratin dauphinois": ["lactose"]}))]
+    allergens: Option<serde_json::Value>,
+    // Estimates for one portion, by dish name, when a nutrition table is set.
+    #[schema(
+        value_type = Option<Object>,
+        example = json!({"Lasagnes": {"kcal": 520, "protein": 28, "carbs": 45, "fat": 24}})
+    )]
+    nutrition: Option<serde_json::Value>,
+    // Names as read from the upload, when `keep_raw` is set.
+    #[schema(value_type = Option<Object>, example = json!({"Pommes vapeur": "Pom- mes  vapeur"}))]
+    raw: Option<serde_json::Value>,
+    // Near-duplicates merged into a dish while parsing, by dish name.
+    #[schema(value_type = Option<Object>, example = json!({"Salade verte": ["Salade vertes"]}))]
+    merged: Option<serde_json::Value>,
+}
+
+#[derive(ToSchema)]
+pub struct DayResponse {
+    success: bool,
+    date: String,
+    weekday: String,
+    is_today: bool,
+    days_from_now: i64,
+    dishes: Vec<String>,
+    notes: Option<Vec<String>>,
+    #[schema(value_type = Option<Object>)]
+    allergens: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    nutrition: Option<serde_json::Value>,
+    #[schema(value_type = Option<Object>)]
+    raw: Option<serde_json::Value>,
+}
+
+#[derive(ToSchema)]
+pub struct UpcomingDayResponse {
+    success: bool,
+    date: String,
+    weekday: String,
+    is_today: bool,
+    days_from_now: i64,
+    dishes: Vec<String>,
+    notes: Option<Vec<String>>,
+    #[schema(value_type = Option<Object>)]
+    allergens: Option<serde_json::Value>,
+    days_until: i64,
+}
+
+#[derive(ToSchema)]
+pub struct RatedDayResponse {
+    success: bool,
+    date: String,
+    dishes: Vec<String>,
+    notes: Option<Vec<String>>,
+    #[schema(value_type = Option<Object>)]
+    allergens: Option<serde_json::Value>,
+    // Scores by dish name, only for rated dishes.
+    #[schema(
+        value_type = Option<Object>,
+        example = json!({"Lasagnes": {"average": 4.2, "count": 12}})
+    )]
+    ratings: Option<serde_json::Value>,
+}
+
+#[derive(ToSchema)]
+pub struct RatingRequest {
+    #[schema(minimum = 1, maximum = 5)]
+    stars: u8,
+    #[schema(max_length = 500)]
+    comment: Option<String>,
+}
+
+#[derive(ToSchema)]
+pub struct DishRatingsResponse {
+    success: bool,
+    dish: String,
+    // Both missing until the dish is rated.
+    average: Option<f64>,
+    count: Option<usize>,
+    comments: Vec<String>,
+}
+
+#[derive(ToSchema)]
+pub struct RankedDish {
+    dish: String,
+    average: f64,
+    count: usize,
+}
+
+#[derive(ToSchema)]
+pub struct LeaderboardResponse {
+    success: bool,
+    dishes: Vec<RankedDish>,
+}
+
+#[derive(ToSchema)]
+pub struct MergedDish {
+    #[schema(example = "2023-06-12")]
+    date: String,
+    #[schema(example = "Salade verte")]
+    dish: String,
+    merged: Vec<String>,
+}
+
+#[derive(ToSchema)]
+pub struct MergedDishesResponse {
+    success: bool,
+    dishes: Vec<MergedDish>,
+}
+
+#[derive(ToSchema)]
+pub struct ServedDish {
+    #[schema(example = "Hachis parmentier")]
+    name: String,
+    // Days it was on the menu.
+    served: usize,
+    #[schema(example = "2023-06-12")]
+    last_served: String,
+}
+
+#[derive(ToSchema)]
+pub struct DishesResponse {
+    success: bool,
+    dishes: Vec<ServedDish>,
+}
+
+#[derive(ToSchema)]
+pub struct RsvpRequest {
+    // Shown to the others, the answer is anonymous without it.
+    #[schema(example = "Camille")]
+    name: Option<String>,
+}
+
+#[derive(ToSchema)]
+pub struct RsvpResponse {
+    success: bool,
+    date: String,
+    count: usize,
+    names: Vec<String>,
+}
+
+// The `admin_token`, or a key with the `admin` scope.
+#[derive(ToSchema)]
+pub struct LoginRequest {
+    token: String,
+}
+
+// `null` goes back to the allergens detected from the dish name.
+#[derive(ToSchema)]
+pub struct AllergensRequest {
+    #[schema(example = json!(["gluten", "eggs"]))]
+    allergens: Option<Vec<String>>,
+}
+
+#[derive(ToSchema)]
+pub struct CatalogueResponse {
+    success: bool,
+    days: Vec<Day>,
+}
+
+#[derive(ToSchema)]
+pub struct WeekResponse {
+    success: bool,
+    year: i32,
+    week: u8,
+    // Monday and Friday of the week.
+    #[schema(example = "2023-06-12")]
+    from: String,
+    #[schema(example = "2023-06-16")]
+    to: String,
+    // The closest weeks with menus, omitted at the ends of the catalogue.
+    #[schema(example = "2023-23")]
+    previous: Option<String>,
+    #[schema(example = "2023-25")]
+    next: Option<String>,
+    days: Vec<Day>,
+}
+
+#[derive(ToSchema)]
+pub struct MonthResponse {
+    success: bool,
+    year: i32,
+    #[schema(minimum = 1, maximum = 12)]
+    month: u8,
+    days: Vec<Day>,
+}
+
+#[derive(ToSchema)]
+pub struct CatalogueExport {
+    version: u32,
+    generator: String,
+    exported_at: String,
+    days: Vec<Day>,
+}
+
+#[derive(ToSchema)]
+pub struct CatalogueSnapshot {
+    // Hex SHA-256 of the days, as in `X-Catalogue-Version`.
+    version: String,
+    days: Vec<Day>,
+}
+
+#[derive(ToSchema)]
+pub struct DayDiff {
+    date: String,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+#[derive(ToSchema)]
+pub struct UpdateResponse {
+    success: bool,
+    inserted: Vec<String>,
+    replaced: Vec<String>,
+    removed: Vec<String>,
+    skipped: Vec<String>,
+    conflicts: Vec<String>,
+    changes: Vec<DayDiff>,
+    // The document was already uploaded and its days are unchanged.
+    unchanged: bool,
+}
+
+#[derive(ToSchema)]
+pub struct FileReport {
+    name: Option<String>,
+    success: bool,
+    inserted: Option<Vec<String>>,
+    replaced: Option<Vec<String>>,
+    unchanged: Option<bool>,
+    error: Option<String>,
+    #[schema(value_type = Option<Object>)]
+    diagnostics: Option<serde_json::Value>,
+}
+
+#[derive(ToSchema)]
+pub struct UploadResponse {
+    success: bool,
+    inserted: Vec<String>,
+    replaced: Vec<String>,
+    removed: Vec<String>,
+    skipped: Vec<String>,
+    conflicts: Vec<String>,
+    changes: Vec<DayDiff>,
+    unchanged: bool,
+    files: Vec<FileReport>,
+}
+
+#[derive(ToSchema)]
+pub struct JobFile {
+    name: Option<String>,
+    #[schema(example = "processing")]
+    status: String,
+    inserted: Option<Vec<String>>,
+    replaced: Option<Vec<String>>,
+    error: Option<String>,
+}
+
+#[derive(ToSchema)]
+pub struct JobResponse {
+    success: bool,
+    id: String,
+    finished: bool,
+    files: Vec<JobFile>,
+}
+
+#[derive(ToSchema)]
+pub struct Revision {
+    replaced_at: String,
+    date: String,
+    dishes: Vec<String>,
+    notes: Option<Vec<String>>,
+}
+
+#[derive(ToSchema)]
+pub struct DayHistoryResponse {
+    success: bool,
+    date: String,
+    current: Option<Day>,
+    revisions: Vec<Revision>,
+}
+
+#[derive(ToSchema)]
+pub struct SiteToday {
+    name: String,
+    today: Option<Day>,
+}
+
+#[derive(ToSchema)]
+pub struct SitesResponse {
+    success: bool,
+    sites: Vec<SiteToday>,
+}
+
+#[derive(ToSchema)]
+pub struct VersionResponse {
+    success: bool,
+    #[schema(example = "0.1.21")]
+    version: String,
+    #[schema(example = "3f2c1ab")]
+    commit: Option<String>,
+    build_date: Option<String>,
+    #[schema(example = json!(["ocr", "server"]))]
+    features: Vec<String>,
+    sites: usize,
+}
+
+#[derive(ToSchema)]
+pub struct AuditEntry {
+    timestamp: String,
+    source: String,
+    #[schema(example = "upload")]
+    action: String,
+    site: Option<String>,
+    file: Option<String>,
+    dates: Vec<String>,
+    error: Option<String>,
+}
+
+#[derive(ToSchema)]
+pub struct AuditResponse {
+    success: bool,
+    entries: Vec<AuditEntry>,
+}
+
+#[derive(ToSchema)]
+pub struct SourceHealth {
+    #[schema(example = "imap menus@example.com")]
+    name: String,
+    site: Option<String>,
+    #[schema(example = "healthy")]
+    status: String,
+    last_attempt: Option<String>,
+    last_success: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(ToSchema)]
+pub struct SourcesResponse {
+    success: bool,
+    sources: Vec<SourceHealth>,
+}
+
+#[derive(ToSchema)]
+pub struct FailedDelivery {
+    timestamp: String,
+    url: String,
+    error: String,
+}
+
+#[derive(ToSchema)]
+pub struct DeliveriesResponse {
+    success: bool,
+    deliveries: Vec<FailedDelivery>,
+}
+
+#[derive(ToSchema)]
+pub struct WatchTarget {
+    #[schema(example = "ntfy")]
+    r#type: String,
+    url: Option<String>,
+    #[schema(example = "discord")]
+    kind: Option<String>,
+    token: Option<String>,
+    address: Option<String>,
+    endpoint: Option<String>,
+    keys: Option<PushKeys>,
+}
+
+#[derive(ToSchema)]
+pub struct WatchRequest {
+    #[schema(example = "frites")]
+    dish: String,
+    site: Option<String>,
+    target: WatchTarget,
+}
+
+#[derive(ToSchema)]
+pub struct Watch {
+    id: String,
+    dish: String,
+    site: Option<String>,
+    target: WatchTarget,
+}
+
+#[derive(ToSchema)]
+pub struct WatchResponse {
+    success: bool,
+    id: String,
+    dish: String,
+    site: Option<String>,
+    target: WatchTarget,
+}
+
+#[derive(ToSchema)]
+pub struct WatchListResponse {
+    success: bool,
+    watches: Vec<Watch>,
+}
+
+#[derive(ToSchema)]
+pub struct Preferences {
+    // Matched anywhere in dish names.
+    #[schema(example = json!(["betterave", "chou"]))]
+    dislikes: Option<Vec<String>>,
+    #[schema(example = json!(["lactose"]))]
+    allergens: Option<Vec<String>>,
+}
+
+#[derive(ToSchema)]
+pub struct ProfileResponse {
+    success: bool,
+    token: String,
+    dislikes: Vec<String>,
+    allergens: Vec<String>,
+}
+
+#[derive(ToSchema)]
+pub struct PersonalDayResponse {
+    success: bool,
+    date: String,
+    dishes: Vec<String>,
+    notes: Option<Vec<String>>,
+    #[schema(value_type = Option<Object>)]
+    allergens: Option<serde_json::Value>,
+    // Dishes the profile avoids, still listed in `dishes` unless stripped.
+    avoided: Vec<String>,
+}
+
+#[derive(ToSchema)]
+pub struct PushKeyResponse {
+    success: bool,
+    public_key: String,
+}
+
+#[derive(ToSchema)]
+pub struct PushKeys {
+    p256dh: String,
+    auth: String,
+}
+
+#[derive(ToSchema)]
+pub struct PushSubscription {
+    endpoint: String,
+    keys: PushKeys,
+    site: Option<String>,
+}
+
+#[derive(ToSchema)]
+pub struct UnsubscribeRequest {
+    endpoint: String,
+}
+
+#[derive(ToSchema)]
+pub struct SuccessResponse {
+    success: bool,
+}
+
+#[derive(ToSchema)]
+pub struct HomeAssistantSensor {
+    #[schema(example = "Poulet basquaise")]
+    state: String,
+    date: Option<String>,
+    dishes: Vec<String>,
+    notes: Vec<String>,
+}
+
+#[derive(ToSchema)]
+pub struct AlexaRequest {
+    version: String,
+    #[schema(value_type = Object)]
+    request: serde_json::Value,
+    #[schema(value_type = Option<Object>)]
+    context: Option<serde_json::Value>,
+}
+
+#[derive(ToSchema)]
+pub struct AlexaResponse {
+    version: String,
+    #[schema(value_type = Object)]
+    response: serde_json::Value,
+}
+
+#[derive(ToSchema)]
+#[allow(non_snake_case)]
+pub struct DialogflowRequest {
+    #[schema(value_type = Object)]
+    queryResult: serde_json::Value,
+}
+
+#[derive(ToSchema)]
+#[allow(non_snake_case)]
+pub struct DialogflowResponse {
+    fulfillmentText: String,
+}
+
+#[derive(ToSchema)]
+pub struct ErrorResponse {
+    success: bool,
+    error: String,
+}
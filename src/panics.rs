@@ -0,0 +1,50 @@
+use std::any::Any;
+
+use axum::{
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::{error::Error, response::ErrorFormat};
+
+const REQUEST_ID: &str = "X-Request-Id";
+
+// Marks the answers of handlers that panicked, to be rendered once the
+// request ID and format are known.
+#[derive(Clone, Debug)]
+struct Panicked;
+
+// Answer of `CatchPanicLayer`, the panic itself being printed by the hook.
+pub fn response(_panic: Box<dyn Any + Send>) -> Response {
+    let mut response = Error::Internal.into_response();
+    response.extensions_mut().insert(Panicked);
+    response
+}
+
+// Tags every request and its answer with an `X-Request-Id`, kept from the
+// reverse proxy when it sets one, so failures can be found in the logs.
+pub async fn request_id<B>(
+    format: ErrorFormat,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID)
+        .cloned()
+        .unwrap_or_else(|| {
+            HeaderValue::from_str(&Uuid::new_v4().to_string()).expect("invalid request id")
+        });
+    request.headers_mut().insert(REQUEST_ID, id.clone());
+
+    let mut response = next.run(request).await;
+    if response.extensions().get::<Panicked>().is_some() {
+        let id = id.to_str().unwrap_or_default().to_owned();
+        eprintln!("request {id} panicked");
+        response = format.respond(Error::Panic(id));
+    }
+    response.headers_mut().insert(REQUEST_ID, id);
+    response
+}
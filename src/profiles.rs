@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Query},
+    http::{header, request::Parts},
+    response::Response,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    allergens::Allergen,
+    day::{Day, Dish},
+    error::Error,
+    response::{ErrorFormat, TextRepresentable},
+    utils::escape,
+};
+
+// Dishes people would rather not eat, saved to `profiles_file` on every
+// change. The token is the only way to read a profile back.
+#[derive(Debug)]
+pub struct Profiles {
+    profiles: Vec<Profile>,
+    path: Option<PathBuf>,
+}
+
+impl Profiles {
+    pub fn open(path: Option<&Path>) -> Result<Self, String> {
+        let profiles = match path.map(fs::read_to_string) {
+            Some(Ok(content)) => serde_json::from_str(&content)
+                .map_err(|err| format!("invalid profiles file: {err}"))?,
+            Some(Err(err)) if err.kind() != ErrorKind::NotFound => return Err(err.to_string()),
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            profiles,
+            path: path.map(ToOwned::to_owned),
+        })
+    }
+
+    pub fn create(&mut self, preferences: Preferences) -> Result<Profile, Error> {
+        let profile = Profile {
+            token: Uuid::new_v4().to_string(),
+            preferences: preferences.cleaned(),
+        };
+        self.profiles.push(profile.clone());
+        self.save()?;
+        Ok(profile)
+    }
+
+    pub fn get(&self, token: &str) -> Option<Profile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.token == token)
+            .cloned()
+    }
+
+    pub fn update(&mut self, token: &str, preferences: Preferences) -> Result<Profile, Error> {
+        let profile = self
+            .profiles
+            .iter_mut()
+            .find(|profile| profile.token == token)
+            .ok_or(Error::Unauthorized)?;
+        profile.preferences = preferences.cleaned();
+        let profile = profile.clone();
+        self.save()?;
+        Ok(profile)
+    }
+
+    pub fn remove(&mut self, token: &str) -> Result<(), Error> {
+        let count = self.profiles.len();
+        self.profiles.retain(|profile| profile.token != token);
+        if self.profiles.len() == count {
+            return Err(Error::Unauthorized);
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string(&self.profiles).expect("profiles serialization failed");
+        fs::write(path, content).map_err(|err| {
+            eprintln!("failed to save profiles: {err}");
+            Error::Internal
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Profile {
+    pub token: String,
+    #[serde(flatten)]
+    pub preferences: Preferences,
+}
+
+impl TextRepresentable for Profile {
+    fn as_plain_text(&self, _human: bool) -> String {
+        self.preferences
+            .dislikes
+            .iter()
+            .map(String::as_str)
+            .chain(self.preferences.allergens.iter().map(Allergen::as_fr_str))
+            .join("\n")
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Preferences {
+    // Matched anywhere in dish names, ignoring case.
+    #[serde(default)]
+    pub dislikes: Vec<String>,
+    #[serde(default)]
+    pub allergens: Vec<Allergen>,
+}
+
+impl Preferences {
+    fn cleaned(self) -> Self {
+        Self {
+            dislikes: self
+                .dislikes
+                .iter()
+                .map(|dislike| dislike.trim().to_lowercase())
+                .filter(|dislike| !dislike.is_empty())
+                .unique()
+                .collect(),
+            allergens: self.allergens.into_iter().sorted().dedup().collect(),
+        }
+    }
+
+    pub fn avoids(&self, dish: &Dish) -> bool {
+        let name = dish.name().to_lowercase();
+        self.dislikes.iter().any(|dislike| name.contains(dislike))
+            || dish.contains_any(&self.allergens)
+    }
+}
+
+// The profile of the token sent as a bearer token, or as the `token` query
+// parameter for links opened in a browser.
+pub struct Me(pub Profile);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Me
+where
+    S: Send + Sync,
+    Arc<RwLock<Profiles>>: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let query = Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+            .map(|Query(query)| query)
+            .unwrap_or_default();
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .or(query.get("token").map(String::as_str));
+        let profile = match token {
+            Some(token) => Arc::<RwLock<Profiles>>::from_ref(state)
+                .read()
+                .await
+                .get(token),
+            None => None,
+        };
+        profile
+            .map(Me)
+            .ok_or_else(|| ErrorFormat::loose(&parts.headers).respond(Error::Unauthorized))
+    }
+}
+
+// A day as seen by a profile: avoided dishes are either listed in `avoided` or
+// removed altogether.
+#[derive(Serialize, Debug)]
+pub struct PersonalDay {
+    #[serde(flatten)]
+    day: Day,
+    avoided: Vec<String>,
+    #[serde(skip)]
+    stripped: bool,
+}
+
+impl PersonalDay {
+    pub fn new(mut day: Day, preferences: &Preferences, strip: bool) -> Self {
+        let avoided = day
+            .dishes_ref()
+            .iter()
+            .filter(|dish| preferences.avoids(dish))
+            .map(|dish| dish.name().to_owned())
+            .collect_vec();
+        if strip {
+            day.retain_dishes(|dish| !preferences.avoids(dish));
+        }
+        Self {
+            day,
+            avoided,
+            stripped: strip,
+        }
+    }
+
+    fn warning(&self) -> Option<String> {
+        (!self.stripped && !self.avoided.is_empty())
+            .then(|| format!("À éviter : {}.", self.avoided.join(", ")))
+    }
+}
+
+impl TextRepresentable for PersonalDay {
+    fn as_plain_text(&self, human: bool) -> String {
+        let separator = if human { " " } else { "\n" };
+        self.warning().map_or_else(
+            || self.day.as_plain_text(human),
+            |warning| format!("{}{separator}{warning}", self.day.as_plain_text(human)),
+        )
+    }
+
+    fn as_html(&self) -> String {
+        self.warning().map_or_else(
+            || self.day.as_html(),
+            |warning| {
+                format!(
+                    r#"{}<div class="note">{}</div>"#,
+                    self.day.as_html(),
+                    escape(&warning)
+                )
+            },
+        )
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::{header, request::Parts, Extensions, HeaderMap},
+};
+
+use crate::config::Config;
+
+// The address of the client. Behind one of the `trusted_proxies`, it is the
+// last address of `X-Forwarded-For` that isn't a trusted proxy, as earlier
+// ones can be made up by the client.
+pub fn client_ip(headers: &HeaderMap, extensions: &Extensions, config: &Config) -> Option<IpAddr> {
+    let peer = extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())?;
+    if !config.trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+    let forwarded = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .filter_map(|address| address.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    forwarded
+        .iter()
+        .rev()
+        .find(|address| !config.trusted_proxies.contains(address))
+        .or(forwarded.first())
+        .copied()
+        .or(Some(peer))
+}
+
+// Root URL of the instance, like "https://ovr.example.com", to build
+// absolute URLs. It is the `public_url` from the configuration, or the scheme
+// and host the client used to reach the instance.
+#[derive(Clone, Debug)]
+pub struct Origin(String);
+
+impl Origin {
+    // For links built outside of requests, like CalDAV events.
+    pub fn public(config: &Config) -> Option<Self> {
+        config
+            .public_url
+            .as_deref()
+            .map(|url| Self(url.trim_end_matches('/').to_owned()))
+    }
+
+    pub fn is_secure(&self) -> bool {
+        self.0.starts_with("https://")
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.0)
+    }
+
+    // Where the routes of `site` are served, the default site at the root.
+    pub fn site_url(&self, site: Option<&str>) -> String {
+        match site {
+            Some(site) => self.url(&format!("/sites/{site}")),
+            None => self.0.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Origin
+where
+    S: Send + Sync,
+    Arc<Config>: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let config = Arc::<Config>::from_ref(state);
+        if let Some(origin) = Self::public(&config) {
+            return Ok(origin);
+        }
+        let trusted = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .is_some_and(|ConnectInfo(addr)| config.trusted_proxies.contains(&addr.ip()));
+        let forwarded = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.split(',').next())
+                .map(str::trim)
+                .filter(|value| trusted && !value.is_empty())
+        };
+        let scheme = forwarded("x-forwarded-proto").unwrap_or("http");
+        let host = forwarded("x-forwarded-host")
+            .or_else(|| {
+                parts
+                    .headers
+                    .get(header::HOST)
+                    .and_then(|h| h.to_str().ok())
+            })
+            .unwrap_or("localhost");
+        Ok(Self(format!("{scheme}://{host}")))
+    }
+}
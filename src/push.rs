@@ -0,0 +1,157 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use web_push::{
+    ContentEncoding, SubscriptionInfo, VapidSignatureBuilder, WebPushClient, WebPushError,
+    WebPushMessageBuilder, URL_SAFE_NO_PAD,
+};
+
+use crate::{day::Day, error::Error, response::TextRepresentable};
+
+#[derive(Deserialize, Debug)]
+pub struct WebPushConfig {
+    // Base64url encoded VAPID keys, like the ones from `npx web-push generate-vapid-keys`.
+    pub public_key: String,
+    pub private_key: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PushKey {
+    pub public_key: String,
+}
+
+impl TextRepresentable for PushKey {
+    fn as_plain_text(&self, _human: bool) -> String {
+        self.public_key.clone()
+    }
+}
+
+// Browsers that asked for today's menu every morning, saved to
+// `push_subscriptions_file` on every change.
+#[derive(Debug)]
+pub struct PushSubscriptions {
+    subscriptions: Vec<PushSubscription>,
+    path: Option<PathBuf>,
+}
+
+impl PushSubscriptions {
+    pub fn open(path: Option<&Path>) -> Result<Self, String> {
+        let subscriptions = match path.map(fs::read_to_string) {
+            Some(Ok(content)) => serde_json::from_str(&content)
+                .map_err(|err| format!("invalid push subscriptions file: {err}"))?,
+            Some(Err(err)) if err.kind() != ErrorKind::NotFound => return Err(err.to_string()),
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            subscriptions,
+            path: path.map(ToOwned::to_owned),
+        })
+    }
+
+    // Subscribing again from the same browser only updates its site.
+    pub fn add(&mut self, subscription: PushSubscription) -> Result<(), Error> {
+        self.subscriptions
+            .retain(|existing| existing.endpoint != subscription.endpoint);
+        self.subscriptions.push(subscription);
+        self.save()
+    }
+
+    pub fn remove(&mut self, endpoint: &str) -> Result<(), Error> {
+        let count = self.subscriptions.len();
+        self.subscriptions
+            .retain(|subscription| subscription.endpoint != endpoint);
+        if self.subscriptions.len() == count {
+            return Err(Error::SubscriptionNotFound);
+        }
+        self.save()
+    }
+
+    pub fn list(&self) -> Vec<PushSubscription> {
+        self.subscriptions.clone()
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string(&self.subscriptions)
+            .expect("push subscriptions serialization failed");
+        fs::write(path, content).map_err(|err| {
+            eprintln!("failed to save push subscriptions: {err}");
+            Error::Internal
+        })
+    }
+}
+
+// The `PushSubscription` of the browser Push API, serialized with `toJSON()`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PushSubscription {
+    pub endpoint: String,
+    pub keys: PushKeys,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PushKeys {
+    pub p256dh: String,
+    pub auth: String,
+}
+
+pub struct Pusher {
+    client: WebPushClient,
+    private_key: String,
+}
+
+impl Pusher {
+    pub fn new(config: &WebPushConfig) -> Result<Self, String> {
+        Ok(Self {
+            client: WebPushClient::new().map_err(|err| err.to_string())?,
+            private_key: config.private_key.clone(),
+        })
+    }
+
+    // The service worker shows `title` and `body` as a notification.
+    pub async fn send(&self, subscription: &PushSubscription, day: &Day) -> Result<(), PushError> {
+        let info = SubscriptionInfo::new(
+            &subscription.endpoint,
+            &subscription.keys.p256dh,
+            &subscription.keys.auth,
+        );
+        let signature =
+            VapidSignatureBuilder::from_base64(&self.private_key, URL_SAFE_NO_PAD, &info)?
+                .build()?;
+        let payload = json!({
+            "title": day.long_date(),
+            "body": day.as_plain_text(true),
+        })
+        .to_string();
+
+        let mut builder = WebPushMessageBuilder::new(&info)?;
+        builder.set_payload(ContentEncoding::Aes128Gcm, payload.as_bytes());
+        builder.set_vapid_signature(signature);
+        self.client.send(builder.build()?).await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum PushError {
+    // The browser unsubscribed, the subscription should be dropped.
+    Expired,
+    Other(WebPushError),
+}
+
+impl From<WebPushError> for PushError {
+    fn from(value: WebPushError) -> Self {
+        match value {
+            WebPushError::EndpointNotValid | WebPushError::EndpointNotFound => PushError::Expired,
+            err => PushError::Other(err),
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    iter,
+    path::Path,
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+
+use crate::{
+    day::Day,
+    error::Error,
+    response::TextRepresentable,
+    utils::{escape, format_date, now_local},
+};
+
+const MAX_COMMENT_LENGTH: usize = 500;
+
+// Every rating ever given, kept in `ratings_log` like the audit log. Scores
+// are computed per dish name, so a dish served twice shares its ratings.
+#[derive(Debug)]
+pub struct Ratings {
+    ratings: Vec<Rating>,
+    file: Option<File>,
+}
+
+impl Ratings {
+    pub fn open(path: Option<&Path>) -> Result<Self, String> {
+        let Some(path) = path else {
+            return Ok(Self {
+                ratings: Vec::new(),
+                file: None,
+            });
+        };
+        let ratings = match fs::read_to_string(path) {
+            Ok(content) => content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| format!("invalid ratings log: {err}"))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.to_string()),
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            ratings,
+            file: Some(file),
+        })
+    }
+
+    pub fn add(
+        &mut self,
+        site: Option<&str>,
+        day: &Day,
+        index: usize,
+        request: RatingRequest,
+    ) -> Result<DishRatings, Error> {
+        let dish = day.dishes_ref().get(index).ok_or(Error::DishNotFound)?;
+        let comment = request
+            .comment
+            .map(|comment| comment.trim().to_owned())
+            .filter(|comment| !comment.is_empty());
+        if !(1..=5).contains(&request.stars)
+            || comment
+                .as_ref()
+                .is_some_and(|comment| comment.chars().count() > MAX_COMMENT_LENGTH)
+        {
+            return Err(Error::InvalidRating);
+        }
+
+        let rating = Rating {
+            timestamp: now_local()
+                .format(&Rfc3339)
+                .expect("rating date formatting failed"),
+            site: site.map(ToOwned::to_owned),
+            date: format_date(day.date()),
+            dish: dish.name().to_owned(),
+            stars: request.stars,
+            comment,
+        };
+        if let Some(file) = &mut self.file {
+            let line = serde_json::to_string(&rating).expect("rating serialization failed");
+            if let Err(err) = writeln!(file, "{line}") {
+                eprintln!("failed to write ratings log: {err}");
+            }
+        }
+        self.ratings.push(rating);
+        Ok(self.dish(site, dish.name()))
+    }
+
+    pub fn dish(&self, site: Option<&str>, dish: &str) -> DishRatings {
+        let ratings = self.of_dish(site, dish).collect_vec();
+        DishRatings {
+            dish: dish.to_owned(),
+            score: Score::of(ratings.iter().copied()),
+            comments: ratings
+                .iter()
+                .rev()
+                .filter_map(|rating| rating.comment.clone())
+                .collect(),
+        }
+    }
+
+    // Scores of the rated dishes of `day`, by name.
+    pub fn day(&self, site: Option<&str>, day: &Day) -> BTreeMap<String, Score> {
+        day.dishes_ref()
+            .iter()
+            .filter_map(|dish| {
+                let score = Score::of(self.of_dish(site, dish.name()))?;
+                Some((dish.name().to_owned(), score))
+            })
+            .collect()
+    }
+
+    // Best average first, the most rated first on ties.
+    pub fn leaderboard(&self, site: Option<&str>, min_count: usize) -> Leaderboard {
+        let dishes = self
+            .ratings
+            .iter()
+            .filter(|rating| rating.site.as_deref() == site)
+            .into_group_map_by(|rating| rating.dish.to_lowercase())
+            .into_values()
+            .filter_map(|ratings| {
+                let score = Score::of(ratings.iter().copied())?;
+                Some(RankedDish {
+                    dish: ratings.last()?.dish.clone(),
+                    score,
+                })
+            })
+            .filter(|ranked| ranked.score.count >= min_count)
+            .sorted_by(|a, b| {
+                b.score
+                    .average
+                    .total_cmp(&a.score.average)
+                    .then(b.score.count.cmp(&a.score.count))
+                    .then_with(|| a.dish.cmp(&b.dish))
+            })
+            .collect();
+        Leaderboard { dishes }
+    }
+
+    fn of_dish<'a>(
+        &'a self,
+        site: Option<&'a str>,
+        dish: &str,
+    ) -> impl Iterator<Item = &'a Rating> + 'a {
+        let dish = dish.to_lowercase();
+        self.ratings.iter().filter(move |rating| {
+            rating.site.as_deref() == site && rating.dish.to_lowercase() == dish
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Rating {
+    timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    site: Option<String>,
+    date: String,
+    dish: String,
+    stars: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RatingRequest {
+    stars: u8,
+    comment: Option<String>,
+}
+
+#[derive(Serialize, Copy, Clone, Debug)]
+pub struct Score {
+    average: f64,
+    count: usize,
+}
+
+impl Score {
+    fn of<'a>(ratings: impl Iterator<Item = &'a Rating>) -> Option<Self> {
+        let (sum, count) = ratings.fold((0, 0), |(sum, count), rating| {
+            (sum + rating.stars as usize, count + 1)
+        });
+        (count > 0).then(|| Self {
+            average: (sum as f64 / count as f64 * 10.0).round() / 10.0,
+            count,
+        })
+    }
+}
+
+impl TextRepresentable for Score {
+    fn as_plain_text(&self, _human: bool) -> String {
+        format!("{}/5 ({} avis)", self.average, self.count)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct DishRatings {
+    dish: String,
+    #[serde(flatten)]
+    score: Option<Score>,
+    // Latest first.
+    comments: Vec<String>,
+}
+
+impl TextRepresentable for DishRatings {
+    fn as_plain_text(&self, human: bool) -> String {
+        let score = self.score.map_or_else(
+            || "pas encore noté".to_owned(),
+            |score| score.as_plain_text(human),
+        );
+        iter::once(format!("{} : {score}", self.dish))
+            .chain(self.comments.iter().cloned())
+            .join("\n")
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct RankedDish {
+    dish: String,
+    #[serde(flatten)]
+    score: Score,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Leaderboard {
+    dishes: Vec<RankedDish>,
+}
+
+impl TextRepresentable for Leaderboard {
+    fn as_plain_text(&self, human: bool) -> String {
+        self.dishes
+            .iter()
+            .enumerate()
+            .map(|(rank, ranked)| {
+                format!(
+                    "{}. {} : {}",
+                    rank + 1,
+                    ranked.dish,
+                    ranked.score.as_plain_text(human)
+                )
+            })
+            .join("\n")
+    }
+
+    fn as_html(&self) -> String {
+        format!(
+            r#"<ol class="leaderboard">{}</ol>"#,
+            self.dishes
+                .iter()
+                .map(|ranked| {
+                    format!(
+                        r#"<li class="dish">{} <span class="score">{}</span></li>"#,
+                        escape(&ranked.dish),
+                        ranked.score.as_plain_text(true)
+                    )
+                })
+                .collect::<String>()
+        )
+    }
+}
+
+// A day with the scores of its rated dishes.
+#[derive(Serialize, Debug)]
+pub struct RatedDay {
+    #[serde(flatten)]
+    day: Day,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    ratings: BTreeMap<String, Score>,
+}
+
+impl RatedDay {
+    pub fn new(day: Day, ratings: BTreeMap<String, Score>) -> Self {
+        Self { day, ratings }
+    }
+}
+
+impl TextRepresentable for RatedDay {
+    fn as_plain_text(&self, human: bool) -> String {
+        self.day.as_plain_text(human)
+    }
+
+    fn as_html(&self) -> String {
+        self.day.as_html()
+    }
+}
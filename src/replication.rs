@@ -0,0 +1,99 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::{sync::RwLock, time::interval};
+
+use crate::{
+    audit::{AuditAction, AuditLog, AuditSource},
+    catalogue::CatalogueExport,
+    config::Config,
+    site::Sites,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct ReplicationConfig {
+    // Root URL of the primary instance, like "https://ovr.example.com".
+    pub primary: String,
+    #[serde(default = "ReplicationConfig::default_interval")]
+    pub interval: u64,
+}
+
+impl ReplicationConfig {
+    fn default_interval() -> u64 {
+        300
+    }
+}
+
+// Replicas copy the catalogue of each site from the primary's `/export`,
+// replacing local uploads. Nothing is changed when the primary can't be
+// reached.
+pub fn spawn(sites: Arc<Sites>, config: Arc<Config>, audit: Arc<RwLock<AuditLog>>) {
+    if config.replication.is_none() {
+        return;
+    }
+    tokio::spawn(async move {
+        let replication = config
+            .replication
+            .as_ref()
+            .expect("replication configuration missing");
+        let client = Client::new();
+        let source = AuditSource::new(format!("replication {}", replication.primary));
+        let names = [None]
+            .into_iter()
+            .chain(config.sites.iter().map(|name| Some(name.as_str())))
+            .collect::<Vec<_>>();
+        let mut interval = interval(Duration::from_secs(replication.interval.max(1)));
+        loop {
+            interval.tick().await;
+            for &name in &names {
+                let Some(site) = sites.get(name) else {
+                    continue;
+                };
+                let export = match fetch(&client, replication, site.name.as_deref()).await {
+                    Ok(export) => export,
+                    Err(err) => {
+                        eprintln!("replication: failed to fetch {name:?}: {err}");
+                        continue;
+                    }
+                };
+                if export.version != CatalogueExport::VERSION {
+                    eprintln!("replication: unsupported export version {}", export.version);
+                    continue;
+                }
+                let mut catalogue = site.catalogue.write().await;
+                if catalogue.export().days == export.days {
+                    continue;
+                }
+                let update = catalogue.restore(export.days);
+                drop(catalogue);
+                audit.write().await.record(
+                    &source,
+                    AuditAction::Replicate,
+                    site.name.as_deref(),
+                    None,
+                    Ok(&update),
+                );
+            }
+        }
+    });
+}
+
+async fn fetch(
+    client: &Client,
+    config: &ReplicationConfig,
+    site: Option<&str>,
+) -> Result<CatalogueExport, reqwest::Error> {
+    let root = config.primary.trim_end_matches('/');
+    let url = match site {
+        Some(site) => format!("{root}/api/v1/sites/{site}/export"),
+        None => format!("{root}/api/v1/export"),
+    };
+    client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
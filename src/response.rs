@@ -1,88 +1,217 @@
-use std::sync::Arc;
+use std::{convert::Infallible, sync::Arc};
 
 use axum::{
     async_trait,
+    body::Bytes,
     extract::{FromRef, FromRequestParts, Query},
-    http::{request::Parts, StatusCode},
-    response::{Html, IntoResponse, Response},
-    Json,
+    http::{header, request::Parts, HeaderMap, HeaderName, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
 };
 use either::Either;
 use http_negotiator::{AsNegotiationStr, ContentTypeNegotiation, Negotiation, Negotiator};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use crate::error::Error;
+pub use crate::text::{TextRepresentable, TextStyle};
+use crate::{branding, day, error::Error, text::Language, utils::escape};
+
+// Bumped, along with the `/api/vN` prefix, when payload shapes change.
+pub const API_VERSION: u32 = 1;
 
 pub struct ApiResponse<T> {
     pub response_type: ResponseType,
     pub data: Result<T, Error>,
 }
 
-impl<T: Serialize + TextRepresentable> IntoResponse for ApiResponse<T> {
-    fn into_response(self) -> Response {
-        (
-            match &self.data {
-                Ok(_) => StatusCode::OK,
-                Err(err) => err.status_code(),
-            },
-            match self.response_type {
-                ResponseType::Json(human) => {
-                    #[derive(Serialize)]
-                    struct JsonResponse<T> {
-                        success: bool,
-                        #[serde(flatten)]
-                        data: T,
-                    }
-                    Json(JsonResponse {
-                        success: self.data.is_ok(),
-                        data: if human {
-                            Ok(json!({
-                                "message": match self.data {
-                                    Ok(data) => data.as_plain_text(human),
-                                    Err(err) => err.as_plain_text(human),
-                                }
-                            }))
-                        } else {
-                            match self.data {
-                                Ok(data) => serde_json::to_value(data),
-                                Err(err) => serde_json::to_value(err),
-                            }
+impl<T: Serialize + TextRepresentable> ApiResponse<T> {
+    pub fn render(self) -> RenderedResponse {
+        self.render_in(Language::French)
+    }
+
+    // With the text of errors in `language`. Menus are always in French
+    // here, see `Translator` for them.
+    pub fn render_in(self, language: Language) -> RenderedResponse {
+        let mut status = match &self.data {
+            Ok(_) => StatusCode::OK,
+            Err(err) => err.status_code(),
+        };
+        let (content_type, body) = match self.response_type {
+            ResponseType::Json(human) => {
+                #[derive(Serialize)]
+                struct JsonResponse<T> {
+                    success: bool,
+                    api_version: u32,
+                    #[serde(flatten)]
+                    data: T,
+                }
+                let success = self.data.is_ok();
+                let data = if human {
+                    Ok(json!({
+                        "message": match self.data {
+                            Ok(data) => data.as_plain_text(human),
+                            Err(err) => error_text(&err, language, human),
                         }
-                        .expect("serialization failed"),
+                    }))
+                } else {
+                    match self.data {
+                        Ok(data) => serde_json::to_value(data),
+                        Err(err) => serde_json::to_value(err),
+                    }
+                };
+                let body = data.and_then(|data| {
+                    serde_json::to_vec(&JsonResponse {
+                        success,
+                        api_version: API_VERSION,
+                        data,
                     })
-                    .into_response()
-                }
-                ResponseType::Text(human) => match self.data {
+                });
+                // Answers with the reason rather than dropping the request,
+                // an error always serializing.
+                let body = body.unwrap_or_else(|err| {
+                    let err = Error::Rendering(err.to_string());
+                    status = err.status_code();
+                    serde_json::to_vec(&JsonResponse {
+                        success: false,
+                        api_version: API_VERSION,
+                        data: err,
+                    })
+                    .unwrap_or_default()
+                });
+                ("application/json", body)
+            }
+            ResponseType::Text(human, color) => (
+                "text/plain; charset=utf-8",
+                match self.data {
+                    Ok(data) if color => data.as_styled_text(human, TextStyle::Ansi),
                     Ok(data) => data.as_plain_text(human),
-                    Err(err) => err.as_plain_text(human),
+                    Err(err) => error_text(&err, language, human),
                 }
-                .into_response(),
-                ResponseType::Html(scale, center) => Html(
-                    include_str!("wrapper.html")
-                        .replacen("$SCALE", &scale.to_string(), 1)
-                        .replacen("$CENTER", if center { "center" } else { "flex-start" }, 1)
-                        .replacen(
-                            "$BODY",
-                            &match self.data {
-                                Ok(data) => data.as_html(),
-                                Err(err) => err.as_html(),
-                            },
-                            1,
+                .into_bytes(),
+            ),
+            ResponseType::Table => (
+                "text/plain; charset=utf-8",
+                match self.data {
+                    Ok(data) => data.as_table(),
+                    Err(err) => error_text(&err, language, false),
+                }
+                .into_bytes(),
+            ),
+            ResponseType::Html(scale, center) => (
+                "text/html; charset=utf-8",
+                html_page(
+                    &match self.data {
+                        Ok(data) => data.as_html(),
+                        Err(err) if language == Language::French => err.as_html(),
+                        Err(err) => format!(
+                            r#"<div class="error">{}</div>"#,
+                            escape(&error_text(&err, language, false))
                         ),
+                    },
+                    scale,
+                    center,
                 )
-                .into_response(),
-            },
-        )
-            .into_response()
+                .into_bytes(),
+            ),
+            ResponseType::Calendar => {
+                let err = match self.data {
+                    Ok(_) => Error::ContentNegotiation,
+                    Err(err) => err,
+                };
+                status = err.status_code();
+                (
+                    "text/plain; charset=utf-8",
+                    error_text(&err, language, false).into_bytes(),
+                )
+            }
+        };
+        RenderedResponse {
+            status,
+            headers: vec![(header::CONTENT_TYPE, HeaderValue::from_static(content_type))],
+            body: Bytes::from(body),
+        }
+    }
+}
+
+// The French messages, or the descriptions as sentences in English.
+fn error_text(err: &Error, language: Language, human: bool) -> String {
+    match language {
+        Language::French => err.as_plain_text(human),
+        Language::English => {
+            let description = err.to_string();
+            let mut chars = description.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>() + ".")
+                .unwrap_or_default()
+        }
+    }
+}
+
+impl<T: Serialize + TextRepresentable> IntoResponse for ApiResponse<T> {
+    fn into_response(self) -> Response {
+        self.render().into_response()
+    }
+}
+
+// A response serialized ahead of time, to be sent again as is.
+#[derive(Clone, Debug)]
+pub struct RenderedResponse {
+    pub status: StatusCode,
+    pub headers: Vec<(HeaderName, HeaderValue)>,
+    pub body: Bytes,
+}
+
+impl IntoResponse for RenderedResponse {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, self.body).into_response();
+        for (name, value) in self.headers {
+            response.headers_mut().insert(name, value);
+        }
+        response
     }
 }
 
+// Replaces the empty bodies of the 405 answered by the router, keeping their
+// `Allow` header.
+pub async fn method_not_allowed<B>(
+    format: ErrorFormat,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let response = next.run(request).await;
+    if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+        return response;
+    }
+    let mut rejection = format.respond(Error::MethodNotAllowed);
+    if let Some(allow) = response.headers().get(header::ALLOW) {
+        rejection.headers_mut().insert(header::ALLOW, allow.clone());
+    }
+    rejection
+}
+
+// Answers the routes served at the root with the days they had before
+// `/api/v1`, without their categories.
+pub async fn legacy_shape<B>(request: Request<B>, next: Next<B>) -> Response {
+    day::LEGACY_SHAPE.scope(true, next.run(request)).await
+}
+
+pub fn html_page(body: &str, scale: f32, center: bool) -> String {
+    include_str!("wrapper.html")
+        .replacen("$TITLE", &branding::title(), 1)
+        .replacen("$HEADER", &branding::header(), 1)
+        .replacen("$FOOTER", &branding::footer(), 1)
+        .replacen("$SCALE", &scale.to_string(), 1)
+        .replacen("$CENTER", if center { "center" } else { "flex-start" }, 1)
+        .replacen("$BODY", body, 1)
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum ResponseTypeRaw {
     Json,
     Text,
     Html,
+    Calendar,
 }
 
 impl AsNegotiationStr for ResponseTypeRaw {
@@ -91,6 +220,7 @@ impl AsNegotiationStr for ResponseTypeRaw {
             ResponseTypeRaw::Json => "application/json",
             ResponseTypeRaw::Text => "text/plain",
             ResponseTypeRaw::Html => "text/html",
+            ResponseTypeRaw::Calendar => "text/calendar",
         }
     }
 }
@@ -98,8 +228,13 @@ impl AsNegotiationStr for ResponseTypeRaw {
 #[derive(Copy, Clone, Debug)]
 pub enum ResponseType {
     Json(bool),
-    Text(bool),
+    Text(bool, bool),
+    // Plain text laid out in columns, with `?format=table` or
+    // `text/plain;variant=table`.
+    Table,
     Html(f32, bool),
+    // Only negotiated by the routes extracting `WithCalendar`.
+    Calendar,
 }
 
 #[async_trait]
@@ -108,18 +243,19 @@ where
     S: Send + Sync,
     Arc<Negotiator<ContentTypeNegotiation, ResponseTypeRaw>>: FromRef<S>,
 {
-    type Rejection = ApiResponse<()>;
+    type Rejection = Response;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Not a type of its own, so settled before the negotiation.
+        if table_requested(parts) {
+            return Ok(ResponseType::Table);
+        }
         let Negotiation(_, raw) =
             Negotiation::<ContentTypeNegotiation, ResponseTypeRaw>::from_request_parts(
                 parts, state,
             )
             .await
-            .map_err(|_| ApiResponse {
-                response_type: ResponseType::Json(false),
-                data: Err(Error::ContentNegotiation),
-            })?;
+            .map_err(|_| ErrorFormat::loose(&parts.headers).respond(Error::ContentNegotiation))?;
 
         #[derive(Deserialize)]
         struct QueryFormat {
@@ -129,6 +265,8 @@ where
             scale: f32,
             #[serde(default)]
             center: bool,
+            #[serde(default)]
+            color: bool,
         }
 
         impl QueryFormat {
@@ -139,30 +277,185 @@ where
 
         let Query(format) = Query::<QueryFormat>::from_request_parts(parts, state)
             .await
-            .map_err(|_| ApiResponse {
-                response_type: ResponseType::Json(false),
-                data: Err(Error::InvalidFormatParameter),
+            .map_err(|_| {
+                ErrorFormat {
+                    response_type: ResponseType::from(raw),
+                    language: accepted_language(&parts.headers),
+                }
+                .respond(Error::InvalidFormatParameter)
             })?;
 
         Ok(match raw {
             ResponseTypeRaw::Json => ResponseType::Json(format.human),
-            ResponseTypeRaw::Text => ResponseType::Text(format.human),
+            ResponseTypeRaw::Text => ResponseType::Text(format.human, format.color),
             ResponseTypeRaw::Html => ResponseType::Html(format.scale, format.center),
+            ResponseTypeRaw::Calendar => ResponseType::Calendar,
         })
     }
 }
 
-pub trait TextRepresentable {
-    fn as_plain_text(&self, _human: bool) -> String {
-        String::new()
+// Also offers `text/calendar`, for the index, the weeks and the days, see
+// `cache::calendar`.
+pub struct WithCalendar(pub ResponseType);
+
+// The negotiator of `WithCalendar`, the other routes using the one without
+// `text/calendar`.
+#[derive(Clone)]
+pub struct CalendarNegotiator(pub Arc<Negotiator<ContentTypeNegotiation, ResponseTypeRaw>>);
+
+impl FromRef<CalendarNegotiator> for Arc<Negotiator<ContentTypeNegotiation, ResponseTypeRaw>> {
+    fn from_ref(negotiator: &CalendarNegotiator) -> Self {
+        Arc::clone(&negotiator.0)
     }
+}
 
-    fn as_html(&self) -> String {
-        String::new()
+#[async_trait]
+impl<S> FromRequestParts<S> for WithCalendar
+where
+    S: Send + Sync,
+    CalendarNegotiator: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let negotiator = CalendarNegotiator::from_ref(state);
+        ResponseType::from_request_parts(parts, &negotiator)
+            .await
+            .map(Self)
+    }
+}
+
+impl From<ResponseTypeRaw> for ResponseType {
+    fn from(raw: ResponseTypeRaw) -> Self {
+        match raw {
+            ResponseTypeRaw::Json => ResponseType::Json(false),
+            ResponseTypeRaw::Text => ResponseType::Text(false, false),
+            ResponseTypeRaw::Html => ResponseType::Html(1.0, false),
+            ResponseTypeRaw::Calendar => ResponseType::Calendar,
+        }
+    }
+}
+
+// How errors raised before a handler answers, like missing credentials or
+// an unsatisfiable `Accept`, are rendered: in the negotiated format when
+// there is one, in the language of `Accept-Language`. Never fails itself.
+#[derive(Copy, Clone, Debug)]
+pub struct ErrorFormat {
+    pub response_type: ResponseType,
+    pub language: Language,
+}
+
+impl ErrorFormat {
+    // Without negotiation, the preferred of the known types listed in
+    // `Accept`, JSON otherwise.
+    pub fn loose(headers: &HeaderMap) -> Self {
+        let response_type = header_values(headers, header::ACCEPT)
+            .filter_map(|(value, quality)| match value.as_str() {
+                "application/json" => Some((ResponseTypeRaw::Json, quality)),
+                "text/plain" => Some((ResponseTypeRaw::Text, quality)),
+                "text/html" => Some((ResponseTypeRaw::Html, quality)),
+                "text/calendar" => Some((ResponseTypeRaw::Calendar, quality)),
+                _ => None,
+            })
+            .reduce(|best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            })
+            .map_or(ResponseType::Json(false), |(raw, _)| raw.into());
+        Self {
+            response_type,
+            language: accepted_language(headers),
+        }
+    }
+
+    pub fn respond(self, err: Error) -> Response {
+        ApiResponse::<()> {
+            response_type: self.response_type,
+            data: Err(err),
+        }
+        .render_in(self.language)
+        .into_response()
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ErrorFormat
+where
+    S: Send + Sync,
+    Arc<Negotiator<ContentTypeNegotiation, ResponseTypeRaw>>: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(match ResponseType::from_request_parts(parts, state).await {
+            Ok(response_type) => Self {
+                response_type,
+                language: accepted_language(&parts.headers),
+            },
+            Err(_) => Self::loose(&parts.headers),
+        })
     }
 }
 
-impl TextRepresentable for () {}
+// English when preferred to French in `Accept-Language`.
+fn accepted_language(headers: &HeaderMap) -> Language {
+    let quality = |language: &str| {
+        header_values(headers, header::ACCEPT_LANGUAGE)
+            .filter(|(tag, _)| tag.split('-').next() == Some(language))
+            .map(|(_, quality)| quality)
+            .fold(0.0, f32::max)
+    };
+    if quality("en") > quality("fr") {
+        Language::English
+    } else {
+        Language::French
+    }
+}
+
+// With `?format=table`, or `text/plain;variant=table` in `Accept`.
+fn table_requested(parts: &Parts) -> bool {
+    let query = parts.uri.query().unwrap_or_default();
+    if query.split('&').any(|pair| pair == "format=table") {
+        return true;
+    }
+    parts
+        .headers
+        .get_all(header::ACCEPT)
+        .into_iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|entry| {
+            let mut params = entry.split(';').map(str::trim);
+            params
+                .next()
+                .is_some_and(|value| value.eq_ignore_ascii_case("text/plain"))
+                && params.any(|param| param.eq_ignore_ascii_case("variant=table"))
+        })
+}
+
+// Lowercase values of a comma separated header, with their `q` weight.
+fn header_values(
+    headers: &HeaderMap,
+    name: HeaderName,
+) -> impl Iterator<Item = (String, f32)> + '_ {
+    headers
+        .get_all(name)
+        .into_iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|entry| {
+            let mut params = entry.split(';');
+            let value = params.next().unwrap_or_default().trim().to_lowercase();
+            let quality = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|quality| quality.parse().ok())
+                .unwrap_or(1.0);
+            (value, quality)
+        })
+}
 
 impl<L: TextRepresentable, R: TextRepresentable> TextRepresentable for Either<L, R> {
     fn as_plain_text(&self, human: bool) -> String {
@@ -178,4 +471,214 @@ impl<L: TextRepresentable, R: TextRepresentable> TextRepresentable for Either<L,
             Either::Right(rhs) => rhs.as_html(),
         }
     }
+
+    fn as_styled_text(&self, human: bool, style: TextStyle) -> String {
+        match self {
+            Either::Left(lhs) => lhs.as_styled_text(human, style),
+            Either::Right(rhs) => rhs.as_styled_text(human, style),
+        }
+    }
+
+    fn as_table(&self) -> String {
+        match self {
+            Either::Left(lhs) => lhs.as_table(),
+            Either::Right(rhs) => rhs.as_table(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    // Every variant, with a sample of the data of some.
+    fn errors() -> Vec<Error> {
+        vec![
+            Error::ContentNegotiation,
+            Error::InvalidFormatParameter,
+            Error::InvalidBody,
+            Error::InvalidJson,
+            Error::InvalidPdf,
+            Error::InvalidCsv,
+            Error::InvalidXlsx,
+            Error::InvalidIcs,
+            Error::SchemaViolation("missing date".to_owned()),
+            Error::NoMealToday,
+            Error::NoNextMeal,
+            Error::InvalidWeek,
+            Error::InvalidMonth,
+            Error::InvalidCategory,
+            Error::InvalidDay,
+            Error::InvalidCutoff,
+            Error::WeekNotFound,
+            Error::DayNotFound,
+            Error::JobNotFound,
+            Error::SiteNotFound,
+            Error::SchemaNotFound,
+            Error::UnsupportedExportVersion,
+            Error::InvalidWatch,
+            Error::WatchNotFound,
+            Error::PushDisabled,
+            Error::SubscriptionNotFound,
+            Error::DishNotFound,
+            Error::InvalidAllergen,
+            Error::InvalidRating,
+            Error::InvalidLanguage,
+            Error::TooManyRequests,
+            Error::Busy,
+            Error::NothingToUndo,
+            Error::CatalogueChanged,
+            Error::SourceNotFound,
+            Error::RouteNotFound,
+            Error::MethodNotAllowed,
+            Error::Unauthorized,
+            Error::AdminDisabled,
+            Error::SessionsDisabled,
+            Error::Rendering("<script>".to_owned()),
+            Error::Internal,
+            Error::Panic("0b5e".to_owned()),
+        ]
+    }
+
+    fn response_types() -> [ResponseType; 8] {
+        [
+            ResponseType::Json(false),
+            ResponseType::Json(true),
+            ResponseType::Text(false, false),
+            ResponseType::Text(true, false),
+            ResponseType::Text(false, true),
+            ResponseType::Table,
+            ResponseType::Html(1.0, false),
+            ResponseType::Calendar,
+        ]
+    }
+
+    fn headers(pairs: &[(HeaderName, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect()
+    }
+
+    fn parts(accept: &'static str) -> Parts {
+        let (parts, _) = Request::builder()
+            .header(header::ACCEPT, accept)
+            .body(())
+            .expect("invalid request")
+            .into_parts();
+        parts
+    }
+
+    #[tokio::test]
+    async fn calendars_are_only_negotiated_where_offered() {
+        let negotiator = Arc::new(
+            Negotiator::new([
+                ResponseTypeRaw::Json,
+                ResponseTypeRaw::Text,
+                ResponseTypeRaw::Html,
+            ])
+            .expect("invalid negotiator"),
+        );
+        let calendar = CalendarNegotiator(Arc::new(
+            Negotiator::new([
+                ResponseTypeRaw::Json,
+                ResponseTypeRaw::Text,
+                ResponseTypeRaw::Html,
+                ResponseTypeRaw::Calendar,
+            ])
+            .expect("invalid negotiator"),
+        ));
+        let accept = "text/calendar, application/json;q=0.9";
+        assert!(matches!(
+            ResponseType::from_request_parts(&mut parts(accept), &negotiator).await,
+            Ok(ResponseType::Json(false))
+        ));
+        assert!(matches!(
+            WithCalendar::from_request_parts(&mut parts(accept), &calendar).await,
+            Ok(WithCalendar(ResponseType::Calendar))
+        ));
+    }
+
+    #[test]
+    fn every_error_in_every_format() {
+        for err in errors() {
+            for response_type in response_types() {
+                for language in [Language::French, Language::English] {
+                    let rendered = ApiResponse::<()> {
+                        response_type,
+                        data: Err(err.clone()),
+                    }
+                    .render_in(language);
+                    let context = format!("{err:?} as {response_type:?} in {language:?}");
+                    assert_eq!(rendered.status, err.status_code(), "{context}");
+                    let content_type = &rendered.headers[0].1;
+                    let body = std::str::from_utf8(&rendered.body).expect("invalid utf-8");
+                    match response_type {
+                        ResponseType::Json(human) => {
+                            assert_eq!(content_type, "application/json", "{context}");
+                            let json = serde_json::from_str::<Value>(body).expect(&context);
+                            assert_eq!(json["success"], false, "{context}");
+                            let field = if human { "message" } else { "error" };
+                            assert!(json[field].is_string(), "{context}");
+                        }
+                        ResponseType::Html(..) => {
+                            assert_eq!(content_type, "text/html; charset=utf-8", "{context}");
+                            assert!(body.contains(r#"<div class="error">"#), "{context}");
+                            assert!(!body.contains(": <script>"), "{context}");
+                        }
+                        ResponseType::Text(human, _) => {
+                            assert_eq!(content_type, "text/plain; charset=utf-8", "{context}");
+                            assert_eq!(body, error_text(&err, language, human), "{context}");
+                        }
+                        ResponseType::Table | ResponseType::Calendar => {
+                            assert_eq!(content_type, "text/plain; charset=utf-8", "{context}");
+                            assert_eq!(body, error_text(&err, language, false), "{context}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn english_errors_are_sentences() {
+        assert_eq!(
+            error_text(&Error::DayNotFound, Language::English, false),
+            "Day not found."
+        );
+        assert_eq!(
+            error_text(&Error::NoMealToday, Language::French, false),
+            "Aucun repas de prévu pour aujourd'hui."
+        );
+    }
+
+    #[test]
+    fn loose_format_follows_accept() {
+        let format = ErrorFormat::loose(&HeaderMap::new());
+        assert!(matches!(format.response_type, ResponseType::Json(false)));
+        assert_eq!(format.language, Language::French);
+
+        let format = ErrorFormat::loose(&headers(&[
+            (header::ACCEPT, "text/html;q=0.8, text/plain, image/png"),
+            (header::ACCEPT_LANGUAGE, "en-US, fr;q=0.5"),
+        ]));
+        assert!(matches!(format.response_type, ResponseType::Text(false, false)));
+        assert_eq!(format.language, Language::English);
+
+        let format = ErrorFormat::loose(&headers(&[(header::ACCEPT, "image/png")]));
+        assert!(matches!(format.response_type, ResponseType::Json(false)));
+    }
+
+    #[test]
+    fn loose_format_renders_in_the_accepted_type() {
+        let response = ErrorFormat::loose(&headers(&[(header::ACCEPT, "text/html")]))
+            .respond(Error::Busy);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+    }
 }
@@ -0,0 +1,153 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use time::Date;
+
+use crate::{error::Error, response::TextRepresentable, utils::format_date};
+
+// Who is going to lunch, one answer per profile token and day, saved to
+// `rsvp_file` on every change.
+#[derive(Debug)]
+pub struct Rsvps {
+    rsvps: Vec<Rsvp>,
+    path: Option<PathBuf>,
+}
+
+impl Rsvps {
+    pub fn open(path: Option<&Path>) -> Result<Self, String> {
+        let rsvps = match path.map(fs::read_to_string) {
+            Some(Ok(content)) => {
+                serde_json::from_str(&content).map_err(|err| format!("invalid rsvp file: {err}"))?
+            }
+            Some(Err(err)) if err.kind() != ErrorKind::NotFound => return Err(err.to_string()),
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            rsvps,
+            path: path.map(ToOwned::to_owned),
+        })
+    }
+
+    // Answering again only updates the name.
+    pub fn add(
+        &mut self,
+        site: Option<&str>,
+        date: Date,
+        token: &str,
+        request: RsvpRequest,
+    ) -> Result<Attendance, Error> {
+        let name = request
+            .name
+            .map(|name| name.trim().to_owned())
+            .filter(|name| !name.is_empty());
+        let date_str = format_date(date);
+        match self
+            .rsvps
+            .iter_mut()
+            .find(|rsvp| rsvp.is(site, &date_str, token))
+        {
+            Some(rsvp) => rsvp.name = name,
+            None => self.rsvps.push(Rsvp {
+                site: site.map(ToOwned::to_owned),
+                date: date_str,
+                token: token.to_owned(),
+                name,
+            }),
+        }
+        self.save()?;
+        Ok(self.attendance(site, date))
+    }
+
+    pub fn remove(
+        &mut self,
+        site: Option<&str>,
+        date: Date,
+        token: &str,
+    ) -> Result<Attendance, Error> {
+        let date_str = format_date(date);
+        self.rsvps.retain(|rsvp| !rsvp.is(site, &date_str, token));
+        self.save()?;
+        Ok(self.attendance(site, date))
+    }
+
+    pub fn attendance(&self, site: Option<&str>, date: Date) -> Attendance {
+        let date = format_date(date);
+        let rsvps = self
+            .rsvps
+            .iter()
+            .filter(|rsvp| rsvp.site.as_deref() == site && rsvp.date == date)
+            .collect_vec();
+        Attendance {
+            date,
+            count: rsvps.len(),
+            names: rsvps
+                .iter()
+                .filter_map(|rsvp| rsvp.name.clone())
+                .sorted()
+                .collect(),
+        }
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string(&self.rsvps).expect("rsvp serialization failed");
+        fs::write(path, content).map_err(|err| {
+            eprintln!("failed to save rsvp: {err}");
+            Error::Internal
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Rsvp {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    site: Option<String>,
+    date: String,
+    token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+impl Rsvp {
+    fn is(&self, site: Option<&str>, date: &str, token: &str) -> bool {
+        self.site.as_deref() == site && self.date == date && self.token == token
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub struct RsvpRequest {
+    // Shown to the others, the answer is anonymous without it.
+    name: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Attendance {
+    date: String,
+    count: usize,
+    names: Vec<String>,
+}
+
+impl TextRepresentable for Attendance {
+    fn as_plain_text(&self, human: bool) -> String {
+        if !human {
+            return self.count.to_string();
+        }
+        let people = match self.count {
+            0 => "Personne ne s'est encore inscrit".to_owned(),
+            1 => "1 personne inscrite".to_owned(),
+            count => format!("{count} personnes inscrites"),
+        };
+        if self.names.is_empty() {
+            format!("{people}.")
+        } else {
+            format!("{people} : {}.", self.names.join(", "))
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use std::sync::OnceLock;
+
+use jsonschema::JSONSchema;
+use serde_json::Value;
+
+const DAY: &str = include_str!("schemas/day.json");
+
+static DAY_SCHEMA: OnceLock<JSONSchema> = OnceLock::new();
+
+pub fn get(file: &str) -> Option<&'static str> {
+    match file {
+        "day.json" => Some(DAY),
+        "catalogue.json" => Some(include_str!("schemas/catalogue.json")),
+        "weeks.json" => Some(include_str!("schemas/weeks.json")),
+        "catalogue-update.json" => Some(include_str!("schemas/catalogue-update.json")),
+        _ => None,
+    }
+}
+
+// Every way `day` breaks the Day schema, prefixed with the offending path.
+pub fn validate_day(day: &Value) -> Vec<String> {
+    let schema = DAY_SCHEMA.get_or_init(|| {
+        JSONSchema::compile(&serde_json::from_str(DAY).expect("invalid day schema"))
+            .expect("invalid day schema")
+    });
+    match schema.validate(day) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|err| format!("{}: {err}", err.instance_path))
+            .collect(),
+    }
+}
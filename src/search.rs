@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use itertools::Itertools;
+use serde::Serialize;
+use time::Date;
+
+use crate::{day::Day, response::TextRepresentable, utils::format_date};
+
+// Words of `text`, lowercase and without accents, so "Pâtes" is found with
+// "pate".
+pub fn tokens(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            word.chars()
+                .flat_map(char::to_lowercase)
+                .map(fold)
+                .collect()
+        })
+        .collect()
+}
+
+fn fold(c: char) -> char {
+    match c {
+        'à' | 'â' | 'ä' => 'a',
+        'ç' => 'c',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'î' | 'ï' => 'i',
+        'ô' | 'ö' => 'o',
+        'ù' | 'û' | 'ü' => 'u',
+        'ÿ' => 'y',
+        c => c,
+    }
+}
+
+// Dishes of the catalogue by the tokens of their names, kept up to date by
+// the catalogue on every change.
+#[derive(Clone, Default, Debug)]
+pub struct DishIndex {
+    // Token → dishes with it, as their date and position in the day.
+    tokens: BTreeMap<String, BTreeSet<(Date, usize)>>,
+}
+
+impl DishIndex {
+    pub fn new(days: &[Day]) -> Self {
+        let mut index = Self::default();
+        days.iter().for_each(|day| index.add(day));
+        index
+    }
+
+    pub fn add(&mut self, day: &Day) {
+        for (position, dish) in day.dishes_ref().iter().enumerate() {
+            for token in tokens(dish.name()) {
+                self.tokens
+                    .entry(token)
+                    .or_default()
+                    .insert((day.date(), position));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, day: &Day) {
+        for (position, dish) in day.dishes_ref().iter().enumerate() {
+            for token in tokens(dish.name()) {
+                let Some(dishes) = self.tokens.get_mut(&token) else {
+                    continue;
+                };
+                dishes.remove(&(day.date(), position));
+                if dishes.is_empty() {
+                    self.tokens.remove(&token);
+                }
+            }
+        }
+    }
+
+    // Dishes with, for every word of `query`, a word starting with it.
+    // Nothing when `query` has no words.
+    pub fn matching(&self, query: &str) -> BTreeSet<(Date, usize)> {
+        let mut matching: Option<BTreeSet<_>> = None;
+        for token in tokens(query) {
+            let dishes = self
+                .tokens
+                .range(token.clone()..)
+                .take_while(|(candidate, _)| candidate.starts_with(&token))
+                .flat_map(|(_, dishes)| dishes.iter().copied())
+                .collect::<BTreeSet<_>>();
+            matching = Some(match matching {
+                Some(matching) => matching.intersection(&dishes).copied().collect(),
+                None => dishes,
+            });
+            if matching.as_ref().is_some_and(BTreeSet::is_empty) {
+                break;
+            }
+        }
+        matching.unwrap_or_default()
+    }
+}
+
+// Distinct dish names, the most served first.
+#[derive(Serialize, Debug)]
+pub struct DishList {
+    dishes: Vec<ServedDish>,
+}
+
+#[derive(Serialize, Debug)]
+struct ServedDish {
+    name: String,
+    served: usize,
+    last_served: String,
+}
+
+impl DishList {
+    // `dishes` as found in the days, in any order.
+    pub fn new<'a>(dishes: impl Iterator<Item = (Date, &'a str)>, limit: usize) -> Self {
+        let mut served = HashMap::<&str, (usize, Date)>::new();
+        for (date, name) in dishes {
+            let (count, last) = served.entry(name).or_insert((0, date));
+            *count += 1;
+            *last = (*last).max(date);
+        }
+        Self {
+            dishes: served
+                .into_iter()
+                .sorted_by(|(a, (a_count, a_last)), (b, (b_count, b_last))| {
+                    b_count.cmp(a_count).then(b_last.cmp(a_last)).then(a.cmp(b))
+                })
+                .take(limit)
+                .map(|(name, (served, last))| ServedDish {
+                    name: name.to_owned(),
+                    served,
+                    last_served: format_date(last),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TextRepresentable for DishList {
+    fn as_plain_text(&self, _human: bool) -> String {
+        self.dishes.iter().map(|dish| &dish.name).join("\n")
+    }
+}
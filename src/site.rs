@@ -0,0 +1,244 @@
+use std::{collections::HashMap, convert::Infallible, sync::Arc};
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Path},
+    http::{header, request::Parts, HeaderValue, Method, Request},
+    middleware::Next,
+    response::Response,
+};
+use itertools::Itertools;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use tokio::sync::RwLock;
+
+use crate::{
+    catalogue::Catalogue,
+    day::Day,
+    error::Error,
+    response::{ErrorFormat, TextRepresentable},
+    sources::Sources,
+    utils::escape,
+};
+
+// One catalogue per restaurant. The default site also answers the routes
+// that aren't prefixed with `/sites/:site`.
+#[derive(Debug)]
+pub struct Sites {
+    default_name: String,
+    catalogues: Vec<(String, Arc<RwLock<Catalogue>>, Arc<Sources>)>,
+}
+
+impl Sites {
+    pub fn new(
+        default_name: String,
+        default: Catalogue,
+        others: &[String],
+        sources: Sources,
+    ) -> Self {
+        let mut catalogues = vec![(
+            default_name.clone(),
+            Arc::new(RwLock::new(default)),
+            Arc::new(sources.clone()),
+        )];
+        for name in others {
+            if catalogues.iter().all(|(existing, _, _)| existing != name) {
+                catalogues.push((
+                    name.clone(),
+                    Arc::new(RwLock::new(Catalogue::new())),
+                    Arc::new(sources.site(name)),
+                ));
+            }
+        }
+        Self {
+            default_name,
+            catalogues,
+        }
+    }
+
+    pub fn get(&self, name: Option<&str>) -> Option<Site> {
+        let name = name.unwrap_or(&self.default_name);
+        self.catalogues
+            .iter()
+            .find(|(site, _, _)| site == name)
+            .map(|(site, catalogue, sources)| Site {
+                name: (site != &self.default_name).then(|| site.clone()),
+                catalogue: Arc::clone(catalogue),
+                sources: Arc::clone(sources),
+            })
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = Site> + '_ {
+        self.catalogues
+            .iter()
+            .filter_map(|(name, _, _)| self.get(Some(name)))
+    }
+
+    pub fn count(&self) -> usize {
+        self.catalogues.len()
+    }
+
+    pub async fn overview(&self) -> SitesOverview {
+        let mut sites = Vec::with_capacity(self.catalogues.len());
+        for (name, catalogue, _) in &self.catalogues {
+            // Linked like the site itself in `SitesOverview::as_html`.
+            let today = catalogue.read().await.today();
+            let root = format!("/sites/{name}");
+            sites.push((name.clone(), today.map(|day| day.in_site(&root))));
+        }
+        SitesOverview { sites }
+    }
+}
+
+// The catalogue targeted by a request, `name` being `None` for the default
+// site.
+#[derive(Clone, Debug)]
+pub struct Site {
+    pub name: Option<String>,
+    pub catalogue: Arc<RwLock<Catalogue>>,
+    pub sources: Arc<Sources>,
+}
+
+impl Site {
+    // Where the routes of the site are nested, empty for the default site,
+    // for links between its pages.
+    pub fn path_prefix(&self) -> String {
+        self.name
+            .as_deref()
+            .map(|name| format!("/sites/{name}"))
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Site
+where
+    S: Send + Sync,
+    Arc<Sites>: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let name = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .ok()
+            .and_then(|Path(mut params)| params.remove("site"));
+        Arc::<Sites>::from_ref(state)
+            .get(name.as_deref())
+            .ok_or_else(|| ErrorFormat::loose(&parts.headers).respond(Error::SiteNotFound))
+    }
+}
+
+// Adds the catalogue version to the answers of read requests, so clients can
+// tell whether anything changed without comparing the days.
+pub async fn version_header<B>(
+    Site { catalogue, .. }: Site,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let read = matches!(*request.method(), Method::GET | Method::HEAD);
+    let mut response = next.run(request).await;
+    if read {
+        let version = catalogue.read().await.version();
+        response.headers_mut().insert(
+            "X-Catalogue-Version",
+            HeaderValue::from_str(&version).expect("invalid catalogue version header"),
+        );
+    }
+    response
+}
+
+// The catalogue version a change was based on, from `If-Match`. Changes
+// without one always apply.
+#[derive(Debug)]
+pub struct IfMatch(Option<String>);
+
+impl IfMatch {
+    // To call with the catalogue locked for the change, so nothing can slip
+    // in between.
+    pub fn check(&self, catalogue: &Catalogue) -> Result<(), Error> {
+        match &self.0 {
+            Some(version) if version != "*" && *version != catalogue.version() => {
+                Err(Error::CatalogueChanged)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Accepts the version as is or quoted like an entity tag.
+        Ok(Self(
+            parts
+                .headers
+                .get(header::IF_MATCH)
+                .and_then(|h| h.to_str().ok())
+                .map(|h| h.trim().trim_start_matches("W/").trim_matches('"').to_owned()),
+        ))
+    }
+}
+
+pub struct SitesOverview {
+    sites: Vec<(String, Option<Day>)>,
+}
+
+impl Serialize for SitesOverview {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Site<'a> {
+            name: &'a str,
+            today: Option<&'a Day>,
+        }
+
+        let mut state = serializer.serialize_struct("SitesOverview", 1)?;
+        state.serialize_field(
+            "sites",
+            &self
+                .sites
+                .iter()
+                .map(|(name, today)| Site {
+                    name,
+                    today: today.as_ref(),
+                })
+                .collect_vec(),
+        )?;
+        state.end()
+    }
+}
+
+impl TextRepresentable for SitesOverview {
+    fn as_plain_text(&self, human: bool) -> String {
+        self.sites
+            .iter()
+            .map(|(name, today)| {
+                let menu = match today {
+                    Some(day) => day.as_plain_text(human),
+                    None => Error::NoMealToday.as_plain_text(human),
+                };
+                format!("{name} :\n{menu}")
+            })
+            .join("\n\n")
+    }
+
+    fn as_html(&self) -> String {
+        self.sites
+            .iter()
+            .map(|(name, today)| {
+                let name = escape(name);
+                format!(
+                    r#"<a href="/sites/{name}" class="site">{name}</a>{}"#,
+                    match today {
+                        Some(day) => day.as_html(),
+                        None => Error::NoMealToday.as_html(),
+                    }
+                )
+            })
+            .collect()
+    }
+}
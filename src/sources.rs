@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::{
+    catalogue::week_of,
+    day::Day,
+    error::Error,
+    storage::{self, Storage},
+    upload::{FileKind, UploadFile},
+};
+
+// The uploaded PDFs of a site, one per week, so the original can be checked
+// and parsed again after parser fixes.
+#[derive(Clone, Debug)]
+pub struct Sources {
+    storage: Option<Arc<Storage>>,
+    dir: String,
+}
+
+impl Sources {
+    pub fn new(storage: Option<Arc<Storage>>, dir: &str) -> Self {
+        Self {
+            storage,
+            dir: dir.to_owned(),
+        }
+    }
+
+    // Other sites than the default one are kept in `sites/NAME`.
+    pub fn site(&self, name: &str) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            dir: storage::key(&self.dir, &format!("sites/{name}")),
+        }
+    }
+
+    // Replaces the PDF of every week `days` are part of.
+    pub async fn store(&self, file: &UploadFile, days: &[Day]) {
+        if !matches!(file.kind(), FileKind::Pdf) {
+            return;
+        }
+        let Some(storage) = &self.storage else {
+            return;
+        };
+        let weeks = days
+            .iter()
+            .map(|day| week_of(day.date()))
+            .unique();
+        for (year, week) in weeks {
+            let key = storage::key(&self.dir, &file_name(year, week));
+            if let Err(err) = storage.put(&key, file.data(), "application/pdf").await {
+                eprintln!("failed to store source of {year}-{week}: {err}");
+            }
+        }
+    }
+
+    pub async fn get(&self, year: i32, week: u8) -> Result<Vec<u8>, Error> {
+        let storage = self.storage.as_ref().ok_or(Error::SourceNotFound)?;
+        match storage
+            .get(&storage::key(&self.dir, &file_name(year, week)))
+            .await
+        {
+            Ok(Some(data)) => Ok(data),
+            Ok(None) => Err(Error::SourceNotFound),
+            Err(err) => {
+                eprintln!("failed to read source of {year}-{week}: {err}");
+                Err(Error::Internal)
+            }
+        }
+    }
+
+    // Every stored PDF by file name, oldest week first.
+    pub async fn all(&self) -> Vec<(String, Vec<u8>)> {
+        let Some(storage) = &self.storage else {
+            return Vec::new();
+        };
+        let names = match storage.list(&self.dir).await {
+            Ok(names) => names,
+            Err(err) => {
+                eprintln!("failed to list sources: {err}");
+                return Vec::new();
+            }
+        };
+        let mut sources = Vec::new();
+        for name in names
+            .into_iter()
+            .filter(|name| name.ends_with(".pdf"))
+            .sorted()
+        {
+            match storage.get(&storage::key(&self.dir, &name)).await {
+                Ok(Some(data)) => sources.push((name, data)),
+                Ok(None) => {}
+                Err(err) => eprintln!("failed to read source {name}: {err}"),
+            }
+        }
+        sources
+    }
+}
+
+fn file_name(year: i32, week: u8) -> String {
+    format!("{year}-{week:02}.pdf")
+}
@@ -0,0 +1,213 @@
+use std::{collections::HashMap, fs, io::ErrorKind, path::PathBuf, sync::Arc, time::Duration};
+
+use s3::{bucket::Bucket, creds::Credentials, region::Region};
+use serde::Deserialize;
+use tokio::time::interval;
+
+use crate::{catalogue::CatalogueExport, config::Config, site::Sites};
+
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize, Debug)]
+pub struct S3Config {
+    // Like "https://s3.eu-west-3.amazonaws.com" or a MinIO server.
+    pub endpoint: String,
+    #[serde(default = "S3Config::default_region")]
+    pub region: String,
+    pub bucket: String,
+    pub access_key: String,
+    pub secret_key: String,
+    // Buckets in the URL path rather than the host name, for most
+    // self-hosted servers.
+    #[serde(default)]
+    pub path_style: bool,
+    // Prepended to every object key, like "ovr/".
+    #[serde(default)]
+    pub prefix: String,
+}
+
+impl S3Config {
+    fn default_region() -> String {
+        "us-east-1".to_owned()
+    }
+}
+
+// Where files outliving the process are kept, a local directory or an
+// S3-compatible bucket. Keys are relative paths like "sources/2023-24.pdf".
+#[derive(Debug)]
+pub enum Storage {
+    Directory(PathBuf),
+    S3 { bucket: Box<Bucket>, prefix: String },
+}
+
+impl Storage {
+    pub fn s3(config: &S3Config) -> Result<Self, String> {
+        let credentials = Credentials::new(
+            Some(&config.access_key),
+            Some(&config.secret_key),
+            None,
+            None,
+            None,
+        )
+        .map_err(|err| format!("invalid s3 credentials: {err}"))?;
+        let region = Region::Custom {
+            region: config.region.clone(),
+            endpoint: config.endpoint.clone(),
+        };
+        let bucket = Bucket::new(&config.bucket, region, credentials)
+            .map_err(|err| format!("invalid s3 bucket: {err}"))?;
+        Ok(Self::S3 {
+            bucket: Box::new(if config.path_style {
+                bucket.with_path_style()
+            } else {
+                bucket
+            }),
+            prefix: config.prefix.clone(),
+        })
+    }
+
+    pub async fn put(&self, key: &str, data: &[u8], content_type: &str) -> Result<(), String> {
+        match self {
+            Storage::Directory(dir) => {
+                let path = dir.join(key);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+                }
+                fs::write(path, data).map_err(|err| err.to_string())
+            }
+            Storage::S3 { bucket, prefix } => {
+                let response = bucket
+                    .put_object_with_content_type(format!("{prefix}{key}"), data, content_type)
+                    .await
+                    .map_err(|err| err.to_string())?;
+                match response.status_code() {
+                    200..=299 => Ok(()),
+                    status => Err(format!("s3 answered {status}")),
+                }
+            }
+        }
+    }
+
+    // `None` when there is no such file.
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        match self {
+            Storage::Directory(dir) => match fs::read(dir.join(key)) {
+                Ok(data) => Ok(Some(data)),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.to_string()),
+            },
+            Storage::S3 { bucket, prefix } => {
+                let response = bucket
+                    .get_object(format!("{prefix}{key}"))
+                    .await
+                    .map_err(|err| err.to_string())?;
+                match response.status_code() {
+                    200..=299 => Ok(Some(response.bytes().to_vec())),
+                    404 => Ok(None),
+                    status => Err(format!("s3 answered {status}")),
+                }
+            }
+        }
+    }
+
+    // Names of the files right under `dir`, "" being the root.
+    pub async fn list(&self, dir: &str) -> Result<Vec<String>, String> {
+        match self {
+            Storage::Directory(root) => match fs::read_dir(root.join(dir)) {
+                Ok(entries) => Ok(entries
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.path().is_file())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .collect()),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+                Err(err) => Err(err.to_string()),
+            },
+            Storage::S3 { bucket, prefix } => {
+                let dir = key(&format!("{prefix}{dir}"), "");
+                let results = bucket
+                    .list(dir.clone(), Some("/".to_owned()))
+                    .await
+                    .map_err(|err| err.to_string())?;
+                Ok(results
+                    .into_iter()
+                    .flat_map(|result| result.contents)
+                    .filter_map(|object| object.key.strip_prefix(&dir).map(ToOwned::to_owned))
+                    .collect())
+            }
+        }
+    }
+}
+
+// `name` in `dir`, "" being the root.
+pub fn key(dir: &str, name: &str) -> String {
+    match dir.trim_end_matches('/') {
+        "" => name.to_owned(),
+        dir => format!("{dir}/{name}"),
+    }
+}
+
+// Key of the snapshot of a site's catalogue.
+fn snapshot_key(site: Option<&str>) -> String {
+    match site {
+        Some(site) => format!("snapshots/sites/{site}.json"),
+        None => "snapshots/default.json".to_owned(),
+    }
+}
+
+// Replaces the catalogues with their latest snapshots, for instances without
+// a persistent volume.
+pub async fn restore(sites: &Sites, config: &Config, storage: &Storage) -> Result<(), String> {
+    for name in site_names(config) {
+        let Some(site) = sites.get(name) else {
+            continue;
+        };
+        let Some(data) = storage.get(&snapshot_key(site.name.as_deref())).await? else {
+            continue;
+        };
+        let export = serde_json::from_slice::<CatalogueExport>(&data)
+            .map_err(|err| format!("invalid snapshot: {err}"))?;
+        if export.version != CatalogueExport::VERSION {
+            return Err(format!("unsupported snapshot version {}", export.version));
+        }
+        site.catalogue.write().await.restore(export.days);
+    }
+    Ok(())
+}
+
+// Uploads the export of every catalogue that changed since the last upload,
+// checking every minute.
+pub fn spawn(sites: Arc<Sites>, config: Arc<Config>, storage: Arc<Storage>) {
+    tokio::spawn(async move {
+        let mut versions = HashMap::new();
+        let mut interval = interval(SNAPSHOT_INTERVAL);
+        loop {
+            interval.tick().await;
+            for name in site_names(&config) {
+                let Some(site) = sites.get(name) else {
+                    continue;
+                };
+                let (version, export) = {
+                    let catalogue = site.catalogue.read().await;
+                    (catalogue.version(), catalogue.export())
+                };
+                if versions.get(&site.name) == Some(&version) {
+                    continue;
+                }
+                let data = serde_json::to_vec(&export).expect("export serialization failed");
+                let key = snapshot_key(site.name.as_deref());
+                match storage.put(&key, &data, "application/json").await {
+                    Ok(()) => {
+                        versions.insert(site.name, version);
+                    }
+                    Err(err) => eprintln!("failed to store snapshot {key}: {err}"),
+                }
+            }
+        }
+    });
+}
+
+fn site_names(config: &Config) -> impl Iterator<Item = Option<&str>> {
+    [None]
+        .into_iter()
+        .chain(config.sites.iter().map(|name| Some(name.as_str())))
+}
@@ -0,0 +1,42 @@
+const CACHE = 'ovr-v1';
+
+self.addEventListener('install', (event) => {
+    self.skipWaiting();
+    event.waitUntil(
+        caches.open(CACHE).then((cache) => cache.add(new Request('/today', { headers: { Accept: 'text/html' } })))
+    );
+});
+
+self.addEventListener('activate', (event) => {
+    event.waitUntil(clients.claim());
+});
+
+// Pages come from the network when possible, the last answer of each one is
+// kept for when there's no signal.
+self.addEventListener('fetch', (event) => {
+    const request = event.request;
+    if (request.method !== 'GET' || request.mode !== 'navigate') {
+        return;
+    }
+    event.respondWith(
+        fetch(request)
+            .then((response) => {
+                if (response.ok) {
+                    const copy = response.clone();
+                    caches.open(CACHE).then((cache) => cache.put(request, copy));
+                }
+                return response;
+            })
+            .catch(async () => (await caches.match(request)) || (await caches.match('/today')) || Response.error())
+    );
+});
+
+self.addEventListener('push', (event) => {
+    const { title, body } = event.data.json();
+    event.waitUntil(self.registration.showNotification(title, { body, icon: '/icon.svg' }));
+});
+
+self.addEventListener('notificationclick', (event) => {
+    event.notification.close();
+    event.waitUntil(clients.openWindow('/today'));
+});
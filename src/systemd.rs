@@ -0,0 +1,34 @@
+use std::{net::TcpListener, os::unix::io::FromRawFd, time::Duration};
+
+use sd_notify::NotifyState;
+use tokio::time::interval;
+
+// The first socket passed by systemd when the service is socket activated,
+// used instead of binding the port.
+pub fn listener() -> Result<Option<TcpListener>, String> {
+    let mut fds =
+        sd_notify::listen_fds().map_err(|err| format!("invalid systemd sockets: {err}"))?;
+    // The descriptors are handed to this process only, see sd_listen_fds(3).
+    Ok(fds.next().map(|fd| unsafe { TcpListener::from_raw_fd(fd) }))
+}
+
+// Tells systemd the server accepts requests, then keeps its watchdog fed
+// while the runtime is responsive. Nothing happens outside of systemd.
+pub fn notify_ready() {
+    if let Err(err) = sd_notify::notify(false, &[NotifyState::Ready]) {
+        eprintln!("systemd: failed to notify readiness: {err}");
+    }
+    let mut usec = 0;
+    if !sd_notify::watchdog_enabled(false, &mut usec) {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_micros(usec) / 2);
+        loop {
+            interval.tick().await;
+            if let Err(err) = sd_notify::notify(false, &[NotifyState::Watchdog]) {
+                eprintln!("systemd: failed to notify watchdog: {err}");
+            }
+        }
+    });
+}
@@ -0,0 +1,56 @@
+use std::env;
+
+use opentelemetry::{
+    runtime,
+    sdk::{trace, Resource},
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use serde::Deserialize;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+#[derive(Deserialize, Debug)]
+pub struct TelemetryConfig {
+    // OTLP gRPC collector, like "http://tempo:4317". `OTEL_EXPORTER_OTLP_ENDPOINT`
+    // otherwise.
+    pub endpoint: Option<String>,
+    // Shown as `service.name`, to tell instances apart.
+    #[serde(default = "TelemetryConfig::default_service_name")]
+    pub service_name: String,
+}
+
+impl TelemetryConfig {
+    fn default_service_name() -> String {
+        "ovr".to_owned()
+    }
+}
+
+// Exports the spans of requests and background jobs when an endpoint is set,
+// in the configuration or the environment.
+pub fn init(config: Option<&TelemetryConfig>) -> Result<(), String> {
+    let endpoint = config.and_then(|config| config.endpoint.clone());
+    if endpoint.is_none() && env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+        return Ok(());
+    }
+    let service_name = config
+        .map(|config| config.service_name.clone())
+        .unwrap_or_else(TelemetryConfig::default_service_name);
+
+    let mut exporter = opentelemetry_otlp::new_exporter().tonic();
+    if let Some(endpoint) = endpoint {
+        exporter = exporter.with_endpoint(endpoint);
+    }
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(
+            trace::config()
+                .with_resource(Resource::new([KeyValue::new("service.name", service_name)])),
+        )
+        .install_batch(runtime::Tokio)
+        .map_err(|err| format!("invalid telemetry configuration: {err}"))?;
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()
+        .map_err(|err| err.to_string())
+}
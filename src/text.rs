@@ -0,0 +1,56 @@
+// Of the answers, dish names being translated by the `Translator`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Language {
+    French,
+    English,
+}
+
+// How text answers are decorated, ANSI colors being only sent with
+// `?color=true` so pipes and scripts never get escape codes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum TextStyle {
+    Plain,
+    Ansi,
+}
+
+impl TextStyle {
+    // Bold yellow, for the date of today.
+    pub fn today(self, text: &str) -> String {
+        self.paint(text, "1;33")
+    }
+
+    // Bold, for the main courses.
+    pub fn main_course(self, text: &str) -> String {
+        self.paint(text, "1")
+    }
+
+    fn paint(self, text: &str, code: &str) -> String {
+        match self {
+            TextStyle::Plain => text.to_owned(),
+            TextStyle::Ansi => format!("\x1b[{code}m{text}\x1b[0m"),
+        }
+    }
+}
+
+pub trait TextRepresentable {
+    fn as_plain_text(&self, _human: bool) -> String {
+        String::new()
+    }
+
+    // The plain text with `style`, undecorated unless implemented.
+    fn as_styled_text(&self, human: bool, _style: TextStyle) -> String {
+        self.as_plain_text(human)
+    }
+
+    fn as_html(&self) -> String {
+        String::new()
+    }
+
+    // Aligned columns for terminals, see `?format=table`. The plain text
+    // when there's nothing to lay out.
+    fn as_table(&self) -> String {
+        self.as_plain_text(false)
+    }
+}
+
+impl TextRepresentable for () {}
@@ -0,0 +1,238 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::Deserialize;
+#[cfg(feature = "translation-api")]
+use serde::Serialize;
+#[cfg(feature = "translation-api")]
+use tokio::sync::RwLock;
+
+use crate::{config::Config, error::Error, text::Language};
+
+#[derive(Deserialize, Debug)]
+pub struct LanguageQuery {
+    lang: Option<String>,
+}
+
+impl LanguageQuery {
+    // Menus are written in French, which is also the default.
+    pub fn language(&self) -> Result<Language, Error> {
+        match self.lang.as_deref().map(str::trim) {
+            None | Some("" | "fr") => Ok(Language::French),
+            Some("en") => Ok(Language::English),
+            Some(_) => Err(Error::InvalidLanguage),
+        }
+    }
+}
+
+#[cfg(feature = "translation-api")]
+#[derive(Deserialize, Debug)]
+pub struct TranslationApiConfig {
+    // A LibreTranslate compatible server, like `https://libretranslate.com`.
+    pub url: String,
+    pub api_key: Option<String>,
+}
+
+// French to English dish names. Whole names from the glossary are used as
+// is, the others go to the translation API when there is one, and fall back
+// to translating the glossary words they contain.
+#[derive(Debug)]
+pub struct Translator {
+    glossary: HashMap<String, String>,
+    #[cfg(feature = "translation-api")]
+    api: Option<Api>,
+}
+
+impl Translator {
+    pub fn new(config: &Config) -> Result<Self, String> {
+        let glossary = match &config.translation_glossary {
+            Some(path) => read_glossary(path)?,
+            None => HashMap::new(),
+        };
+        Ok(Self {
+            glossary,
+            #[cfg(feature = "translation-api")]
+            api: config.translation_api.as_ref().map(Api::new),
+        })
+    }
+
+    // Translations of `texts`, by original text, for the ones that changed.
+    pub async fn translate(
+        &self,
+        language: Language,
+        texts: Vec<String>,
+    ) -> HashMap<String, String> {
+        if language == Language::French {
+            return HashMap::new();
+        }
+        let (mut translations, unknown) = texts.into_iter().fold(
+            (HashMap::new(), Vec::new()),
+            |(mut translations, mut unknown), text| {
+                match self.glossary.get(&text.to_lowercase()) {
+                    Some(translation) => {
+                        translations.insert(text, translation.clone());
+                    }
+                    None => unknown.push(text),
+                }
+                (translations, unknown)
+            },
+        );
+        #[cfg(feature = "translation-api")]
+        let unknown = match &self.api {
+            Some(api) => {
+                let (translated, unknown) = api.translate(unknown).await;
+                translations.extend(translated);
+                unknown
+            }
+            None => unknown,
+        };
+        for text in unknown {
+            let translation = self.translate_words(&text);
+            if translation != text {
+                translations.insert(text, translation);
+            }
+        }
+        translations
+    }
+
+    // Replaces the longest runs of words found in the glossary, keeping the
+    // other words untouched.
+    fn translate_words(&self, text: &str) -> String {
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let mut translated = Vec::with_capacity(words.len());
+        let mut start = 0;
+        while start < words.len() {
+            let found = (start + 1..=words.len()).rev().find_map(|end| {
+                let phrase = words[start..end].join(" ").to_lowercase();
+                Some((end, self.glossary.get(&phrase)?))
+            });
+            match found {
+                Some((end, translation)) => {
+                    translated.push(translation.clone());
+                    start = end;
+                }
+                None => {
+                    translated.push(words[start].to_owned());
+                    start += 1;
+                }
+            }
+        }
+        let translated = translated.join(" ");
+        match text.chars().next() {
+            Some(first) if first.is_uppercase() => capitalize(&translated),
+            _ => translated,
+        }
+    }
+}
+
+fn read_glossary(path: &Path) -> Result<HashMap<String, String>, String> {
+    let content = fs::read_to_string(path).map_err(|err| format!("invalid glossary: {err}"))?;
+    let glossary = toml::from_str::<HashMap<String, String>>(&content)
+        .map_err(|err| format!("invalid glossary: {err}"))?;
+    Ok(glossary
+        .into_iter()
+        .map(|(french, english)| (french.trim().to_lowercase(), english))
+        .collect())
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(feature = "translation-api")]
+#[derive(Debug)]
+struct Api {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    // Dish names come back every week, each one is only sent once.
+    cache: RwLock<HashMap<String, String>>,
+}
+
+#[cfg(feature = "translation-api")]
+impl Api {
+    fn new(config: &TranslationApiConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: format!("{}/translate", config.url.trim_end_matches('/')),
+            api_key: config.api_key.clone(),
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    // Translated texts, and the ones that couldn't be translated.
+    async fn translate(&self, texts: Vec<String>) -> (HashMap<String, String>, Vec<String>) {
+        let (mut translations, missing) = {
+            let cache = self.cache.read().await;
+            texts.into_iter().fold(
+                (HashMap::new(), Vec::new()),
+                |(mut translations, mut missing), text| {
+                    match cache.get(&text) {
+                        Some(translation) => {
+                            translations.insert(text, translation.clone());
+                        }
+                        None => missing.push(text),
+                    }
+                    (translations, missing)
+                },
+            )
+        };
+        if missing.is_empty() {
+            return (translations, missing);
+        }
+        match self.request(&missing).await {
+            Ok(translated) if translated.len() == missing.len() => {
+                let translated = missing.into_iter().zip(translated).collect::<Vec<_>>();
+                self.cache.write().await.extend(translated.iter().cloned());
+                translations.extend(translated);
+                (translations, Vec::new())
+            }
+            Ok(_) => {
+                eprintln!("translation api answered a different number of texts");
+                (translations, missing)
+            }
+            Err(err) => {
+                eprintln!("failed to translate dishes: {err}");
+                (translations, missing)
+            }
+        }
+    }
+
+    async fn request(&self, texts: &[String]) -> Result<Vec<String>, reqwest::Error> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            q: &'a [String],
+            source: &'static str,
+            target: &'static str,
+            format: &'static str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            api_key: Option<&'a str>,
+        }
+
+        #[derive(Deserialize)]
+        struct Response {
+            #[serde(rename = "translatedText")]
+            translated_text: Vec<String>,
+        }
+
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&Request {
+                q: texts,
+                source: "fr",
+                target: "en",
+                format: "text",
+                api_key: self.api_key.as_deref(),
+            })
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Response>()
+            .await?;
+        Ok(response.translated_text)
+    }
+}
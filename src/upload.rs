@@ -0,0 +1,471 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    async_trait,
+    body::{Body, Bytes},
+    extract::{FromRef, FromRequest, FromRequestParts, Multipart},
+    http::{header, request::Parts, HeaderValue, Request, StatusCode},
+    response::Response,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize, Serializer};
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task::{self, JoinHandle},
+    time::timeout,
+};
+
+use crate::{
+    catalogue::{sha256_hex, Catalogue, CatalogueUpdate, MergePolicy},
+    config::Config,
+    day::Day,
+    error::{Error, ParseError},
+    response::{ErrorFormat, TextRepresentable},
+    utils::escape,
+    week,
+};
+
+// Seconds rejected uploads are told to wait before trying again.
+const BUSY_RETRY_AFTER: u32 = 10;
+
+#[derive(Deserialize, Copy, Clone, Debug)]
+pub struct UploadOptions {
+    #[serde(default, rename = "async")]
+    pub asynchronous: bool,
+    #[serde(default)]
+    pub debug: bool,
+    #[serde(default)]
+    pub force: bool,
+    pub policy: Option<MergePolicy>,
+}
+
+// Limits the uploads parsed at once, `max_concurrent_uploads`, so a batch
+// import doesn't slow down reads on small hosts.
+#[derive(Debug)]
+pub struct UploadSlots {
+    semaphore: Option<Arc<Semaphore>>,
+    queue_timeout: Duration,
+}
+
+impl UploadSlots {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            semaphore: config
+                .max_concurrent_uploads
+                .map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            queue_timeout: Duration::from_secs(config.upload_queue_timeout),
+        }
+    }
+}
+
+// A place among the uploads being parsed, kept until the upload is done,
+// including asynchronous ones. Requests waiting longer than
+// `upload_queue_timeout` are rejected with a 503.
+#[derive(Debug)]
+pub struct UploadSlot(Option<OwnedSemaphorePermit>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for UploadSlot
+where
+    S: Send + Sync,
+    Arc<UploadSlots>: FromRef<S>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let slots = Arc::<UploadSlots>::from_ref(state);
+        let Some(semaphore) = &slots.semaphore else {
+            return Ok(UploadSlot(None));
+        };
+        match timeout(slots.queue_timeout, Arc::clone(semaphore).acquire_owned()).await {
+            Ok(Ok(permit)) => Ok(UploadSlot(Some(permit))),
+            _ => {
+                let mut response = ErrorFormat::loose(&parts.headers).respond(Error::Busy);
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from(BUSY_RETRY_AFTER));
+                Err(response)
+            }
+        }
+    }
+}
+
+// The files of an upload request, with its options and its slot.
+#[derive(Debug)]
+pub struct Upload {
+    pub files: Vec<UploadFile>,
+    pub options: UploadOptions,
+    pub slot: UploadSlot,
+}
+
+#[derive(Debug)]
+pub struct UploadFile {
+    name: Option<String>,
+    kind: FileKind,
+    data: Bytes,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum FileKind {
+    Json,
+    Pdf,
+    Csv,
+    Xlsx,
+    Ics,
+}
+
+impl FileKind {
+    fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type.split(';').next()?.trim() {
+            "application/json" => Some(FileKind::Json),
+            "application/pdf" => Some(FileKind::Pdf),
+            "text/csv" => Some(FileKind::Csv),
+            "text/calendar" => Some(FileKind::Ics),
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => {
+                Some(FileKind::Xlsx)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            FileKind::Json => "application/json",
+            FileKind::Pdf => "application/pdf",
+            FileKind::Csv => "text/csv",
+            FileKind::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            FileKind::Ics => "text/calendar",
+        }
+    }
+
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let (_, extension) = name.rsplit_once('.')?;
+        match extension.to_lowercase().as_str() {
+            "json" => Some(FileKind::Json),
+            "pdf" => Some(FileKind::Pdf),
+            "csv" => Some(FileKind::Csv),
+            "xlsx" => Some(FileKind::Xlsx),
+            "ics" => Some(FileKind::Ics),
+            _ => None,
+        }
+    }
+
+    pub fn parse(self, data: &[u8], config: &Config) -> Result<Vec<Day>, ParseError> {
+        match self {
+            FileKind::Json => week::parse_json(data, &config.parser),
+            FileKind::Pdf => week::parse_pdf(data, &config.parser),
+            FileKind::Csv => week::parse_csv(data, &config.parser),
+            FileKind::Xlsx => week::parse_xlsx(data, &config.parser),
+            FileKind::Ics => week::parse_ics(data, &config.parser),
+        }
+    }
+}
+
+impl UploadFile {
+    pub async fn read_all(request: Request<Body>) -> Result<Vec<Self>, Error> {
+        let content_type = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|h| h.to_str().ok())
+            .map(ToOwned::to_owned);
+        if !content_type
+            .as_deref()
+            .is_some_and(|h| h.starts_with("multipart/form-data"))
+        {
+            let data = Bytes::from_request(request, &())
+                .await
+                .map_err(|_| Error::InvalidBody)?;
+            return Ok(vec![Self {
+                name: None,
+                kind: content_type
+                    .as_deref()
+                    .and_then(FileKind::from_content_type)
+                    .unwrap_or(FileKind::Pdf),
+                data,
+            }]);
+        }
+
+        let mut multipart = Multipart::from_request(request, &())
+            .await
+            .map_err(|_| Error::InvalidBody)?;
+        let mut files = Vec::new();
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|_| Error::InvalidBody)?
+        {
+            let name = field.file_name().or(field.name()).map(ToOwned::to_owned);
+            let content_type = field
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|h| h.to_str().ok());
+            let kind = content_type
+                .and_then(FileKind::from_content_type)
+                .or_else(|| name.as_deref().and_then(FileKind::from_file_name))
+                .unwrap_or(match content_type {
+                    Some("application/octet-stream") => FileKind::Json,
+                    _ => FileKind::Pdf,
+                });
+            files.push(Self {
+                name,
+                kind,
+                data: field.bytes().await.map_err(|_| Error::InvalidBody)?,
+            });
+        }
+        Ok(files)
+    }
+
+    // For documents fetched by the server itself.
+    pub fn new(name: Option<String>, kind: FileKind, data: Bytes) -> Self {
+        Self { name, kind, data }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn parse(&self, config: &Config) -> Result<Vec<Day>, ParseError> {
+        self.kind.parse(&self.data, config)
+    }
+
+    // Parses on the blocking threads, giving the file back with its days.
+    pub fn spawn_parse(
+        self,
+        config: Arc<Config>,
+    ) -> JoinHandle<(Self, Result<Vec<Day>, ParseError>)> {
+        task::spawn_blocking(move || {
+            let days = self.parse(&config);
+            (self, days)
+        })
+    }
+
+    // Recognizes documents uploaded again.
+    pub fn hash(&self) -> String {
+        sha256_hex(&self.data)
+    }
+}
+
+// A file of an upload, being parsed alongside the other files unless it was
+// uploaded before as is.
+pub struct PendingFile {
+    pub name: Option<String>,
+    pub hash: String,
+    // `None` for unchanged files.
+    pub parsing: Option<JoinHandle<(UploadFile, Result<Vec<Day>, ParseError>)>>,
+}
+
+impl PendingFile {
+    // Starts parsing every changed file at once, bulk uploads taking as long
+    // as their largest document rather than all of them.
+    pub fn start_all(
+        files: Vec<UploadFile>,
+        catalogue: &Catalogue,
+        config: &Arc<Config>,
+    ) -> Vec<Self> {
+        files
+            .into_iter()
+            .map(|file| {
+                let name = file.name.clone();
+                let hash = file.hash();
+                let parsing = (!catalogue.is_unchanged(&hash))
+                    .then(|| file.spawn_parse(Arc::clone(config)));
+                Self {
+                    name,
+                    hash,
+                    parsing,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct UploadReport {
+    files: Vec<FileReport>,
+    // Path prefix of the site, see `Site::path_prefix`.
+    root: String,
+}
+
+impl UploadReport {
+    pub fn new(root: String) -> Self {
+        Self {
+            files: Vec::new(),
+            root,
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        name: Option<String>,
+        result: Result<CatalogueUpdate, ParseError>,
+        debug: bool,
+    ) {
+        let (result, diagnostics) = match result {
+            Ok(update) => (Ok(update), None),
+            Err(err) => (Err(err.error()), debug.then_some(err)),
+        };
+        self.files.push(FileReport {
+            name,
+            result,
+            diagnostics,
+        });
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        if self.files.iter().any(|file| file.result.is_err()) {
+            StatusCode::MULTI_STATUS
+        } else if self.update().has_blocked_conflicts() {
+            StatusCode::CONFLICT
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    // A lone failing file is reported as a plain error, like before per-file
+    // results existed, unless diagnostics were asked for.
+    pub fn into_result(self) -> Result<Self, Error> {
+        match self.files.as_slice() {
+            [FileReport {
+                result: Err(err),
+                diagnostics: None,
+                ..
+            }] => Err(err.clone()),
+            _ => Ok(self),
+        }
+    }
+
+    fn update(&self) -> CatalogueUpdate {
+        let mut update = CatalogueUpdate::default();
+        for file in &self.files {
+            if let Ok(file_update) = &file.result {
+                update += file_update.clone();
+            }
+        }
+        update.unchanged = !self.files.is_empty()
+            && self
+                .files
+                .iter()
+                .all(|file| file.result.as_ref().is_ok_and(|update| update.unchanged));
+        update
+    }
+}
+
+impl Serialize for UploadReport {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Report<'a> {
+            #[serde(flatten)]
+            update: CatalogueUpdate,
+            files: &'a [FileReport],
+        }
+
+        Report {
+            update: self.update(),
+            files: &self.files,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl TextRepresentable for UploadReport {
+    fn as_plain_text(&self, human: bool) -> String {
+        let mut text = self.update().as_plain_text(human);
+        let failures = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(index, file)| {
+                file.result
+                    .as_ref()
+                    .err()
+                    .map(|err| format!("{}: {err}", file.display_name(index)))
+            })
+            .join("\n");
+        if !failures.is_empty() {
+            if !text.is_empty() {
+                text += "\n\n";
+            }
+            text += "Failed:\n";
+            text += &failures;
+        }
+        text
+    }
+
+    fn as_html(&self) -> String {
+        let failures = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(index, file)| {
+                file.result.as_ref().err().map(|err| {
+                    format!(
+                        r#"<div class="change failed">{} : {}</div>"#,
+                        escape(&file.display_name(index)),
+                        escape(&err.as_plain_text(false))
+                    )
+                })
+            })
+            .collect::<String>();
+        let mut html = self.update().as_html_in(&self.root);
+        if !failures.is_empty() {
+            html += &format!(
+                r#"<div class="update"><div class="update-title">Échecs</div>{failures}</div>"#
+            );
+        }
+        html
+    }
+}
+
+#[derive(Clone, Debug)]
+struct FileReport {
+    name: Option<String>,
+    result: Result<CatalogueUpdate, Error>,
+    diagnostics: Option<ParseError>,
+}
+
+impl FileReport {
+    fn display_name(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("#{}", index + 1))
+    }
+}
+
+impl Serialize for FileReport {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct File<'a> {
+            name: Option<&'a str>,
+            success: bool,
+            #[serde(flatten)]
+            update: Option<&'a CatalogueUpdate>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            error: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            diagnostics: Option<&'a ParseError>,
+        }
+
+        File {
+            name: self.name.as_deref(),
+            success: self.result.is_ok(),
+            update: self.result.as_ref().ok(),
+            error: self.result.as_ref().err().map(ToString::to_string),
+            diagnostics: self.diagnostics.as_ref(),
+        }
+        .serialize(serializer)
+    }
+}
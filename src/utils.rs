@@ -1,55 +1,98 @@
 use std::sync::OnceLock;
 
 use time::{
-    format_description, format_description::FormatItem, macros::offset, util::days_in_year_month,
-    Date, Duration, Month, OffsetDateTime, PrimitiveDateTime,
+    format_description, format_description::FormatItem, Date, Month, OffsetDateTime,
+    PrimitiveDateTime, Time, UtcOffset,
 };
+use time_tz::{timezones, OffsetDateTimeExt, TimeZone, Tz};
+
+pub const DEFAULT_TIMEZONE: &str = "Europe/Paris";
 
 static FORMATTER: OnceLock<Vec<FormatItem<'static>>> = OnceLock::new();
-static ICS_FORMATTER: OnceLock<Vec<FormatItem<'static>>> = OnceLock::new();
+static TIMEZONE: OnceLock<&'static Tz> = OnceLock::new();
 
-pub fn now_local() -> OffsetDateTime {
-    OffsetDateTime::now_local().unwrap_or_else(|_| {
-        let now = OffsetDateTime::now_utc().to_offset(offset!(+1));
-        let start = last_sunday_of_month(now.date(), Month::March)
-            .with_hms(2, 0, 0)
-            .expect("failed to calculate local date")
-            .assume_offset(offset!(+1));
-        let end = last_sunday_of_month(now.date(), Month::October)
-            .with_hms(3, 0, 0)
-            .expect("failed to calculate local date")
-            .assume_offset(offset!(+2));
-
-        let offset = if (start..end).contains(&now) {
-            offset!(+2)
-        } else {
-            offset!(+1)
-        };
-        now.to_offset(offset)
+// Must be called before the first date computation, later calls fail.
+pub fn set_timezone(name: &str) -> Result<(), String> {
+    let timezone =
+        timezones::get_by_name(name).ok_or_else(|| format!("unknown timezone {name}"))?;
+    TIMEZONE
+        .set(timezone)
+        .map_err(|_| "timezone already set".to_owned())
+}
+
+fn timezone() -> &'static Tz {
+    TIMEZONE.get_or_init(|| {
+        timezones::get_by_name(DEFAULT_TIMEZONE).expect("default timezone not found")
     })
 }
 
-fn last_sunday_of_month(date: Date, month: Month) -> Date {
-    let month_end =
-        Date::from_calendar_date(date.year(), month, days_in_year_month(date.year(), month))
-            .expect("failed to calculate local date");
-    month_end.saturating_sub(Duration::days((month_end.weekday() as i64 + 1) % 7))
+pub fn timezone_name() -> &'static str {
+    timezone().name()
 }
 
+pub fn now_local() -> OffsetDateTime {
+    OffsetDateTime::now_utc().to_timezone(timezone())
+}
+
+// Wall-clock `time` on `date` in the configured timezone. Offsets only change
+// at night, so the one at the same UTC time is good enough.
+pub fn local_datetime(date: Date, time: Time) -> OffsetDateTime {
+    let datetime = date.with_time(time);
+    datetime.assume_offset(datetime.assume_utc().to_timezone(timezone()).offset())
+}
+
+// Formatted by hand, unlike with a format description, it can't fail on
+// dates far in the past or the future.
 pub fn format_date(date: Date) -> String {
-    date.format(FORMATTER.get_or_init(|| {
-        format_description::parse("[year]-[month]-[day]").expect("invalid date formatter")
-    }))
-    .expect("date formatting failed")
+    format!(
+        "{:04}-{:02}-{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
 }
 
 pub fn format_icalendar_date(datetime: PrimitiveDateTime) -> String {
-    datetime
-        .format(ICS_FORMATTER.get_or_init(|| {
-            format_description::parse("[year][month][day]T[hour][minute][second]")
-                .expect("invalid icalendar date formatter")
-        }))
-        .expect("ics datetime formatting failed")
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}",
+        datetime.year(),
+        u8::from(datetime.month()),
+        datetime.day(),
+        datetime.hour(),
+        datetime.minute(),
+        datetime.second()
+    )
+}
+
+pub fn format_icalendar_utc(datetime: OffsetDateTime) -> String {
+    let datetime = datetime.to_offset(UtcOffset::UTC);
+    format_icalendar_date(PrimitiveDateTime::new(datetime.date(), datetime.time())) + "Z"
+}
+
+// Accepts "13", "13:30" or "13h30".
+pub fn parse_time(input: &str) -> Option<Time> {
+    let (hour, minute) = input.split_once([':', 'h']).unwrap_or((input, ""));
+    let minute = match minute.trim() {
+        "" => 0,
+        minute => minute.parse().ok()?,
+    };
+    Time::from_hms(hour.trim().parse().ok()?, minute, 0).ok()
+}
+
+// ISO week like "2023-24", or "current", as a year and a week number.
+pub fn parse_week(input: &str) -> Option<(i32, u8)> {
+    if input == "current" {
+        let (year, week, _) = now_local().date().to_iso_week_date();
+        return Some((year, week));
+    }
+    let (year, week) = input.split_once('-')?;
+    Some((year.parse().ok()?, week.parse().ok()?))
+}
+
+// Month like "2023-06", as a year and a month.
+pub fn parse_month(input: &str) -> Option<(i32, Month)> {
+    let (year, month) = input.split_once('-')?;
+    Some((year.parse().ok()?, Month::try_from(month.parse::<u8>().ok()?).ok()?))
 }
 
 pub fn parse_date(input: &str) -> Option<Date> {
@@ -61,3 +104,12 @@ pub fn parse_date(input: &str) -> Option<Date> {
     )
     .ok()
 }
+
+// For user text put in HTML or XML, in elements and quoted attributes.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use axum::{async_trait, body::Bytes};
+use itertools::Itertools;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use time::{macros::format_description, Date, Weekday};
+
+use crate::{
+    ingestion::{Ingester, IngestionSource},
+    upload::{FileKind, UploadFile},
+    utils::now_local,
+};
+
+// Days missing upstream before the fetch stops, as the API has no listing.
+const ALLOWED_MISSING_DAYS: u32 = 5;
+
+#[derive(Deserialize, Debug)]
+pub struct VendorApiConfig {
+    // Root URL of the catering company's API, days being at `/api/day/DATE`.
+    pub url: String,
+    // Name of the site the menus are added to, the default one otherwise.
+    pub site: Option<String>,
+    #[serde(default = "VendorApiConfig::default_interval")]
+    pub interval: u64,
+    // Fields of a day holding its dishes, in the order of the categories.
+    #[serde(default = "VendorApiConfig::default_categories")]
+    pub categories: Vec<String>,
+    // Days fetched per week from Monday, 6 for sites open on Saturday.
+    #[serde(default = "VendorApiConfig::default_weekdays")]
+    pub weekdays: u8,
+}
+
+impl VendorApiConfig {
+    fn default_interval() -> u64 {
+        3600
+    }
+
+    fn default_categories() -> Vec<String> {
+        [
+            "starters_without_usual",
+            "mains",
+            "sides",
+            "cheeses_without_usual",
+            "desserts_without_usual",
+        ]
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .collect()
+    }
+
+    fn default_weekdays() -> u8 {
+        5
+    }
+}
+
+// A row of the JSON upload format, the date followed by the dishes of
+// `categories`. Missing fields are empty categories.
+fn row(mut day: Map<String, Value>, categories: &[String]) -> Option<Vec<String>> {
+    let Some(Value::String(date)) = day.remove("date") else {
+        return None;
+    };
+    let dishes = categories
+        .iter()
+        .filter_map(|category| match day.remove(category) {
+            Some(Value::Array(dishes)) => Some(dishes),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|dish| match dish {
+            Value::String(dish) => Some(dish),
+            _ => None,
+        });
+    Some(
+        [date]
+            .into_iter()
+            .chain(dishes)
+            .filter(|dish| !dish.eq_ignore_ascii_case("Poisson selon arrivage"))
+            .unique()
+            .collect(),
+    )
+}
+
+// Fetches the open days from the current week on, one request per day, and
+// adds them as a single JSON document.
+pub struct VendorApi {
+    url: String,
+    site: Option<String>,
+    interval: Duration,
+    categories: Vec<String>,
+    weekdays: u8,
+    client: Client,
+}
+
+impl VendorApi {
+    pub fn new(config: &VendorApiConfig) -> Self {
+        Self {
+            url: config.url.trim_end_matches('/').to_owned(),
+            site: config.site.clone(),
+            interval: Duration::from_secs(config.interval),
+            categories: config.categories.clone(),
+            weekdays: config.weekdays.clamp(1, 7),
+            client: Client::new(),
+        }
+    }
+
+    // `None` when the API has no menu for `date`, which it answers with any
+    // error status.
+    async fn day(&self, date: Date) -> Result<Option<Map<String, Value>>, reqwest::Error> {
+        let date = date
+            .format(format_description!("[year]-[month]-[day]"))
+            .expect("date formatting failed");
+        let response = self
+            .client
+            .get(format!("{}/api/day/{date}", self.url))
+            .send()
+            .await?;
+        if response.status() != StatusCode::OK {
+            return Ok(None);
+        }
+        response.json().await.map(Some)
+    }
+}
+
+#[async_trait]
+impl IngestionSource for VendorApi {
+    fn name(&self) -> String {
+        format!("api {}", self.url)
+    }
+
+    fn site(&self) -> Option<&str> {
+        self.site.as_deref()
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn poll(&mut self, ingester: &Ingester) -> Result<(), String> {
+        let today = now_local().date();
+        let mut week_start = if today.weekday() == Weekday::Monday {
+            today
+        } else {
+            today.prev_occurrence(Weekday::Monday)
+        };
+
+        let mut allowed_missing = ALLOWED_MISSING_DAYS;
+        let mut rows = Vec::new();
+        while allowed_missing > 0 {
+            for offset in 0..self.weekdays {
+                let date = week_start + time::Duration::days(offset.into());
+                match self.day(date).await {
+                    Ok(Some(day)) => match row(day, &self.categories) {
+                        Some(row) => rows.push(row),
+                        None => return Err(format!("day {date} has no date")),
+                    },
+                    Ok(None) => allowed_missing = allowed_missing.saturating_sub(1),
+                    Err(err) => return Err(format!("failed to fetch {date}: {err}")),
+                }
+            }
+            week_start = week_start.next_occurrence(Weekday::Monday);
+        }
+
+        // Only the days that are new or changed upstream, so unchanged days
+        // aren't reported as replaced on every poll.
+        let mut changed = Vec::new();
+        for row in rows {
+            let data = serde_json::to_vec(&[&row]).expect("row serialization failed");
+            let days = FileKind::Json
+                .parse(&data, ingester.config())
+                .map_err(|err| format!("invalid day {}: {}", row[0], err.error()))?;
+            let mut current = true;
+            for day in &days {
+                current &= ingester.contains(day).await;
+            }
+            if !current {
+                changed.push(row);
+            }
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        let data = serde_json::to_vec(&changed).expect("rows serialization failed");
+        // Failures are in the audit log.
+        let _ = ingester
+            .ingest(UploadFile::new(
+                Some("days.json".to_owned()),
+                FileKind::Json,
+                Bytes::from(data),
+            ))
+            .await;
+        Ok(())
+    }
+}
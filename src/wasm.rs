@@ -0,0 +1,27 @@
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    error::ParseError,
+    week::{self, ParserConfig},
+};
+
+// Parses a PDF menu with the default configuration, like the server would for
+// an upload. Returns the days as JSON, or throws the `ParseError` as JSON.
+#[wasm_bindgen]
+pub fn parse_pdf(bytes: &[u8]) -> Result<String, JsValue> {
+    to_json(week::parse_pdf(bytes, &ParserConfig::default()))
+}
+
+#[wasm_bindgen]
+pub fn parse_json(bytes: &[u8]) -> Result<String, JsValue> {
+    to_json(week::parse_json(bytes, &ParserConfig::default()))
+}
+
+fn to_json<T: serde::Serialize>(result: Result<T, ParseError>) -> Result<String, JsValue> {
+    match result {
+        Ok(days) => Ok(serde_json::to_string(&days).expect("days serialization failed")),
+        Err(err) => Err(JsValue::from_str(
+            &serde_json::to_string(&err).expect("parse error serialization failed"),
+        )),
+    }
+}
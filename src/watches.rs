@@ -0,0 +1,160 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use lettre::Address;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{error::Error, notifier::WebhookKind, push::PushKeys, response::TextRepresentable};
+
+// Dishes people asked to be told about, saved to `watches_file` on every
+// change.
+#[derive(Debug)]
+pub struct Watches {
+    watches: Vec<Watch>,
+    path: Option<PathBuf>,
+}
+
+impl Watches {
+    pub fn open(path: Option<&Path>) -> Result<Self, String> {
+        let watches = match path.map(fs::read_to_string) {
+            Some(Ok(content)) => serde_json::from_str(&content)
+                .map_err(|err| format!("invalid watches file: {err}"))?,
+            Some(Err(err)) if err.kind() != ErrorKind::NotFound => return Err(err.to_string()),
+            _ => Vec::new(),
+        };
+        Ok(Self {
+            watches,
+            path: path.map(ToOwned::to_owned),
+        })
+    }
+
+    pub fn add(
+        &mut self,
+        request: WatchRequest,
+        email_enabled: bool,
+        push_enabled: bool,
+    ) -> Result<Watch, Error> {
+        let dish = request.dish.trim();
+        let valid_target = match &request.target {
+            WatchTarget::Webhook { url, .. } | WatchTarget::Ntfy { url, .. } => !url.is_empty(),
+            WatchTarget::Email { address } => email_enabled && address.parse::<Address>().is_ok(),
+            WatchTarget::WebPush { endpoint, .. } => push_enabled && !endpoint.is_empty(),
+        };
+        if dish.is_empty() || !valid_target {
+            return Err(Error::InvalidWatch);
+        }
+
+        let watch = Watch {
+            id: Uuid::new_v4().to_string(),
+            dish: dish.to_owned(),
+            site: request.site,
+            target: request.target,
+        };
+        self.watches.push(watch.clone());
+        self.save()?;
+        Ok(watch)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<Watch, Error> {
+        let index = self
+            .watches
+            .iter()
+            .position(|watch| watch.id == id)
+            .ok_or(Error::WatchNotFound)?;
+        let watch = self.watches.remove(index);
+        self.save()?;
+        Ok(watch)
+    }
+
+    pub fn list(&self) -> WatchList {
+        WatchList {
+            watches: self.watches.clone(),
+        }
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let content = serde_json::to_string(&self.watches).expect("watches serialization failed");
+        fs::write(path, content).map_err(|err| {
+            eprintln!("failed to save watches: {err}");
+            Error::Internal
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Watch {
+    pub id: String,
+    pub dish: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+    pub target: WatchTarget,
+}
+
+impl Watch {
+    pub fn matches(&self, dish: &str) -> bool {
+        dish.to_lowercase().contains(&self.dish.to_lowercase())
+    }
+}
+
+impl TextRepresentable for Watch {
+    fn as_plain_text(&self, _human: bool) -> String {
+        format!("{} : {}", self.id, self.dish)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WatchTarget {
+    Webhook {
+        url: String,
+        #[serde(default)]
+        kind: WebhookKind,
+    },
+    Ntfy {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    Email {
+        address: String,
+    },
+    WebPush {
+        endpoint: String,
+        keys: PushKeys,
+    },
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WatchRequest {
+    pub dish: String,
+    pub site: Option<String>,
+    pub target: WatchTarget,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WatchList {
+    watches: Vec<Watch>,
+}
+
+impl WatchList {
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+}
+
+impl TextRepresentable for WatchList {
+    fn as_plain_text(&self, human: bool) -> String {
+        self.watches
+            .iter()
+            .map(|watch| watch.as_plain_text(human))
+            .join("\n")
+    }
+}
@@ -0,0 +1,151 @@
+use std::{collections::HashSet, time::Duration};
+
+use axum::{async_trait, body::Bytes};
+use regex::Regex;
+use reqwest::{Client, Url};
+use scraper::{Html, Selector};
+use serde::Deserialize;
+
+use crate::{
+    ingestion::{Ingester, IngestionSource},
+    upload::{FileKind, UploadFile},
+};
+
+#[derive(Deserialize, Debug)]
+pub struct ScraperConfig {
+    // Page listing the menus, like "https://intranet.example.com/cantine".
+    pub url: String,
+    // Elements whose `href` are candidate links.
+    #[serde(default = "ScraperConfig::default_selector")]
+    pub selector: String,
+    // Matched against the absolute URL of each link.
+    #[serde(default = "ScraperConfig::default_pattern")]
+    pub pattern: String,
+    // Name of the site the menus are added to, the default one otherwise.
+    pub site: Option<String>,
+    #[serde(default = "ScraperConfig::default_interval")]
+    pub interval: u64,
+}
+
+impl ScraperConfig {
+    fn default_selector() -> String {
+        "a[href]".to_owned()
+    }
+
+    fn default_pattern() -> String {
+        r"(?i)\.pdf$".to_owned()
+    }
+
+    fn default_interval() -> u64 {
+        3600
+    }
+}
+
+// Loads a page listing the menus and adds the documents of links that weren't
+// seen before.
+pub struct Scraper {
+    url: Url,
+    selector: Selector,
+    pattern: Regex,
+    site: Option<String>,
+    interval: Duration,
+    client: Client,
+    seen: HashSet<Url>,
+}
+
+impl Scraper {
+    pub fn new(config: &ScraperConfig) -> Result<Self, String> {
+        Ok(Self {
+            url: Url::parse(&config.url)
+                .map_err(|err| format!("invalid scraper url {}: {err}", config.url))?,
+            selector: Selector::parse(&config.selector)
+                .map_err(|_| format!("invalid scraper selector {}", config.selector))?,
+            pattern: Regex::new(&config.pattern)
+                .map_err(|err| format!("invalid scraper pattern {}: {err}", config.pattern))?,
+            site: config.site.clone(),
+            interval: Duration::from_secs(config.interval),
+            client: Client::new(),
+            seen: HashSet::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl IngestionSource for Scraper {
+    fn name(&self) -> String {
+        format!("scraper {}", self.url)
+    }
+
+    fn site(&self) -> Option<&str> {
+        self.site.as_deref()
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    async fn poll(&mut self, ingester: &Ingester) -> Result<(), String> {
+        let page = fetch(&self.client, self.url.clone())
+            .await
+            .map_err(|err| format!("failed to load {}: {err}", self.url))?;
+        let mut failures = Vec::new();
+        for link in links(self, &String::from_utf8_lossy(&page)) {
+            if self.seen.contains(&link) {
+                continue;
+            }
+            let data = match fetch(&self.client, link.clone()).await {
+                Ok(data) => data,
+                Err(err) => {
+                    failures.push(format!("failed to download {link}: {err}"));
+                    continue;
+                }
+            };
+            let name = link
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .filter(|name| !name.is_empty())
+                .map(ToOwned::to_owned);
+            let kind = name
+                .as_deref()
+                .and_then(FileKind::from_file_name)
+                .unwrap_or(FileKind::Pdf);
+            // Failures are in the audit log.
+            let _ = ingester.ingest(UploadFile::new(name, kind, data)).await;
+            self.seen.insert(link);
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures.join(", "))
+        }
+    }
+}
+
+// Absolute URLs of the matching links, in the page order.
+fn links(scraper: &Scraper, page: &str) -> Vec<Url> {
+    let document = Html::parse_document(page);
+    let mut links = Vec::new();
+    for element in document.select(&scraper.selector) {
+        let Some(link) = element
+            .value()
+            .attr("href")
+            .and_then(|href| scraper.url.join(href.trim()).ok())
+        else {
+            continue;
+        };
+        if scraper.pattern.is_match(link.as_str()) && !links.contains(&link) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+async fn fetch(client: &Client, url: Url) -> Result<Bytes, reqwest::Error> {
+    client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await
+}
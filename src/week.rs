@@ -1,72 +1,347 @@
 use std::{
+    io::Cursor,
     mem,
     ops::{AddAssign, Range},
 };
 
+use calamine::{DataType, Reader, Xlsx};
 use itertools::Itertools;
 use lopdf::{Document, Object};
-use pdf_extract::HTMLOutput;
-use regex::Regex;
+use pdf_extract::{MediaBox, OutputDev, OutputError, Transform};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::{Date, Duration, Month};
 
-use crate::{day::Day, error::Error};
+#[cfg(feature = "ocr")]
+use crate::ocr;
+use crate::{
+    day::{Day, DishCategory},
+    error::{ParseError, ParseStage},
+    normalize::NormalizationConfig,
+    schema,
+    utils::format_date,
+};
 
 const MAIN_CONTENT_AREA: Range<u32> = 120..525;
-const CATEGORIES_AREAS: &[(DocumentDimensions, &[Range<u32>])] = &[
-    (
-        DocumentDimensions {
-            width: 792,
-            height: 612,
-        },
-        &[(136..166), (196..226), (296..326), (376..406), (416..446)],
-    ),
-    (
-        DocumentDimensions {
-            width: 841,
-            height: 595,
-        },
-        &[(139..169), (197..227), (293..323), (370..400), (408..438)],
-    ),
-];
 const EXPECTED_CHAR_WIDTH: u32 = 4;
 const COLUMN_ALLOWED_DRIFT: u32 = 30;
 const MULTILINE_DISH_MAX_DISTANCE: u32 = 15;
+const RUN_CONTINUATION_TOLERANCE: f64 = 0.5;
 
-pub fn parse_json(json_data: &[u8]) -> Result<Vec<Day>, Error> {
-    serde_json::from_slice::<Vec<Vec<String>>>(json_data).map_err(|_| Error::InvalidJson)?
-        .into_iter()
-        .filter_map(|f| Day::new(f).transpose())
-        .collect::<Result<Vec<_>, _>>()
+// The part of the configuration used by the parsers, which also build
+// without the server.
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct ParserConfig {
+    pub layouts: Vec<LayoutProfile>,
+    pub notice_keywords: Vec<String>,
+    pub normalization: NormalizationConfig,
+    #[cfg(feature = "ocr")]
+    pub ocr: bool,
 }
 
-pub fn parse_pdf(pdf_data: &[u8]) -> Result<Vec<Day>, Error> {
-    let document = Document::load_mem(pdf_data).map_err(|_| Error::InvalidPdf)?;
-    let mut out_buffer = Vec::new();
-    let mut parser = HTMLOutput::new(&mut out_buffer);
-    pdf_extract::output_doc(&document, &mut parser).map_err(|_| Error::InvalidPdf)?;
-
-    let html = String::from_utf8(out_buffer)
-        .map_err(|_| Error::Internal)?
-        .replace("&nbsp;", " ");
-    let div_regex = Regex::new(r#"<div style='(.+?)'>(.+?)</div>"#).map_err(|_| Error::Internal)?;
-    let top_regex = Regex::new(r#"top:\s?(\d+)(?:\.\d+)?px"#).map_err(|_| Error::Internal)?;
-    let left_regex = Regex::new(r#"left:\s?(\d+)(?:\.\d+)?px"#).map_err(|_| Error::Internal)?;
-
-    let categories = DocumentDimensions::new(&document)?.categories_area();
-    let mut divs = div_regex
-        .captures_iter(&html)
-        .filter_map(|capture| {
-            let style = &capture[1];
-            if style.contains("color: red") {
-                return None;
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            layouts: LayoutProfile::builtin(),
+            notice_keywords: ["fermé", "fermeture", "férié", "menu de fête", "repas de fête"]
+                .into_iter()
+                .map(ToOwned::to_owned)
+                .collect(),
+            normalization: NormalizationConfig::default(),
+            #[cfg(feature = "ocr")]
+            ocr: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct LayoutProfile {
+    width: u32,
+    height: u32,
+    #[serde(default = "LayoutProfile::default_main_content_area")]
+    main_content_area: Range<u32>,
+    categories_areas: Vec<Range<u32>>,
+    #[serde(default = "LayoutProfile::default_expected_char_width")]
+    expected_char_width: u32,
+    #[serde(default = "LayoutProfile::default_column_allowed_drift")]
+    column_allowed_drift: u32,
+    #[serde(default = "LayoutProfile::default_multiline_dish_max_distance")]
+    multiline_dish_max_distance: u32,
+}
+
+impl LayoutProfile {
+    pub fn builtin() -> Vec<Self> {
+        vec![
+            Self::with_categories(
+                792,
+                612,
+                vec![(136..166), (196..226), (296..326), (376..406), (416..446)],
+            ),
+            Self::with_categories(
+                841,
+                595,
+                vec![(139..169), (197..227), (293..323), (370..400), (408..438)],
+            ),
+        ]
+    }
+
+    fn with_categories(width: u32, height: u32, categories_areas: Vec<Range<u32>>) -> Self {
+        Self {
+            width,
+            height,
+            main_content_area: MAIN_CONTENT_AREA,
+            categories_areas,
+            expected_char_width: EXPECTED_CHAR_WIDTH,
+            column_allowed_drift: COLUMN_ALLOWED_DRIFT,
+            multiline_dish_max_distance: MULTILINE_DISH_MAX_DISTANCE,
+        }
+    }
+
+    // Each category area is the header above its dishes.
+    fn category(&self, top: u32) -> Option<DishCategory> {
+        let headers_above = self
+            .categories_areas
+            .iter()
+            .filter(|area| area.end <= top)
+            .count();
+        DishCategory::from_index(headers_above.checked_sub(1)?)
+    }
+
+    // Exact dimensions first, then the profile with the closest page size.
+    fn select(profiles: &[Self], dimensions: DocumentDimensions) -> Option<&Self> {
+        profiles.iter().min_by_key(|profile| {
+            profile.width.abs_diff(dimensions.width) + profile.height.abs_diff(dimensions.height)
+        })
+    }
+
+    fn default_main_content_area() -> Range<u32> {
+        MAIN_CONTENT_AREA
+    }
+
+    const fn default_expected_char_width() -> u32 {
+        EXPECTED_CHAR_WIDTH
+    }
+
+    const fn default_column_allowed_drift() -> u32 {
+        COLUMN_ALLOWED_DRIFT
+    }
+
+    const fn default_multiline_dish_max_distance() -> u32 {
+        MULTILINE_DISH_MAX_DISTANCE
+    }
+}
+
+// Accepts rows of strings like the CSV uploads, or a list of days (alone or
+// as the `days` of a catalogue) following the Day schema.
+pub fn parse_json(json_data: &[u8], config: &ParserConfig) -> Result<Vec<Day>, ParseError> {
+    let value = serde_json::from_slice::<Value>(json_data)
+        .map_err(|_| ParseError::new(ParseStage::Json))?;
+    if let Ok(rows) = Vec::<Vec<String>>::deserialize(&value) {
+        return parse_rows(rows, config, ParseStage::Date);
+    }
+
+    let days = match value {
+        Value::Object(mut object) => object.remove("days").unwrap_or(Value::Null),
+        value => value,
+    };
+    let Value::Array(days) = days else {
+        return Err(ParseError::new(ParseStage::Json));
+    };
+    let violations = days
+        .iter()
+        .enumerate()
+        .flat_map(|(index, day)| {
+            schema::validate_day(day)
+                .into_iter()
+                .map(move |violation| format!("/{index}{violation}"))
+        })
+        .collect_vec();
+    if !violations.is_empty() {
+        return Err(ParseError {
+            violations,
+            ..ParseError::new(ParseStage::Schema)
+        });
+    }
+    days.into_iter()
+        .map(|day| {
+            let mut day = serde_json::from_value::<Day>(day)
+                .map_err(|_| ParseError::new(ParseStage::Date))?;
+            clean_up(&mut day, config);
+            Ok(day)
+        })
+        .collect()
+}
+
+pub fn parse_csv(csv_data: &[u8], config: &ParserConfig) -> Result<Vec<Day>, ParseError> {
+    let rows = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(csv_data)
+        .into_records()
+        .map(|record| {
+            record
+                .map(|record| record.iter().map(ToOwned::to_owned).collect_vec())
+                .map_err(|_| ParseError::new(ParseStage::Csv))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    parse_rows(rows, config, ParseStage::Csv)
+}
+
+pub fn parse_xlsx(xlsx_data: &[u8], config: &ParserConfig) -> Result<Vec<Day>, ParseError> {
+    let mut workbook = Xlsx::new(Cursor::new(xlsx_data))
+        .map_err(|_| ParseError::new(ParseStage::Xlsx))?;
+    let sheet = workbook
+        .worksheet_range_at(0)
+        .ok_or(ParseError::new(ParseStage::Xlsx))?
+        .map_err(|_| ParseError::new(ParseStage::Xlsx))?;
+    let rows = sheet
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    // Days since 1899-12-30, the spreadsheet epoch.
+                    DataType::DateTime(serial) => Date::from_calendar_date(1899, Month::December, 30)
+                        .ok()
+                        .and_then(|epoch| epoch.checked_add(Duration::days(*serial as i64)))
+                        .map(format_date)
+                        .unwrap_or_default(),
+                    cell => cell.to_string(),
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+    parse_rows(rows, config, ParseStage::Xlsx)
+}
+
+// Each event's start date and description lines, as written by
+// `Catalogue::ics`.
+pub fn parse_ics(ics_data: &[u8], config: &ParserConfig) -> Result<Vec<Day>, ParseError> {
+    let ics = std::str::from_utf8(ics_data).map_err(|_| ParseError::new(ParseStage::Ics))?;
+    // Unfold continuation lines first.
+    let ics = ics.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+
+    let mut rows = Vec::new();
+    let mut event = None::<(Option<String>, Vec<String>)>;
+    for line in ics.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.split(';').next().unwrap_or_default();
+        match name {
+            "BEGIN" if value == "VEVENT" => event = Some((None, Vec::new())),
+            "END" if value == "VEVENT" => {
+                if let Some((date, dishes)) = event.take() {
+                    let date = date.ok_or(ParseError::new(ParseStage::Ics))?;
+                    rows.push([date].into_iter().chain(dishes).collect_vec());
+                }
             }
+            "DTSTART" => {
+                if let Some((date, _)) = &mut event {
+                    let value = value
+                        .get(..8)
+                        .filter(|v| v.bytes().all(|b| b.is_ascii_digit()))
+                        .ok_or(ParseError::new(ParseStage::Ics))?;
+                    *date = Some(format!("{}-{}-{}", &value[..4], &value[4..6], &value[6..]));
+                }
+            }
+            "DESCRIPTION" => {
+                if let Some((_, dishes)) = &mut event {
+                    *dishes = unescape_ics_text(value)
+                        .lines()
+                        .map(ToOwned::to_owned)
+                        .collect();
+                }
+            }
+            _ => (),
+        }
+    }
+    parse_rows(rows, config, ParseStage::Ics)
+}
+
+fn unescape_ics_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => (),
+        }
+    }
+    unescaped
+}
+
+// Rows of `date, dish, dish, ...`, optionally preceded by a header row.
+fn parse_rows(
+    mut rows: Vec<Vec<String>>,
+    config: &ParserConfig,
+    date_stage: ParseStage,
+) -> Result<Vec<Day>, ParseError> {
+    for row in &mut rows {
+        row.iter_mut().for_each(|cell| *cell = cell.trim().to_owned());
+        row.retain(|cell| !cell.is_empty());
+    }
+    rows.retain(|row| !row.is_empty());
+    if rows
+        .first()
+        .is_some_and(|row| row[0].eq_ignore_ascii_case("date"))
+    {
+        rows.remove(0);
+    }
+
+    let mut days = rows
+        .iter()
+        .filter_map(|f| Day::new(f.clone()).transpose())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ParseError {
+            candidate_columns: rows.clone(),
+            ..ParseError::new(date_stage)
+        })?;
+    days.iter_mut().for_each(|day| clean_up(day, config));
+    Ok(days)
+}
+
+pub fn parse_pdf(pdf_data: &[u8], config: &ParserConfig) -> Result<Vec<Day>, ParseError> {
+    let document =
+        Document::load_mem(pdf_data).map_err(|_| ParseError::new(ParseStage::Load))?;
+    let dimensions = DocumentDimensions::new(&document)?;
+    let layout = LayoutProfile::select(&config.layouts, dimensions).ok_or(ParseError {
+        page: Some(dimensions),
+        ..ParseError::new(ParseStage::Layout)
+    })?;
+
+    let runs = extract_text_runs(&document)?;
+    // Scanned documents have no text layer where the menu should be.
+    #[cfg(feature = "ocr")]
+    let runs = if config.ocr
+        && !runs
+            .iter()
+            .any(|run| layout.main_content_area.contains(&(run.top.round() as u32)))
+    {
+        ocr::extract_text_runs(&document, dimensions)?
+    } else {
+        runs
+    };
+
+    let mut rejected_lines = Vec::new();
+    let mut divs = runs
+        .iter()
+        .filter_map(|run| {
             let div = Div {
-                top: top_regex.captures(style)?[1].parse().ok()?,
-                left: left_regex.captures(style)?[1].parse().ok()?,
-                text: capture.get(2).unwrap().as_str(),
+                top: run.top.round() as u32,
+                left: run.left.round() as u32,
+                text: &run.text,
+                char_width: layout.expected_char_width,
             };
-            if !MAIN_CONTENT_AREA.contains(&div.top)
-                || categories.into_iter().any(|r| r.contains(&div.top))
+            if !layout.main_content_area.contains(&div.top)
+                || layout.categories_areas.iter().any(|r| r.contains(&div.top))
             {
+                rejected_lines.push(div.text.trim().to_owned());
                 return None;
             }
             Some(div)
@@ -107,18 +382,24 @@ pub fn parse_pdf(pdf_data: &[u8]) -> Result<Vec<Day>, Error> {
                 .then_some(t)
         })
         .collect::<Vec<_>>();
-    words.retain(|w| !lines_to_clear.contains(&w.top));
+    words.retain(|w| {
+        let repeating = lines_to_clear.contains(&w.top);
+        if repeating {
+            rejected_lines.push(w.text.clone());
+        }
+        !repeating
+    });
 
     // Build columns.
     let mut columns = Vec::<Vec<DishBuilder>>::with_capacity(5);
     for word in words {
         match columns.iter_mut().find(|ow| {
             ow.iter()
-                .any(|ow| ow.center().abs_diff(word.center()) < COLUMN_ALLOWED_DRIFT)
+                .any(|ow| ow.center().abs_diff(word.center()) < layout.column_allowed_drift)
         }) {
             Some(column) => {
                 // Multiline dishes.
-                if word.top - column.last().unwrap().top <= MULTILINE_DISH_MAX_DISTANCE
+                if word.top - column.last().unwrap().top <= layout.multiline_dish_max_distance
                     && word.text.chars().next().is_some_and(|c| c.is_lowercase())
                 {
                     *column.last_mut().unwrap() += word;
@@ -136,29 +417,153 @@ pub fn parse_pdf(pdf_data: &[u8]) -> Result<Vec<Day>, Error> {
             .unique_by(|d| d.text.to_lowercase())
             .collect();
     }
+    let candidate_columns = columns
+        .iter()
+        .map(|column| column.iter().map(|tg| tg.text.clone()).collect_vec())
+        .collect_vec();
     // Discard empty days.
     columns.retain(|c| c.len() >= 2);
 
     if columns.is_empty() {
-        return Err(Error::InvalidPdf);
+        return Err(ParseError {
+            page: Some(dimensions),
+            rejected_lines,
+            candidate_columns,
+            ..ParseError::new(ParseStage::Columns)
+        });
     }
 
-    columns
+    let mut days = columns
         .into_iter()
-        .filter_map(|column| Day::new(column.into_iter().map(|tg| tg.text).collect()).transpose())
-        .collect()
+        .filter_map(|column| {
+            // The first line is the date.
+            let categories = column
+                .iter()
+                .skip(1)
+                .map(|dish| layout.category(dish.top))
+                .collect_vec();
+            Day::new(column.into_iter().map(|tg| tg.text).collect())
+                .map(|day| day.map(|day| day.with_categories(categories)))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ParseError {
+            page: Some(dimensions),
+            rejected_lines,
+            candidate_columns,
+            ..ParseError::new(ParseStage::Date)
+        })?;
+    days.iter_mut().for_each(|day| clean_up(day, config));
+    Ok(days)
 }
 
-#[derive(Eq, PartialEq, Copy, Clone, Debug)]
-struct DocumentDimensions {
-    width: u32,
-    height: u32,
+// Names are normalized first, so notes are matched on the cleaned up text.
+fn clean_up(day: &mut Day, config: &ParserConfig) {
+    day.normalize(&config.normalization);
+    day.extract_notes(&config.notice_keywords);
+}
+
+fn extract_text_runs(document: &Document) -> Result<Vec<TextRun>, ParseError> {
+    let mut output = TextRunOutput::default();
+    pdf_extract::output_doc(document, &mut output)
+        .map_err(|_| ParseError::new(ParseStage::Extraction))?;
+    output.flush();
+    Ok(output.runs)
+}
+
+// Groups consecutive glyphs into runs, the same way pdf_extract's HTML output
+// does, but keeps the exact coordinates of each run.
+#[derive(Default)]
+struct TextRunOutput {
+    page_height: f64,
+    runs: Vec<TextRun>,
+    current: Option<TextRun>,
+    next_origin: (f64, f64),
+}
+
+impl TextRunOutput {
+    fn flush(&mut self) {
+        if let Some(run) = self.current.take() {
+            self.runs.push(run);
+        }
+    }
+}
+
+impl OutputDev for TextRunOutput {
+    fn begin_page(
+        &mut self,
+        _page_num: u32,
+        media_box: &MediaBox,
+        _art_box: Option<(f64, f64, f64, f64)>,
+    ) -> Result<(), OutputError> {
+        self.page_height = media_box.ury - media_box.lly;
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> Result<(), OutputError> {
+        self.flush();
+        Ok(())
+    }
+
+    fn output_character(
+        &mut self,
+        trm: &Transform,
+        width: f64,
+        spacing: f64,
+        font_size: f64,
+        char: &str,
+    ) -> Result<(), OutputError> {
+        let origin = (trm.m31, trm.m32);
+        let continues = self.current.is_some()
+            && (origin.0 - self.next_origin.0).abs() < RUN_CONTINUATION_TOLERANCE
+            && (origin.1 - self.next_origin.1).abs() < RUN_CONTINUATION_TOLERANCE;
+        if continues {
+            if let Some(run) = &mut self.current {
+                run.text += char;
+            }
+        } else {
+            self.flush();
+            self.current = Some(TextRun {
+                top: self.page_height - origin.1,
+                left: origin.0,
+                text: char.to_owned(),
+            });
+        }
+
+        let advance = width * font_size + spacing;
+        self.next_origin = (origin.0 + advance * trm.m11, origin.1 + advance * trm.m12);
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> Result<(), OutputError> {
+        self.flush();
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Eq, PartialEq, Copy, Clone, Debug)]
+pub struct DocumentDimensions {
+    pub width: u32,
+    pub height: u32,
 }
 
 impl DocumentDimensions {
-    fn new(document: &Document) -> Result<Self, Error> {
+    fn new(document: &Document) -> Result<Self, ParseError> {
         let mut matrix = document
-            .get_object(document.page_iter().next().ok_or(Error::InvalidPdf)?)?
+            .get_object(
+                document
+                    .page_iter()
+                    .next()
+                    .ok_or(ParseError::new(ParseStage::Layout))?,
+            )?
             .as_dict()?
             .get("MediaBox".as_bytes())?
             .as_array()?
@@ -166,21 +571,26 @@ impl DocumentDimensions {
             .map(|obj| match &obj {
                 Object::Integer(n) => Ok(*n as u32),
                 Object::Real(r) => Ok(*r as u32),
-                _ => Err(Error::InvalidPdf),
+                _ => Err(ParseError::new(ParseStage::Layout)),
             })
             .skip(2);
         Ok(Self {
-            width: matrix.next().ok_or(Error::InvalidPdf)??,
-            height: matrix.next().ok_or(Error::InvalidPdf)??,
+            width: matrix
+                .next()
+                .ok_or(ParseError::new(ParseStage::Layout))??,
+            height: matrix
+                .next()
+                .ok_or(ParseError::new(ParseStage::Layout))??,
         })
     }
+}
 
-    fn categories_area(&self) -> &'static [Range<u32>] {
-        CATEGORIES_AREAS
-            .into_iter()
-            .find_map(|(d, rs)| (d == self).then_some(*rs))
-            .unwrap_or(CATEGORIES_AREAS[0].1)
-    }
+// Positioned on its baseline, in page units from the top left corner.
+#[derive(Debug)]
+pub struct TextRun {
+    pub top: f64,
+    pub left: f64,
+    pub text: String,
 }
 
 #[derive(Debug)]
@@ -188,6 +598,7 @@ struct Div<'a> {
     top: u32,
     left: u32,
     text: &'a str,
+    char_width: u32,
 }
 
 #[derive(Debug)]
@@ -225,7 +636,7 @@ impl<'a> From<Div<'a>> for DishBuilder {
         DishBuilder {
             top: value.top,
             start: value.left,
-            end: value.left + text.chars().count() as u32 * EXPECTED_CHAR_WIDTH,
+            end: value.left + text.chars().count() as u32 * value.char_width,
             text,
         }
     }
@@ -234,7 +645,7 @@ impl<'a> From<Div<'a>> for DishBuilder {
 impl<'a> AddAssign<Div<'a>> for DishBuilder {
     fn add_assign(&mut self, rhs: Div<'a>) {
         self.absorb_text(rhs.text);
-        self.end = rhs.left + rhs.text.chars().count() as u32 * EXPECTED_CHAR_WIDTH;
+        self.end = rhs.left + rhs.text.chars().count() as u32 * rhs.char_width;
     }
 }
 
@@ -249,3 +660,95 @@ impl AddAssign<Self> for DishBuilder {
         self.end = self.end.max(rhs.end);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use time::macros::date;
+
+    use super::*;
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|&cell| cell.to_owned()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn parse_rows_skips_header_and_blank_cells() {
+        let days = parse_rows(
+            rows(&[
+                &["Date", "Plat", "Dessert"],
+                &[" 2023-06-12 ", "Poulet rôti", "", "Yaourt "],
+                &[],
+                &["2023-06-13", "Lasagnes"],
+            ]),
+            &ParserConfig::default(),
+            ParseStage::Csv,
+        )
+        .expect("rows parsing failed");
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date(), date!(2023 - 06 - 12));
+        assert_eq!(days[0].texts(), ["Poulet rôti", "Yaourt"]);
+        assert_eq!(days[1].date(), date!(2023 - 06 - 13));
+        assert_eq!(days[1].texts(), ["Lasagnes"]);
+    }
+
+    #[test]
+    fn parse_rows_moves_notices_to_notes() {
+        let days = parse_rows(
+            rows(&[&["2023-06-12", "Poulet rôti", "Restaurant fermé le soir"]]),
+            &ParserConfig::default(),
+            ParseStage::Csv,
+        )
+        .expect("rows parsing failed");
+        assert_eq!(days[0].dishes_ref().len(), 1);
+        assert_eq!(days[0].notes(), ["Restaurant fermé le soir"]);
+    }
+
+    #[test]
+    fn parse_rows_rejects_invalid_dates() {
+        let err = parse_rows(
+            rows(&[&["12/06/2023", "Poulet rôti"]]),
+            &ParserConfig::default(),
+            ParseStage::Csv,
+        )
+        .expect_err("invalid date accepted");
+        assert!(matches!(err.stage, ParseStage::Csv));
+        assert_eq!(err.candidate_columns, rows(&[&["12/06/2023", "Poulet rôti"]]));
+    }
+
+    #[test]
+    fn parse_csv_rows() {
+        let days = parse_csv(
+            "date,dish,dish\n2023-06-12,Carottes râpées,\"Poulet rôti, frites\"\n".as_bytes(),
+            &ParserConfig::default(),
+        )
+        .expect("csv parsing failed");
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].texts(), ["Carottes râpées", "Poulet rôti, frites"]);
+    }
+
+    #[test]
+    fn parse_ics_unfolds_and_unescapes() {
+        let ics = concat!(
+            "BEGIN:VCALENDAR\r\n",
+            "BEGIN:VEVENT\r\n",
+            "DTSTART:20230612T100000Z\r\n",
+            "DESCRIPTION:Carottes râpées\\nPoulet rô\r\n",
+            " ti\\, frites\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n",
+        );
+        let days = parse_ics(ics.as_bytes(), &ParserConfig::default()).expect("ics parsing failed");
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].date(), date!(2023 - 06 - 12));
+        assert_eq!(days[0].texts(), ["Carottes râpées", "Poulet rôti, frites"]);
+    }
+
+    #[test]
+    fn parse_ics_requires_event_dates() {
+        let ics = "BEGIN:VEVENT\r\nDESCRIPTION:Poulet rôti\r\nEND:VEVENT\r\n";
+        let err = parse_ics(ics.as_bytes(), &ParserConfig::default()).expect_err("event accepted");
+        assert!(matches!(err.stage, ParseStage::Ics));
+    }
+}